@@ -0,0 +1,66 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+/// Failures of a client call. The server's errors are mapped from the
+/// status code and the message of the response body.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The request was rejected as malformed or invalid (400).
+    #[error("{0}")]
+    BadRequest(String),
+
+    /// The API key is missing or not accepted (401).
+    #[error("{0}")]
+    Unauthorized(String),
+
+    /// The form, or the resource of the form, does not exist (404).
+    #[error("{0}")]
+    NotFound(String),
+
+    /// The service is not ready or is shutting down (503).
+    #[error("{0}")]
+    Unavailable(String),
+
+    /// Any other unsuccessful status.
+    #[error("Request failed with {status}: {message}")]
+    Api { status: StatusCode, message: String },
+
+    /// The body of a successful response could not be decoded.
+    #[error("Malformed response: {0}")]
+    Decode(String),
+
+    #[error("Invalid base URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("Invalid API key: {0}")]
+    InvalidApiKey(String),
+
+    /// The request could not be sent or its response not received.
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+impl Error {
+    pub(crate) fn from_status(status: StatusCode, message: String) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => Error::BadRequest(message),
+            StatusCode::UNAUTHORIZED => Error::Unauthorized(message),
+            StatusCode::NOT_FOUND => Error::NotFound(message),
+            StatusCode::SERVICE_UNAVAILABLE => Error::Unavailable(message),
+            status => Error::Api { status, message },
+        }
+    }
+
+    /// The HTTP status the server answered with, if it answered.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::BadRequest(_) => Some(StatusCode::BAD_REQUEST),
+            Error::Unauthorized(_) => Some(StatusCode::UNAUTHORIZED),
+            Error::NotFound(_) => Some(StatusCode::NOT_FOUND),
+            Error::Unavailable(_) => Some(StatusCode::SERVICE_UNAVAILABLE),
+            Error::Api { status, .. } => Some(*status),
+            Error::Http(e) => e.status(),
+            Error::Decode(_) | Error::InvalidUrl(_) | Error::InvalidApiKey(_) => None,
+        }
+    }
+}
@@ -0,0 +1,489 @@
+//! Async client for the form_portal HTTP API.
+//!
+//! ```no_run
+//! use form_portal_client::{models::query::ResponseQuery, Client};
+//!
+//! # async fn run() -> Result<(), form_portal_client::Error> {
+//! let client = Client::new("http://localhost:8080")?.with_api_key("fpk_...")?;
+//! for form in client.list_forms().await? {
+//!     let id = form.id.expect("stored forms have an id");
+//!     let page = client.responses(id, &ResponseQuery::default()).await?;
+//!     println!("{}: {} responses", form.name, page.total);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use bytes::Bytes;
+use futures_util::Stream;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::{RequestBuilder, Response, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+pub use form_portal_models as models;
+
+use models::{
+    query::{ExportQuery, ImportQuery, ResponseQuery, SearchQuery, SummaryQuery},
+    search::SearchHit,
+    summary::FormSummary,
+    ApiResponse, Created, FormResponse, FormSchema, ImportedForm, NotificationSettings,
+};
+
+mod error;
+mod sse;
+
+pub use error::Error;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+const TOTAL_COUNT_HEADER: &str = "X-Total-Count";
+const PAGE_SIZE: usize = 500;
+/// The largest `limit` the server accepts.
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// One page of a listing, with the number of items across all pages.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub offset: usize,
+}
+
+impl<T> Page<T> {
+    /// Whether items past this page remain.
+    pub fn has_more(&self) -> bool {
+        self.offset + self.items.len() < self.total
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: Url,
+    /// `Authorization` header sent with every request; hidden from `Debug`.
+    authorization: Option<HeaderValue>,
+}
+
+impl Client {
+    /// A client for the service at `base_url`, e.g. `http://localhost:8080`.
+    pub fn new(base_url: &str) -> Result<Self> {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Like [`Client::new`], sending requests through the given client, e.g.
+    /// one configured with timeouts or default headers.
+    pub fn with_http_client(base_url: &str, http: reqwest::Client) -> Result<Self> {
+        let mut base_url = Url::parse(base_url).map_err(|e| Error::InvalidUrl(e.to_string()))?;
+        if base_url.cannot_be_a_base() {
+            return Err(Error::InvalidUrl(base_url.to_string()));
+        }
+        // Keep any path prefix when joining relative paths onto it
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        Ok(Self {
+            http,
+            base_url,
+            authorization: None,
+        })
+    }
+
+    /// Sends `key` as a bearer token with every request, as the management
+    /// routes need when the service requires API keys.
+    pub fn with_api_key(mut self, key: &str) -> Result<Self> {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", key))
+            .map_err(|_| Error::InvalidApiKey("keys are printable ASCII".to_string()))?;
+        value.set_sensitive(true);
+        self.authorization = Some(value);
+        Ok(self)
+    }
+
+    pub async fn create_form(&self, schema: &FormSchema) -> Result<Uuid> {
+        let created: Created = self.call(self.http.post(self.url("api/forms")?).json(schema)).await?;
+        Ok(created.id)
+    }
+
+    /// Converts a JSON Schema of an object into a form and, unless
+    /// `dry_run` is set, registers it.
+    pub async fn import_json_schema(&self, document: &serde_json::Value, dry_run: bool) -> Result<ImportedForm> {
+        let query = ImportQuery { dry_run: Some(dry_run) };
+        self.call(self.http.post(self.url("api/forms/import/json-schema")?).query(&query).json(document))
+            .await
+    }
+
+    pub async fn list_forms(&self) -> Result<Vec<FormSchema>> {
+        self.call(self.http.get(self.url("api/forms")?)).await
+    }
+
+    pub async fn get_form(&self, id: Uuid) -> Result<FormSchema> {
+        self.call(self.http.get(self.form_url(id, "")?)).await
+    }
+
+    /// A JSON Schema of the `data` of valid responses to the form.
+    pub async fn response_json_schema(&self, id: Uuid) -> Result<serde_json::Value> {
+        let response = self.send(self.http.get(self.form_url(id, "/schema.json")?)).await?;
+        Ok(response.json().await?)
+    }
+
+    /// A TypeScript interface of the `data` of valid responses to the form.
+    pub async fn response_typescript(&self, id: Uuid) -> Result<String> {
+        let response = self.send(self.http.get(self.form_url(id, "/types.ts")?)).await?;
+        Ok(response.text().await?)
+    }
+
+    /// The form as an HTML page.
+    pub async fn render_form(&self, id: Uuid) -> Result<String> {
+        let response = self.send(self.http.get(self.form_url(id, "/render")?)).await?;
+        Ok(response.text().await?)
+    }
+
+    /// Submits answers keyed by field id, as the rendered form would, and
+    /// returns the id of the stored response.
+    pub async fn submit<A: Serialize + ?Sized>(&self, id: Uuid, answers: &A) -> Result<Uuid> {
+        let created: Created = self.call(self.http.post(self.form_url(id, "/submit")?).form(answers)).await?;
+        Ok(created.id)
+    }
+
+    /// One page of the responses matching the query.
+    pub async fn responses(&self, id: Uuid, query: &ResponseQuery) -> Result<Page<FormResponse>> {
+        let response = self.send(self.http.get(self.form_url(id, "/responses")?).query(query)).await?;
+        let total = response
+            .headers()
+            .get(TOTAL_COUNT_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        let items: Vec<FormResponse> = unwrap_envelope(response).await?;
+        Ok(Page {
+            total: total.unwrap_or(items.len()),
+            offset: query.offset.unwrap_or(0),
+            items,
+        })
+    }
+
+    /// Every response matching the query, fetched page by page. A `limit` in
+    /// the query sets the page size, up to the 1000 the server allows.
+    pub async fn all_responses(&self, id: Uuid, query: &ResponseQuery) -> Result<Vec<FormResponse>> {
+        let mut query = ResponseQuery {
+            limit: Some(query.limit.unwrap_or(PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)),
+            ..query.clone()
+        };
+        let mut all = Vec::new();
+        loop {
+            let page = self.responses(id, &query).await?;
+            let has_more = page.has_more() && !page.items.is_empty();
+            query.offset = Some(page.offset + page.items.len());
+            all.extend(page.items);
+            if !has_more {
+                return Ok(all);
+            }
+        }
+    }
+
+    /// Live responses as they are submitted. With `last_event_id`, the
+    /// responses stored after that one are replayed first.
+    pub async fn stream_responses(
+        &self,
+        id: Uuid,
+        last_event_id: Option<Uuid>,
+    ) -> Result<impl Stream<Item = Result<FormResponse>>> {
+        let mut request = self.http.get(self.form_url(id, "/responses/stream")?);
+        if let Some(last_event_id) = last_event_id {
+            request = request.header("Last-Event-ID", last_event_id.to_string());
+        }
+        let response = self.send(request).await?;
+        Ok(sse::responses(Box::pin(response.bytes_stream())))
+    }
+
+    pub async fn search_responses(&self, id: Uuid, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        self.call(self.http.get(self.form_url(id, "/responses/search")?).query(query)).await
+    }
+
+    /// The responses as a CSV or NDJSON file.
+    pub async fn export_responses(&self, id: Uuid, query: &ExportQuery) -> Result<Bytes> {
+        let response = self.send(self.http.get(self.form_url(id, "/responses/export")?).query(query)).await?;
+        Ok(response.bytes().await?)
+    }
+
+    pub async fn summary(&self, id: Uuid, query: &SummaryQuery) -> Result<FormSummary> {
+        self.call(self.http.get(self.form_url(id, "/summary")?).query(query)).await
+    }
+
+    pub async fn notification_settings(&self, id: Uuid) -> Result<NotificationSettings> {
+        self.call(self.http.get(self.form_url(id, "/notifications")?)).await
+    }
+
+    pub async fn set_notification_settings(
+        &self,
+        id: Uuid,
+        settings: &NotificationSettings,
+    ) -> Result<NotificationSettings> {
+        self.call(self.http.put(self.form_url(id, "/notifications")?).json(settings)).await
+    }
+
+    /// The service's OpenAPI document.
+    pub async fn openapi(&self) -> Result<serde_json::Value> {
+        let response = self.send(self.http.get(self.url("api/openapi.json")?)).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Succeeds when the service is ready to serve traffic.
+    pub async fn ready(&self) -> Result<()> {
+        self.send(self.http.get(self.url("readyz")?)).await?;
+        Ok(())
+    }
+
+    fn url(&self, path: &str) -> Result<Url> {
+        self.base_url.join(path).map_err(|e| Error::InvalidUrl(e.to_string()))
+    }
+
+    fn form_url(&self, id: Uuid, suffix: &str) -> Result<Url> {
+        self.url(&format!("api/forms/{}{}", id, suffix))
+    }
+
+    /// Sends the request, turning unsuccessful statuses into errors.
+    async fn send(&self, mut request: RequestBuilder) -> Result<Response> {
+        if let Some(authorization) = &self.authorization {
+            request = request.header(AUTHORIZATION, authorization.clone());
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.bytes().await.unwrap_or_default();
+        // Errors are bare JSON strings, except for the `ApiResponse` of `/readyz`
+        let message = serde_json::from_slice::<String>(&body)
+            .ok()
+            .or_else(|| serde_json::from_slice::<ApiResponse<serde_json::Value>>(&body).ok()?.error)
+            .unwrap_or_else(|| status.canonical_reason().unwrap_or("Unknown error").to_string());
+        Err(Error::from_status(status, message))
+    }
+
+    /// Sends the request and unwraps the `data` of its JSON envelope.
+    async fn call<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        unwrap_envelope(self.send(request).await?).await
+    }
+}
+
+async fn unwrap_envelope<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let status = response.status();
+    let body = response.bytes().await?;
+    let envelope: ApiResponse<T> = serde_json::from_slice(&body).map_err(|e| Error::Decode(e.to_string()))?;
+    match envelope.data {
+        Some(data) if envelope.success => Ok(data),
+        _ => Err(Error::from_status(
+            status,
+            envelope.error.unwrap_or_else(|| "Response carried no data".to_string()),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use reqwest::StatusCode;
+    use std::sync::{Arc, Mutex};
+
+    /// A request as the mock server received it.
+    #[derive(Debug)]
+    struct Received {
+        method: String,
+        uri: String,
+        content_type: Option<String>,
+        authorization: Option<String>,
+        body: String,
+    }
+
+    type Log = Arc<Mutex<Vec<Received>>>;
+
+    /// Starts a server answering every request with `respond`, and returns a
+    /// client for it, whose base URL has a path that requests must keep.
+    fn serve<F>(respond: F) -> (Client, Log)
+    where
+        F: Fn(&HttpRequest) -> HttpResponse + Clone + Send + 'static,
+    {
+        let log = Log::default();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = HttpServer::new({
+            let log = log.clone();
+            move || {
+                let (log, respond) = (log.clone(), respond.clone());
+                App::new().default_service(web::to(move |req: HttpRequest, body: web::Bytes| {
+                    let (log, respond) = (log.clone(), respond.clone());
+                    async move {
+                        let header = |name| req.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+                        log.lock().unwrap().push(Received {
+                            method: req.method().to_string(),
+                            uri: req.uri().to_string(),
+                            content_type: header("content-type"),
+                            authorization: header("authorization"),
+                            body: String::from_utf8_lossy(&body).into_owned(),
+                        });
+                        respond(&req)
+                    }
+                }))
+            }
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+
+        (Client::new(&format!("http://{}/portal", addr)).unwrap(), log)
+    }
+
+    fn ok<T: Serialize>(data: T) -> HttpResponse {
+        HttpResponse::Ok().json(ApiResponse::success(data))
+    }
+
+    fn schema() -> FormSchema {
+        FormSchema {
+            id: None,
+            name: "Feedback".to_string(),
+            description: None,
+            fields: Vec::new(),
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn stored(index: usize) -> FormResponse {
+        FormResponse {
+            id: Some(Uuid::from_u128(index as u128)),
+            form_id: Uuid::nil(),
+            data: [("index".to_string(), serde_json::json!(index))].into(),
+            created_at: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn builds_requests_under_the_base_path() {
+        let id = Uuid::from_u128(7);
+        let (client, log) = serve(move |req| match *req.method() {
+            actix_web::http::Method::POST => HttpResponse::Created().json(ApiResponse::success(Created {
+                id,
+                message: "Stored".to_string(),
+            })),
+            _ => ok(Vec::<SearchHit>::new()),
+        });
+
+        assert_eq!(client.create_form(&schema()).await.unwrap(), id);
+        assert_eq!(client.submit(id, &[("name", "Jane Doe"), ("rating", "5")]).await.unwrap(), id);
+        let query = SearchQuery { q: "late delivery".to_string(), limit: Some(5) };
+        assert!(client.search_responses(id, &query).await.unwrap().is_empty());
+
+        let log = log.lock().unwrap();
+        assert_eq!((log[0].method.as_str(), log[0].uri.as_str()), ("POST", "/portal/api/forms"));
+        assert_eq!(log[0].content_type.as_deref(), Some("application/json"));
+        let sent: FormSchema = serde_json::from_str(&log[0].body).unwrap();
+        assert_eq!(sent.name, "Feedback");
+
+        assert_eq!(log[1].uri, format!("/portal/api/forms/{}/submit", id));
+        assert_eq!(log[1].content_type.as_deref(), Some("application/x-www-form-urlencoded"));
+        assert_eq!(log[1].body, "name=Jane+Doe&rating=5");
+
+        assert_eq!(log[2].method, "GET");
+        assert_eq!(log[2].uri, format!("/portal/api/forms/{}/responses/search?q=late+delivery&limit=5", id));
+        assert!(log.iter().all(|r| r.authorization.is_none()));
+    }
+
+    #[actix_web::test]
+    async fn fetches_every_page() {
+        // The total claims one more response than there is, as when one is
+        // deleted while paging; paging stops at the first empty page
+        let (client, log) = serve(|req| {
+            let query = web::Query::<ResponseQuery>::from_query(req.query_string()).unwrap();
+            let offset = query.offset.unwrap_or(0).min(5);
+            let end = (offset + query.limit.unwrap_or(5)).min(5);
+            HttpResponse::Ok()
+                .insert_header((TOTAL_COUNT_HEADER, "6"))
+                .json(ApiResponse::success((offset..end).map(stored).collect::<Vec<_>>()))
+        });
+        let form_id = Uuid::nil();
+
+        let query = ResponseQuery {
+            filter: Some("rating >= 4".to_string()),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = client.responses(form_id, &query).await.unwrap();
+        assert_eq!((page.items.len(), page.total, page.offset), (2, 6, 0));
+        assert!(page.has_more());
+
+        let all = client.all_responses(form_id, &query).await.unwrap();
+        assert_eq!(all.iter().map(|r| r.data["index"].as_u64().unwrap()).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+
+        let log = log.lock().unwrap();
+        let sent: Vec<ResponseQuery> = log[1..]
+            .iter()
+            .map(|r| web::Query::<ResponseQuery>::from_query(r.uri.split_once('?').unwrap().1).unwrap().into_inner())
+            .collect();
+        assert_eq!(sent.iter().map(|q| q.offset.unwrap_or(0)).collect::<Vec<_>>(), vec![0, 2, 4, 5]);
+        assert!(sent.iter().all(|q| q.filter.as_deref() == Some("rating >= 4") && q.limit == Some(2)));
+    }
+
+    #[actix_web::test]
+    async fn maps_error_statuses() {
+        let (client, _) = serve(|req| match req.path().rsplit('/').next() {
+            Some("00000000-0000-0000-0000-000000000001") => {
+                HttpResponse::BadRequest().json("Validation error: name: is required")
+            }
+            Some("00000000-0000-0000-0000-000000000002") => HttpResponse::NotFound().json("Not found: no such form"),
+            Some("00000000-0000-0000-0000-000000000003") => {
+                HttpResponse::ServiceUnavailable().json(ApiResponse::<()>::error("Shutting down".to_string()))
+            }
+            Some("00000000-0000-0000-0000-000000000004") => HttpResponse::InternalServerError().body("boom"),
+            _ => HttpResponse::Ok().body("not json"),
+        });
+        let get = |n: u128| client.get_form(Uuid::from_u128(n));
+
+        let error = get(1).await.unwrap_err();
+        assert!(matches!(&error, Error::BadRequest(m) if m == "Validation error: name: is required"), "{:?}", error);
+        assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST));
+
+        let error = get(2).await.unwrap_err();
+        assert!(matches!(&error, Error::NotFound(m) if m == "Not found: no such form"), "{:?}", error);
+
+        let error = get(3).await.unwrap_err();
+        assert!(matches!(&error, Error::Unavailable(m) if m == "Shutting down"), "{:?}", error);
+
+        let error = get(4).await.unwrap_err();
+        assert!(
+            matches!(&error, Error::Api { status, message } if *status == StatusCode::INTERNAL_SERVER_ERROR && message == "Internal Server Error"),
+            "{:?}",
+            error
+        );
+
+        let error = get(5).await.unwrap_err();
+        assert!(matches!(error, Error::Decode(_)), "{:?}", error);
+        assert_eq!(error.status(), None);
+    }
+
+    #[actix_web::test]
+    async fn sends_the_api_key() {
+        let (client, log) = serve(|req| match req.headers().get("authorization") {
+            Some(value) if value == "Bearer fpk_secret" => ok(Vec::<FormSchema>::new()),
+            _ => HttpResponse::Unauthorized().json(ApiResponse::<()>::error("A valid API key is required".to_string())),
+        });
+
+        let error = client.list_forms().await.unwrap_err();
+        assert!(matches!(&error, Error::Unauthorized(m) if m == "A valid API key is required"), "{:?}", error);
+        assert_eq!(error.status(), Some(StatusCode::UNAUTHORIZED));
+
+        let client = client.with_api_key("fpk_secret").unwrap();
+        assert!(client.list_forms().await.unwrap().is_empty());
+        assert!(!format!("{:?}", client).contains("fpk_secret"));
+
+        let log = log.lock().unwrap();
+        assert_eq!(log[0].authorization, None);
+        assert_eq!(log[1].authorization.as_deref(), Some("Bearer fpk_secret"));
+
+        assert!(matches!(client.with_api_key("fpk\nsecret"), Err(Error::InvalidApiKey(_))));
+    }
+}
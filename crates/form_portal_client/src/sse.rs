@@ -0,0 +1,86 @@
+//! Decoding of the Server-Sent Events sent by
+//! `GET /api/forms/{id}/responses/stream`.
+
+use bytes::Bytes;
+use futures_util::{stream, Stream, StreamExt};
+
+use crate::{models::FormResponse, Error, Result};
+
+/// Turns the raw event stream into the responses it announces. Comments,
+/// `retry` hints and events of other types are skipped.
+pub(crate) fn responses<S>(body: S) -> impl Stream<Item = Result<FormResponse>>
+where
+    S: Stream<Item = reqwest::Result<Bytes>> + Unpin,
+{
+    stream::unfold((body, Vec::new()), |(mut body, mut buffer)| async move {
+        loop {
+            if let Some(end) = find_event_end(&buffer) {
+                let block: Vec<u8> = buffer.drain(..end).collect();
+                if let Some(response) = decode_event(&block) {
+                    return Some((response, (body, buffer)));
+                }
+                continue;
+            }
+            match body.next().await {
+                Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                Some(Err(e)) => return Some((Err(Error::Http(e)), (body, buffer))),
+                None => return None,
+            }
+        }
+    })
+}
+
+/// End of the first complete event in `buffer`, including its blank line.
+fn find_event_end(buffer: &[u8]) -> Option<usize> {
+    let lf = buffer.windows(2).position(|w| w == b"\n\n").map(|i| i + 2);
+    let crlf = buffer.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4);
+    match (lf, crlf) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn decode_event(block: &[u8]) -> Option<Result<FormResponse>> {
+    let text = String::from_utf8_lossy(block);
+
+    let mut event = "message";
+    let mut data = Vec::new();
+    for line in text.lines() {
+        let (name, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match name {
+            "event" => event = value,
+            "data" => data.push(value),
+            _ => {}
+        }
+    }
+
+    if event != "response" || data.is_empty() {
+        return None;
+    }
+    Some(serde_json::from_str(&data.join("\n")).map_err(|e| Error::Decode(e.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_events_split_across_chunks() {
+        let event = concat!(
+            "id: 6a2f2d63-3c1a-4f6e-9d0e-3f5b8c1a2b3c\n",
+            "event: response\n",
+            "data: {\"id\":\"6a2f2d63-3c1a-4f6e-9d0e-3f5b8c1a2b3c\",",
+            "\"form_id\":\"0b8f4a1e-58d2-4d0c-9f3e-2a1b7c6d5e4f\",",
+            "\"data\":{\"name\":\"Jane\"},\"created_at\":null}\n\n",
+        );
+        let body = format!("retry: 3000\n\n: keepalive\n\n{}", event);
+        let (head, tail) = body.split_at(body.len() / 2);
+        let chunks = vec![Ok(Bytes::from(head.to_string())), Ok(Bytes::from(tail.to_string()))];
+
+        let responses: Vec<_> = futures::executor::block_on(responses(stream::iter(chunks)).collect());
+        assert_eq!(responses.len(), 1);
+        let response = responses[0].as_ref().unwrap();
+        assert_eq!(response.data["name"], "Jane");
+    }
+}
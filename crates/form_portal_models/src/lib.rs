@@ -0,0 +1,206 @@
+//! Types exchanged with the form_portal HTTP API, shared by the server and
+//! its clients. Enable the `openapi` feature to derive their OpenAPI schemas.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub mod query;
+pub mod search;
+pub mod summary;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum FieldType {
+    Text,
+    Number,
+    Email,
+    Date,
+    Checkbox,
+    Select,
+    Radio,
+    Textarea,
+    /// A phone number, stored in E.164 form such as `+4930123456`.
+    Phone,
+    /// An absolute `http` or `https` URL.
+    Url,
+    /// A time of day, stored as `HH:MM:SS`.
+    Time,
+    /// A date and time, stored as RFC 3339 with the respondent's UTC offset.
+    DateTime,
+    /// A slider between `min` and `max` (0 and 100 by default) in steps of `step`.
+    Range,
+    /// A color, stored as `#rrggbb`.
+    Color,
+    /// Any number of `options`, stored as an array of their values.
+    MultiSelect,
+    /// `options` put in order of preference, stored as an array of their
+    /// values, most preferred first.
+    Ranking,
+    /// A grid asking for one of the `options` (several when `multiple`) per
+    /// item of `rows`, stored as an object keyed by row id.
+    Matrix,
+    /// Stars from 1 to `max` (5 by default, at most 10), stored as a whole number.
+    Rating,
+    /// Net Promoter Score question, "How likely are you to recommend ...?",
+    /// answered from 0 to 10 and stored as a whole number.
+    Nps,
+    /// A set of `fields` answered once per item, such as one per dependent,
+    /// stored as an array of objects keyed by field id.
+    Group,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FieldOption {
+    pub value: String,
+    pub label: String,
+}
+
+/// A statement or question of a `Matrix` field.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MatrixRow {
+    pub id: String,
+    pub label: String,
+    /// Whether the row must be answered; the field's `required` unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FormField {
+    pub id: String,
+    pub label: String,
+    pub field_type: FieldType,
+    pub required: bool,
+    pub placeholder: Option<String>,
+    pub options: Option<Vec<FieldOption>>,
+    pub validation: Option<String>,
+    /// Smallest accepted value of a `Number` or `Range` field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Largest accepted value of a `Number` or `Range` field, or the number
+    /// of stars of a `Rating` field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Increment of a `Range` field, counted from `min`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<f64>,
+    /// Fewest options a `MultiSelect` or `Ranking` answer may hold; one
+    /// for required fields unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_selections: Option<usize>,
+    /// Most options a `MultiSelect` or `Ranking` answer may hold; all of
+    /// them unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_selections: Option<usize>,
+    /// Items of a `Matrix` field; its `options` are the shared columns.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rows: Option<Vec<MatrixRow>>,
+    /// Whether each row of a `Matrix` field takes several options.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub multiple: bool,
+    /// Fields of each item of a `Group` field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(no_recursion))]
+    pub fields: Option<Vec<FormField>>,
+    /// Fewest items of a `Group` field; one for required fields unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_items: Option<usize>,
+    /// Most items of a `Group` field; unlimited unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FormSchema {
+    pub id: Option<Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    pub fields: Vec<FormField>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FormResponse {
+    pub id: Option<Uuid>,
+    pub form_id: Uuid,
+    pub data: HashMap<String, serde_json::Value>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NotificationSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    pub subject_template: Option<String>,
+    #[serde(default = "default_true")]
+    pub include_answers: bool,
+    #[serde(default)]
+    pub send_respondent_copy: bool,
+    pub respondent_email_field: Option<String>,
+}
+
+/// Returned when a form or a submission was stored.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Created {
+    pub id: Uuid,
+    pub message: String,
+}
+
+/// A form converted from a JSON Schema, with the parts of the schema that
+/// could not be converted. `id` is unset on a dry run.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportedForm {
+    pub id: Option<Uuid>,
+    pub schema: FormSchema,
+    /// One entry per unsupported construct, prefixed with its JSON pointer.
+    pub warnings: Vec<String>,
+}
+
+/// Body of a failed request: the error message as a bare JSON string,
+/// such as `"Not found: Form not found"`.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorResponse(pub String);
+
+fn default_true() -> bool {
+    true
+}
+
+/// Envelope of every JSON response; `data` is set on success, `error` otherwise.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn success(data: T) -> Self {
+        Self {
+            success: true,
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    pub fn error(message: String) -> Self {
+        Self {
+            success: false,
+            data: None,
+            error: Some(message),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Query parameters accepted by the listing, search, summary, export and
+//! import endpoints.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ResponseQuery {
+    /// Filter expression, e.g. `rating <= 2 and created_at >= now-7d`
+    pub filter: Option<String>,
+    /// Field to sort by, prefixed with `-` for descending order
+    pub sort: Option<String>,
+    /// Maximum number of responses to return, at most 1000 (default: all)
+    pub limit: Option<usize>,
+    /// Number of matching responses to skip
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct SearchQuery {
+    /// Full-text query over the free-text answers
+    pub q: String,
+    /// Maximum number of hits, at most 100 (default 20)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct SummaryQuery {
+    /// Only count responses created at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Only count responses created before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Bucket size of date histograms (default `day`)
+    pub interval: Option<DateInterval>,
+    /// Number of most frequent text answers to list (default 10)
+    pub top: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum DateInterval {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ExportQuery {
+    /// File format of the export (default `csv`)
+    pub format: Option<ExportFormat>,
+    /// Filter expression, as for listing responses
+    pub filter: Option<String>,
+    /// Field to sort by, prefixed with `-` for descending order
+    pub sort: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One row per response, one column per field.
+    #[default]
+    Csv,
+    /// One JSON-encoded `FormResponse` per line.
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ImportQuery {
+    /// Only convert the schema, without registering the form
+    pub dry_run: Option<bool>,
+}
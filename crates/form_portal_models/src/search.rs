@@ -0,0 +1,16 @@
+//! Hits returned by `GET /api/forms/{id}/responses/search`.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchHit {
+    pub response_id: Uuid,
+    pub score: f32,
+    pub snippet: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub data: Value,
+}
@@ -0,0 +1,174 @@
+//! Per-field statistics returned by `GET /api/forms/{id}/summary`.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::FieldType;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FormSummary {
+    pub form_id: Uuid,
+    pub total_responses: usize,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub fields: Vec<FieldSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FieldSummary {
+    pub field_id: String,
+    pub label: String,
+    pub field_type: FieldType,
+    pub responses: usize,
+    pub blanks: usize,
+    pub stats: FieldStats,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FieldStats {
+    Options {
+        frequencies: Vec<OptionCount>,
+        other: usize,
+    },
+    Number {
+        count: usize,
+        invalid: usize,
+        min: Option<f64>,
+        max: Option<f64>,
+        mean: Option<f64>,
+        median: Option<f64>,
+        percentiles: Option<Percentiles>,
+    },
+    Date {
+        invalid: usize,
+        earliest: Option<NaiveDate>,
+        latest: Option<NaiveDate>,
+        histogram: Vec<DateBucket>,
+    },
+    Text {
+        distinct: usize,
+        top_values: Vec<ValueCount>,
+    },
+    /// Options by `score`, highest first.
+    Ranking {
+        rankings: Vec<OptionRank>,
+        other: usize,
+    },
+    /// The distribution of answers per row, in row order.
+    Matrix {
+        rows: Vec<MatrixRowStats>,
+    },
+    Rating {
+        count: usize,
+        invalid: usize,
+        /// Number of stars of the scale.
+        max: u32,
+        mean: Option<f64>,
+        /// Count per number of stars, from 1 to `max`.
+        distribution: Vec<ScoreCount>,
+    },
+    /// Promoters answered 9 or 10, passives 7 or 8 and detractors 0 to 6.
+    /// `score` is the percentage of promoters minus that of detractors.
+    Nps {
+        count: usize,
+        invalid: usize,
+        score: Option<f64>,
+        promoters: usize,
+        passives: usize,
+        detractors: usize,
+        /// Count per answer, from 0 to 10.
+        distribution: Vec<ScoreCount>,
+        /// The same breakdown per interval of submission time, oldest first.
+        trend: Vec<NpsBucket>,
+    },
+    /// `fields` summarize the answers of every item of every response.
+    Group {
+        items: usize,
+        mean_items: Option<f64>,
+        max_items: usize,
+        #[cfg_attr(feature = "openapi", schema(no_recursion))]
+        fields: Vec<FieldSummary>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OptionCount {
+    pub value: String,
+    pub label: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MatrixRowStats {
+    pub row_id: String,
+    pub label: String,
+    pub responses: usize,
+    pub frequencies: Vec<OptionCount>,
+    pub other: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ScoreCount {
+    pub score: u32,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NpsBucket {
+    pub start: NaiveDate,
+    pub count: usize,
+    pub score: f64,
+    pub promoters: usize,
+    pub passives: usize,
+    pub detractors: usize,
+}
+
+/// How an option of a `Ranking` field was ranked.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OptionRank {
+    pub value: String,
+    pub label: String,
+    /// Answers that ranked the option at all.
+    pub ranked: usize,
+    /// Answers that ranked the option first.
+    pub first_choice: usize,
+    /// Average position among the answers that ranked it, 1 being first.
+    pub mean_rank: Option<f64>,
+    /// Borda count: with `n` options, `n` points per first place, `n - 1`
+    /// per second place, and so on.
+    pub score: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ValueCount {
+    pub value: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DateBucket {
+    pub start: NaiveDate,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Percentiles {
+    pub p25: f64,
+    pub p75: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
@@ -1,138 +1,1084 @@
 // src/api.rs
-use actix_web::{get, post, web, HttpResponse, Responder};
-use serde_json::json;
-use std::collections::HashMap;
-use std::sync::Arc;
+use actix_web::{get, post, put, web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::{
-    db,
+    definition,
     error::AppError,
-    models::{ApiResponse, FormResponse, FormSchema},
-    templates,
+    events::{self, ResponseEvents},
+    export,
+    jsonschema,
+    filter::ResponseFilter,
+    metrics::METRICS,
+    models::{
+        query::{ExportFormat, ExportQuery, ImportQuery, ResponseQuery, SearchQuery, SummaryQuery},
+        search::SearchHit,
+        summary::FormSummary,
+        ApiResponse, Created, ErrorResponse, FormResponse, FormSchema, ImportedForm, NotificationSettings,
+    },
+    notifications::{self, Notifier},
+    openapi,
+    search::SearchIndex,
+    store::FormStore,
+    summary,
+    templates::FormRenderer,
+    typescript,
+    validation,
 };
 
+/// Number of responses matching the filter, before `limit` and `offset`.
+const TOTAL_COUNT_HEADER: &str = "X-Total-Count";
+
+/// Most responses a page may be asked for with `limit`.
+const MAX_PAGE_SIZE: usize = 1000;
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
             .service(register_form_schema)
+            .service(import_json_schema)
+            .service(list_forms)
             .service(get_form_by_id)
+            .service(get_response_json_schema)
+            .service(get_response_typescript)
             .service(render_form)
             .service(submit_form)
-            .service(get_form_responses),
+            .service(get_form_responses)
+            .service(stream_form_responses)
+            .service(export_form_responses)
+            .service(search_form_responses)
+            .service(get_form_summary)
+            .service(get_notification_settings)
+            .service(update_notification_settings)
+            .service(openapi::spec)
+            .service(openapi::docs),
     );
 }
 
+#[utoipa::path(
+    tag = "forms",
+    request_body(
+        description = "The form, as JSON, YAML or TOML",
+        content((FormSchema = "application/json"), (FormSchema = "application/yaml"), (FormSchema = "application/toml")),
+    ),
+    responses(
+        (status = 201, description = "Form registered", body = ApiResponse<Created>),
+        (status = 400, description = "Malformed schema or unsupported content type", body = ErrorResponse),
+    ),
+)]
 #[post("/forms")]
 async fn register_form_schema(
-    session: web::Data<Arc<scylla::Session>>,
-    form_schema: web::Json<FormSchema>,
+    store: web::Data<dyn FormStore>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> Result<impl Responder, AppError> {
+    let format = definition::Format::from_content_type(req.content_type()).ok_or_else(|| {
+        AppError::BadRequest(format!(
+            "Unsupported content type {}; send JSON, YAML or TOML",
+            req.content_type()
+        ))
+    })?;
+    let text = std::str::from_utf8(&body)
+        .map_err(|_| AppError::BadRequest("The form schema is not valid UTF-8".to_string()))?;
+    let form_schema = definition::parse(text, format)?;
+    definition::validate(&form_schema)?;
+
+    let form_id = store.create_form_schema(form_schema).await?;
+
+    Ok(HttpResponse::Created().json(ApiResponse::success(Created {
+        id: form_id,
+        message: "Form schema registered successfully".to_string(),
+    })))
+}
+
+#[utoipa::path(
+    tag = "forms",
+    params(ImportQuery),
+    request_body(content = Object, description = "A JSON Schema (draft 2020-12) of `\"type\": \"object\"`"),
+    responses(
+        (status = 201, description = "Form converted and registered", body = ApiResponse<ImportedForm>),
+        (status = 200, description = "Form converted, on a dry run", body = ApiResponse<ImportedForm>),
+        (status = 400, description = "Not an object schema, or no property could be converted", body = ErrorResponse),
+    ),
+)]
+#[post("/forms/import/json-schema")]
+async fn import_json_schema(
+    store: web::Data<dyn FormStore>,
+    query: web::Query<ImportQuery>,
+    document: web::Json<serde_json::Value>,
 ) -> Result<impl Responder, AppError> {
-    let form_id = db::create_form_schema(&session, form_schema.into_inner()).await?;
-    
-    Ok(HttpResponse::Created().json(ApiResponse::success(json!({
-        "id": form_id,
-        "message": "Form schema registered successfully"
-    }))))
+    let (schema, warnings) = jsonschema::import(&document)?;
+    definition::validate(&schema)?;
+
+    if query.dry_run.unwrap_or(false) {
+        return Ok(HttpResponse::Ok().json(ApiResponse::success(ImportedForm { id: None, schema, warnings })));
+    }
+
+    let form_id = store.create_form_schema(schema.clone()).await?;
+    Ok(HttpResponse::Created().json(ApiResponse::success(ImportedForm {
+        id: Some(form_id),
+        schema,
+        warnings,
+    })))
+}
+
+#[utoipa::path(
+    tag = "forms",
+    responses((status = 200, description = "Every registered form", body = ApiResponse<Vec<FormSchema>>)),
+)]
+#[get("/forms")]
+async fn list_forms(store: web::Data<dyn FormStore>) -> Result<impl Responder, AppError> {
+    let mut forms = Vec::new();
+    for id in store.list_form_ids().await? {
+        forms.push(store.get_form_schema(id).await?);
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(forms)))
 }
 
+#[utoipa::path(
+    tag = "forms",
+    params(("id" = Uuid, Path, description = "Form id")),
+    responses(
+        (status = 200, description = "The form schema", body = ApiResponse<FormSchema>),
+        (status = 400, description = "Malformed form id", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
 #[get("/forms/{id}")]
 async fn get_form_by_id(
-    session: web::Data<Arc<scylla::Session>>,
+    store: web::Data<dyn FormStore>,
     path: web::Path<String>,
 ) -> Result<impl Responder, AppError> {
     let id = Uuid::parse_str(&path.into_inner())
         .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
-    
-    let schema = db::get_form_schema(&session, id).await?;
-    
+
+    let schema = store.get_form_schema(id).await?;
+
     Ok(HttpResponse::Ok().json(ApiResponse::success(schema)))
 }
 
+#[utoipa::path(
+    tag = "forms",
+    params(("id" = Uuid, Path, description = "Form id")),
+    responses(
+        (status = 200, description = "JSON Schema (draft 2020-12) of the `data` of valid responses", body = Object, content_type = "application/schema+json"),
+        (status = 400, description = "Malformed form id", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
+#[get("/forms/{id}/schema.json")]
+async fn get_response_json_schema(
+    store: web::Data<dyn FormStore>,
+    path: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let id = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
+
+    let schema = store.get_form_schema(id).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/schema+json")
+        .json(jsonschema::export(&schema)))
+}
+
+#[utoipa::path(
+    tag = "forms",
+    params(("id" = Uuid, Path, description = "Form id")),
+    responses(
+        (status = 200, description = "TypeScript interface of the `data` of valid responses", body = String, content_type = "application/typescript"),
+        (status = 400, description = "Malformed form id", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
+#[get("/forms/{id}/types.ts")]
+async fn get_response_typescript(
+    store: web::Data<dyn FormStore>,
+    path: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let id = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
+
+    let schema = store.get_form_schema(id).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/typescript; charset=utf-8")
+        .body(typescript::interface(&schema)))
+}
+
+#[utoipa::path(
+    tag = "forms",
+    params(("id" = Uuid, Path, description = "Form id")),
+    responses(
+        (status = 200, description = "The form as an HTML page", body = String, content_type = "text/html"),
+        (status = 400, description = "Malformed form id", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
 #[get("/forms/{id}/render")]
 async fn render_form(
-    session: web::Data<Arc<scylla::Session>>,
+    store: web::Data<dyn FormStore>,
+    renderer: web::Data<FormRenderer>,
     path: web::Path<String>,
 ) -> Result<impl Responder, AppError> {
     let id = Uuid::parse_str(&path.into_inner())
         .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
-    
-    let schema = db::get_form_schema(&session, id).await?;
-    
-    let html = templates::generate_form_html(&schema);
-    
+
+    let schema = store.get_form_schema(id).await?;
+
+    let html = renderer.render(&schema);
+
     Ok(HttpResponse::Ok().content_type("text/html").body(html))
 }
 
+#[utoipa::path(
+    tag = "responses",
+    params(("id" = Uuid, Path, description = "Form id")),
+    request_body(
+        content = HashMap<String, String>,
+        content_type = "application/x-www-form-urlencoded",
+        description = "Answers keyed by field id; `MultiSelect` and `Ranking` answers repeat the id once per option, in order",
+    ),
+    responses(
+        (status = 201, description = "Response stored", body = ApiResponse<Created>),
+        (status = 400, description = "Malformed form id, or answers that fail validation", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
 #[post("/forms/{id}/submit")]
 async fn submit_form(
-    session: web::Data<Arc<scylla::Session>>,
+    store: web::Data<dyn FormStore>,
+    notifier: web::Data<Notifier>,
+    events: web::Data<ResponseEvents>,
+    search: web::Data<SearchIndex>,
     path: web::Path<String>,
-    form: web::Form<HashMap<String, String>>,
+    form: web::Form<Vec<(String, String)>>,
 ) -> Result<impl Responder, AppError> {
     let form_id = Uuid::parse_str(&path.into_inner())
         .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
-    
+
     // Validate that the form exists
-    let schema = db::get_form_schema(&session, form_id).await?;
-    
-    // Convert form data to JSON values
-    let mut data = HashMap::new();
-    for (key, value) in form.into_inner() {
-        // Find the field in the schema to determine the type
-        if let Some(field) = schema.fields.iter().find(|f| f.id == key) {
-            let json_value = match field.field_type {
-                crate::models::FieldType::Number => {
-                    if value.is_empty() {
-                        json!(null)
-                    } else {
-                        match value.parse::<f64>() {
-                            Ok(num) => json!(num),
-                            Err(_) => json!(value),
-                        }
-                    }
-                }
-                crate::models::FieldType::Checkbox => {
-                    if value == "on" {
-                        json!(true)
-                    } else {
-                        json!(value)
-                    }
-                }
-                _ => json!(value),
-            };
-            
-            data.insert(key, json_value);
+    let schema = store.get_form_schema(form_id).await?;
+
+    let data = match validation::coerce_submission(&schema, &form.into_inner()) {
+        Ok(data) => data,
+        Err(e) => {
+            METRICS.record_validation_failure(form_id);
+            return Err(e);
         }
-    }
-    
+    };
+
     let response = FormResponse {
-        id: None,
+        id: Some(Uuid::new_v4()),
         form_id,
-        data,
-        created_at: None,
+        data: data.clone(),
+        created_at: Some(Utc::now()),
     };
-    
-    let response_id = db::submit_form_response(&session, response).await?;
-    
-    Ok(HttpResponse::Created().json(ApiResponse::success(json!({
-        "id": response_id,
-        "message": "Form submitted successfully"
-    }))))
+
+    let response_id = store.submit_form_response(response.clone()).await?;
+    METRICS.record_submission(form_id);
+
+    // The response is stored; a failure to index it is logged, not reported
+    let search = search.into_inner();
+    let (indexed_schema, indexed) = (schema.clone(), response.clone());
+    match web::block(move || search.index_response(&indexed_schema, &indexed)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("Failed to index response {}: {}", response_id, e),
+        Err(e) => warn!("Failed to index response {}: {}", response_id, e),
+    }
+    events.publish(response);
+
+    // Notification failures must never fail the submission itself
+    match store.get_notification_settings(form_id).await {
+        Ok(Some(settings)) => notifier.notify_submission(&schema, &settings, response_id, &data),
+        Ok(None) => {}
+        Err(e) => warn!("Skipping notifications for form {}: {}", form_id, e),
+    }
+
+    Ok(HttpResponse::Created().json(ApiResponse::success(Created {
+        id: response_id,
+        message: "Form submitted successfully".to_string(),
+    })))
 }
 
+#[utoipa::path(
+    tag = "responses",
+    params(("id" = Uuid, Path, description = "Form id"), ResponseQuery),
+    responses(
+        (
+            status = 200,
+            description = "Matching responses",
+            body = ApiResponse<Vec<FormResponse>>,
+            headers(("X-Total-Count" = usize, description = "Number of matching responses, before paging")),
+        ),
+        (status = 400, description = "Malformed form id, filter or sort, or a `limit` over 1000", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
 #[get("/forms/{id}/responses")]
 async fn get_form_responses(
-    session: web::Data<Arc<scylla::Session>>,
+    store: web::Data<dyn FormStore>,
+    path: web::Path<String>,
+    query: web::Query<ResponseQuery>,
+) -> Result<impl Responder, AppError> {
+    let form_id = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
+
+    // Validate that the form exists and compile the filter against it
+    let schema = store.get_form_schema(form_id).await?;
+    let filter = ResponseFilter::compile(&schema, &query)?;
+    if query.limit.is_some_and(|limit| limit > MAX_PAGE_SIZE) {
+        return Err(AppError::BadRequest(format!("`limit` must be at most {}", MAX_PAGE_SIZE)));
+    }
+
+    let (from, to) = filter.created_range();
+    let mut responses = store.get_form_responses_between(form_id, from, to).await?;
+    filter.apply(&mut responses);
+
+    let total = responses.len();
+    let page: Vec<FormResponse> = responses
+        .into_iter()
+        .skip(query.offset.unwrap_or(0))
+        .take(query.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .insert_header((TOTAL_COUNT_HEADER, total))
+        .json(ApiResponse::success(page)))
+}
+
+#[utoipa::path(
+    tag = "responses",
+    params(("id" = Uuid, Path, description = "Form id"), ExportQuery),
+    responses(
+        (
+            status = 200,
+            description = "The responses as CSV, one column per field, or as one JSON object per line",
+            content((String = "text/csv"), (String = "application/x-ndjson")),
+        ),
+        (status = 400, description = "Malformed form id, filter or sort", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
+#[get("/forms/{id}/responses/export")]
+async fn export_form_responses(
+    store: web::Data<dyn FormStore>,
     path: web::Path<String>,
+    query: web::Query<ExportQuery>,
 ) -> Result<impl Responder, AppError> {
     let form_id = Uuid::parse_str(&path.into_inner())
         .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
-    
+
+    let schema = store.get_form_schema(form_id).await?;
+    let query = query.into_inner();
+    let filter = ResponseFilter::compile(
+        &schema,
+        &ResponseQuery {
+            filter: query.filter,
+            sort: query.sort,
+            ..Default::default()
+        },
+    )?;
+
+    let (from, to) = filter.created_range();
+    let mut responses = store.get_form_responses_between(form_id, from, to).await?;
+    filter.apply(&mut responses);
+
+    let format = query.format.unwrap_or_default();
+    let (body, content_type, extension) = match format {
+        ExportFormat::Csv => (export::to_csv(&schema, &responses)?, "text/csv; charset=utf-8", "csv"),
+        ExportFormat::Ndjson => (export::to_ndjson(&responses)?, "application/x-ndjson", "ndjson"),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}.{}\"", form_id, extension),
+        ))
+        .body(body))
+}
+
+#[utoipa::path(
+    tag = "responses",
+    params(
+        ("id" = Uuid, Path, description = "Form id"),
+        ("Last-Event-ID" = Option<Uuid>, Header, description = "Replay responses stored after this one"),
+    ),
+    responses(
+        (status = 200, description = "Server-sent events, one per new response", body = String, content_type = "text/event-stream"),
+        (status = 400, description = "Malformed form id", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
+#[get("/forms/{id}/responses/stream")]
+async fn stream_form_responses(
+    store: web::Data<dyn FormStore>,
+    events: web::Data<ResponseEvents>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let form_id = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
+
     // Validate that the form exists
-    let _ = db::get_form_schema(&session, form_id).await?;
-    
-    let responses = db::get_form_responses(&session, form_id).await?;
-    
-    Ok(HttpResponse::Ok().json(ApiResponse::success(responses)))
-}
\ No newline at end of file
+    let _ = store.get_form_schema(form_id).await?;
+
+    // Subscribe before backfilling so nothing submitted in between is lost
+    let subscription = events.subscribe();
+
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| Uuid::parse_str(v.trim()).ok());
+
+    let backfill = match last_event_id {
+        Some(last_event_id) => {
+            let responses = store.get_form_responses(form_id).await?;
+            events::backfill_after(responses, last_event_id)
+        }
+        None => Vec::new(),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(events::sse_stream(form_id, backfill, subscription)))
+}
+
+#[utoipa::path(
+    tag = "responses",
+    params(("id" = Uuid, Path, description = "Form id"), SearchQuery),
+    responses(
+        (status = 200, description = "Best matching responses first", body = ApiResponse<Vec<SearchHit>>),
+        (status = 400, description = "Malformed form id or query", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
+#[get("/forms/{id}/responses/search")]
+async fn search_form_responses(
+    store: web::Data<dyn FormStore>,
+    search: web::Data<SearchIndex>,
+    path: web::Path<String>,
+    query: web::Query<SearchQuery>,
+) -> Result<impl Responder, AppError> {
+    let form_id = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
+
+    // Validate that the form exists
+    let _ = store.get_form_schema(form_id).await?;
+
+    let search = search.into_inner();
+    let hits = web::block(move || search.search(form_id, &query))
+        .await
+        .map_err(|e| AppError::InternalError(format!("Search failed: {}", e)))??;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(hits)))
+}
+
+#[utoipa::path(
+    tag = "responses",
+    params(("id" = Uuid, Path, description = "Form id"), SummaryQuery),
+    responses(
+        (status = 200, description = "Per-field statistics", body = ApiResponse<FormSummary>),
+        (status = 400, description = "Malformed form id or time range", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
+#[get("/forms/{id}/summary")]
+async fn get_form_summary(
+    store: web::Data<dyn FormStore>,
+    path: web::Path<String>,
+    query: web::Query<SummaryQuery>,
+) -> Result<impl Responder, AppError> {
+    let form_id = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
+
+    let schema = store.get_form_schema(form_id).await?;
+    let responses = store.get_form_responses(form_id).await?;
+
+    let summary = summary::summarize(&schema, &responses, &query)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(summary)))
+}
+
+#[utoipa::path(
+    tag = "notifications",
+    params(("id" = Uuid, Path, description = "Form id")),
+    responses(
+        (status = 200, description = "The notification settings", body = ApiResponse<NotificationSettings>),
+        (status = 400, description = "Malformed form id", body = ErrorResponse),
+        (status = 404, description = "No form with this id, or no settings", body = ErrorResponse),
+    ),
+)]
+#[get("/forms/{id}/notifications")]
+async fn get_notification_settings(
+    store: web::Data<dyn FormStore>,
+    path: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let form_id = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
+
+    // Validate that the form exists
+    let _ = store.get_form_schema(form_id).await?;
+
+    let settings = store.get_notification_settings(form_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("No notification settings for form {}", form_id)))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(settings)))
+}
+
+#[utoipa::path(
+    tag = "notifications",
+    params(("id" = Uuid, Path, description = "Form id")),
+    request_body = NotificationSettings,
+    responses(
+        (status = 200, description = "Settings saved", body = ApiResponse<NotificationSettings>),
+        (status = 400, description = "Malformed form id or invalid settings", body = ErrorResponse),
+        (status = 404, description = "No form with this id", body = ErrorResponse),
+    ),
+)]
+#[put("/forms/{id}/notifications")]
+async fn update_notification_settings(
+    store: web::Data<dyn FormStore>,
+    path: web::Path<String>,
+    settings: web::Json<NotificationSettings>,
+) -> Result<impl Responder, AppError> {
+    let form_id = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))?;
+
+    let schema = store.get_form_schema(form_id).await?;
+    let settings = settings.into_inner();
+
+    if let Err(e) = notifications::validate_settings(&schema, &settings) {
+        METRICS.record_validation_failure(form_id);
+        return Err(e);
+    }
+    store.set_notification_settings(form_id, &settings).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(settings)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    /// The API over `$store`, or an empty memory store, with the directory of
+    /// its search index, which is removed once the test drops it.
+    macro_rules! test_app {
+        () => {
+            test_app!(MemoryStore::default(), false)
+        };
+        ($store:expr, $require_api_key:expr) => {{
+            let store: Arc<dyn FormStore> = Arc::new($store);
+            let index_dir = TempDir::new().unwrap();
+            let app = test::init_service(
+                App::new()
+                    .app_data(web::Data::from(store))
+                    .app_data(web::Data::new(Notifier::disabled()))
+                    .app_data(web::Data::new(ResponseEvents::default()))
+                    .app_data(web::Data::new(SearchIndex::open(index_dir.path()).unwrap()))
+                    .app_data(web::Data::new(FormRenderer::new(None).unwrap()))
+                    .app_data(web::Data::new(crate::health::Readiness::default()))
+                    .app_data(web::Data::new(crate::auth::AuthConfig { require_api_key: $require_api_key }))
+                    .wrap(actix_web::middleware::from_fn(crate::auth::require_api_key))
+                    .configure(crate::health::config)
+                    .configure(config),
+            )
+            .await;
+            (app, index_dir)
+        }};
+    }
+
+    /// Registers the form described in YAML and evaluates to its id.
+    macro_rules! create_form {
+        ($app:expr, $yaml:expr) => {{
+            let req = test::TestRequest::post()
+                .uri("/api/forms")
+                .insert_header(("Content-Type", "application/yaml"))
+                .set_payload($yaml)
+                .to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let resp: Value = test::read_body_json(resp).await;
+            resp["data"]["id"].as_str().unwrap().to_string()
+        }};
+    }
+
+    /// Submits the answers to the form and evaluates to the response.
+    macro_rules! submit {
+        ($app:expr, $id:expr, $answers:expr) => {{
+            let req = test::TestRequest::post()
+                .uri(&format!("/api/forms/{}/submit", $id))
+                .set_form($answers)
+                .to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    /// Evaluates to the body of a GET request as text.
+    macro_rules! get_text {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            String::from_utf8(test::call_and_read_body(&$app, req).await.to_vec()).unwrap()
+        }};
+    }
+
+    const FEEDBACK: &str = "name: Customer Feedback\nfields:\n\
+        \x20 - { id: name, label: Name, field_type: Text, required: true }\n\
+        \x20 - { id: rating, label: Rating, field_type: Number, required: true }\n";
+
+    #[actix_web::test]
+    async fn registers_and_fetches_a_form() {
+        let (app, _index_dir) = test_app!();
+
+        let form = json!({
+            "name": "Customer Feedback",
+            "description": null,
+            "fields": [
+                { "id": "name", "label": "Name", "field_type": "Text", "required": true,
+                  "placeholder": null, "options": null, "validation": null },
+                { "id": "rating", "label": "Rating", "field_type": "Number", "required": true,
+                  "placeholder": null, "options": null, "validation": null }
+            ],
+            "created_at": null,
+            "updated_at": null
+        });
+        let req = test::TestRequest::post().uri("/api/forms").set_json(form).to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        let id = resp["data"]["id"].as_str().unwrap().to_string();
+
+        let req = test::TestRequest::get().uri(&format!("/api/forms/{}", id)).to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["name"], "Customer Feedback");
+        assert_eq!(resp["data"]["fields"].as_array().unwrap().len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn describes_response_data_as_json_schema_and_typescript() {
+        let (app, _index_dir) = test_app!();
+        let id = create_form!(app, FEEDBACK);
+
+        let req = test::TestRequest::get().uri(&format!("/api/forms/{}/schema.json", id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("Content-Type").unwrap(), "application/schema+json");
+        let schema: Value = test::read_body_json(resp).await;
+        assert_eq!(schema["required"], json!(["name", "rating"]));
+        assert_eq!(schema["properties"]["rating"]["type"], "number");
+        assert_eq!(schema["additionalProperties"], false);
+
+        let types = get_text!(app, format!("/api/forms/{}/types.ts", id));
+        assert!(types.contains("export interface CustomerFeedbackResponse {"), "{}", types);
+        assert!(types.contains("  name: string;\n"), "{}", types);
+        assert!(types.contains("  rating: number;\n"), "{}", types);
+    }
+
+    #[actix_web::test]
+    async fn registers_forms_from_yaml_and_json_schema() {
+        let (app, _index_dir) = test_app!();
+        create_form!(app, "name: Survey\nfields:\n  - { id: age, label: Age, field_type: Number, required: true, min: 18 }\n");
+
+        let document = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Signup",
+            "type": "object",
+            "required": ["email"],
+            "properties": {
+                "email": { "type": "string", "format": "email", "pattern": "^(?!admin@)" },
+                "plan": { "enum": ["free", "pro"] },
+                "seats": { "type": "integer", "minimum": 1, "multipleOf": 5 },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "a.b": { "type": "string" }
+            }
+        });
+        let req = test::TestRequest::post()
+            .uri("/api/forms/import/json-schema?dry_run=true")
+            .set_json(&document)
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        let fields = resp["data"]["schema"]["fields"].as_array().unwrap();
+        let types: Vec<_> = fields.iter().map(|f| f["field_type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["Email", "Select", "Number"]);
+        assert_eq!(fields[0]["required"], true);
+        assert!(fields[0]["validation"].is_null());
+        assert_eq!(fields[2]["min"], 1.0);
+        assert!(resp["data"]["id"].is_null());
+        let warnings: Vec<_> = resp["data"]["warnings"].as_array().unwrap().iter().map(|w| w.as_str().unwrap()).collect();
+        assert_eq!(
+            warnings,
+            [
+                "/properties/email/pattern: look-around, including look-ahead and look-behind, is not supported; dropped",
+                "/properties/seats/type: integers are imported as numbers, which also accept fractions",
+                "/properties/seats/multipleOf: not supported",
+                "/properties/tags: array values cannot be converted to a field; skipped",
+                "/properties/a.b: names containing `[`, `]` or `.` cannot be field ids; skipped",
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn stores_and_filters_submissions() {
+        let (app, _index_dir) = test_app!();
+        let id = create_form!(app, FEEDBACK);
+
+        for (name, rating) in [("Jane", "5"), ("John", "2")] {
+            assert_eq!(submit!(app, id, [("name", name), ("rating", rating)]).status(), StatusCode::CREATED);
+        }
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/forms/{}/responses?filter=rating%20%3C%3D%202", id))
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        let responses = resp["data"].as_array().unwrap();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["data"]["name"], "John");
+        assert_eq!(responses[0]["data"]["rating"], 2.0);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/forms/{}/responses?filter=rating%20%3C%3D", id))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/forms/{}/responses?filter=created_at%20%3E%3D%20now-1d&limit=1", id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(TOTAL_COUNT_HEADER).unwrap(), "2");
+        let resp: Value = test::read_body_json(resp).await;
+        assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+
+        let req = test::TestRequest::get().uri(&format!("/api/forms/{}/responses?limit=1001", id)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn renders_richer_field_types_and_stores_normalized_answers() {
+        let (app, _index_dir) = test_app!();
+        let id = create_form!(
+            app,
+            "name: Booking\nfields:\n\
+            \x20 - { id: phone, label: Phone, field_type: Phone, required: true }\n\
+            \x20 - { id: start, label: Start, field_type: DateTime, required: true }\n"
+        );
+
+        let html = get_text!(app, format!("/api/forms/{}/render", id));
+        assert!(html.contains(r#"<input type="tel" id="phone""#), "{}", html);
+        assert!(html.contains(r#"name="start__offset""#), "{}", html);
+        assert!(!html.contains(r#"<input type="email""#), "{}", html);
+
+        let answers = [("phone", "0049 (30) 123-456"), ("start", "2025-05-04T09:30"), ("start__offset", "120")];
+        assert_eq!(submit!(app, id, answers).status(), StatusCode::CREATED);
+
+        let req = test::TestRequest::get().uri(&format!("/api/forms/{}/responses", id)).to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"][0]["data"]["phone"], "+4930123456");
+        assert_eq!(resp["data"][0]["data"]["start"], "2025-05-04T09:30:00+02:00");
+
+        let resp = submit!(app, id, [("phone", "12345"), ("start", "2025-05-04T09:30:00Z")]);
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        let error = body.as_str().unwrap();
+        assert!(error.starts_with("Validation error: phone: "), "{}", error);
+    }
+
+    #[actix_web::test]
+    async fn stores_multiple_selections_and_rankings_as_arrays() {
+        let (app, _index_dir) = test_app!();
+        let id = create_form!(
+            app,
+            "name: Lunch\nfields:\n\
+            \x20 - { id: sides, label: Sides, field_type: MultiSelect, required: false,\n\
+            \x20     options: [{ value: rice, label: Rice }, { value: salad, label: Salad }] }\n\
+            \x20 - { id: mains, label: Mains, field_type: Ranking, required: true,\n\
+            \x20     options: [{ value: fish, label: Fish }, { value: curry, label: Curry }] }\n"
+        );
+
+        let answers = [("sides", "salad"), ("sides", "rice"), ("mains", "curry"), ("mains", "fish")];
+        assert_eq!(submit!(app, id, answers).status(), StatusCode::CREATED);
+
+        let req = test::TestRequest::get().uri(&format!("/api/forms/{}/responses", id)).to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"][0]["data"]["sides"], json!(["salad", "rice"]));
+        assert_eq!(resp["data"][0]["data"]["mains"], json!(["curry", "fish"]));
+    }
+
+    #[actix_web::test]
+    async fn renders_and_exports_matrix_rows() {
+        let (app, _index_dir) = test_app!();
+        let id = create_form!(
+            app,
+            "name: Satisfaction\nfields:\n\
+            \x20 - id: service\n\
+            \x20   label: How much do you agree?\n\
+            \x20   field_type: Matrix\n\
+            \x20   required: false\n\
+            \x20   rows: [{ id: speed, label: It was fast, required: true }, { id: price, label: It was cheap }]\n\
+            \x20   options: [{ value: agree, label: Agree }, { value: neutral, label: Neutral }, { value: disagree, label: Disagree }]\n"
+        );
+
+        let html = get_text!(app, format!("/api/forms/{}/render", id));
+        assert!(
+            html.contains(r#"<input type="radio" name="service[speed]" value="agree" aria-label="It was fast: Agree" required>"#),
+            "{}",
+            html
+        );
+        assert!(html.contains(r#"name="service[price]" value="agree" aria-label="It was cheap: Agree" >"#), "{}", html);
+
+        let answers = [("service[speed]", "agree"), ("service[price]", "disagree")];
+        assert_eq!(submit!(app, id, answers).status(), StatusCode::CREATED);
+
+        let csv = get_text!(app, format!("/api/forms/{}/responses/export?format=csv&sort=created_at", id));
+        assert!(csv.starts_with("id,created_at,service.speed,service.price\n"), "{}", csv);
+        assert!(csv.trim_end().ends_with(",agree,disagree"), "{}", csv);
+    }
+
+    #[actix_web::test]
+    async fn renders_ratings_and_summarizes_net_promoter_scores() {
+        let (app, _index_dir) = test_app!();
+        let id = create_form!(
+            app,
+            "name: Checkout\nfields:\n\
+            \x20 - { id: stars, label: Rate your checkout, field_type: Rating, required: true, max: 4 }\n\
+            \x20 - { id: nps, label: How likely are you to recommend us?, field_type: Nps, required: false }\n"
+        );
+
+        let html = get_text!(app, format!("/api/forms/{}/render", id));
+        assert!(html.contains(r#"<input type="radio" id="stars-4" name="stars" value="4" required>"#), "{}", html);
+        assert!(!html.contains(r#"id="stars-5""#), "{}", html);
+        assert!(html.contains(r#"<input type="radio" name="nps" value="10" >"#), "{}", html);
+
+        for (stars, nps) in [("4", "10"), ("3", "9"), ("2", "7"), ("1", "3")] {
+            assert_eq!(submit!(app, id, [("stars", stars), ("nps", nps)]).status(), StatusCode::CREATED);
+        }
+
+        let req = test::TestRequest::get().uri(&format!("/api/forms/{}/summary", id)).to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["total_responses"], 4);
+        assert_eq!(resp["data"]["fields"][0]["stats"]["mean"], 2.5);
+        assert_eq!(resp["data"]["fields"][1]["stats"]["score"], 25.0);
+    }
+
+    #[actix_web::test]
+    async fn renders_and_exports_repeating_groups() {
+        let (app, _index_dir) = test_app!();
+        let id = create_form!(
+            app,
+            "name: Enrolment\nfields:\n\
+            \x20 - id: dependents\n\
+            \x20   label: Dependents\n\
+            \x20   field_type: Group\n\
+            \x20   required: false\n\
+            \x20   max_items: 2\n\
+            \x20   fields:\n\
+            \x20     - { id: name, label: Name, field_type: Text, required: true }\n\
+            \x20     - { id: age, label: Age, field_type: Number, required: false, min: 0 }\n"
+        );
+
+        let html = get_text!(app, format!("/api/forms/{}/render", id));
+        assert!(html.contains(r#"data-min="0" data-max="2" data-placeholder="__i0__""#), "{}", html);
+        assert!(html.contains(r#"id="dependents-__i0__-name" name="dependents[__i0__][name]" required"#), "{}", html);
+
+        for answers in [vec![("dependents[0][name]", "Ann"), ("dependents[0][age]", "7"), ("dependents[3][name]", "Bob")], vec![]] {
+            assert_eq!(submit!(app, id, answers).status(), StatusCode::CREATED);
+        }
+
+        let csv = get_text!(app, format!("/api/forms/{}/responses/export?format=csv&sort=created_at", id));
+        assert!(
+            csv.starts_with("id,created_at,dependents.1.name,dependents.1.age,dependents.2.name,dependents.2.age\n"),
+            "{}",
+            csv
+        );
+    }
+
+    #[actix_web::test]
+    async fn reports_missing_forms_and_bad_ids() {
+        let (app, _index_dir) = test_app!();
+
+        let req = test::TestRequest::get().uri(&format!("/api/forms/{}", Uuid::new_v4())).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body: Value = test::read_body_json(resp).await;
+        assert!(body.as_str().unwrap().starts_with("Not found"), "{}", body);
+
+        let req = test::TestRequest::get().uri("/api/forms/not-a-uuid").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn answers_health_probes() {
+        let (app, _index_dir) = test_app!();
+
+        for uri in ["/healthz", "/readyz"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        }
+    }
+
+    #[actix_web::test]
+    async fn serves_the_openapi_document() {
+        let (app, _index_dir) = test_app!();
+
+        let req = test::TestRequest::get().uri("/api/openapi.json").to_request();
+        let spec: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(spec["paths"]["/api/forms/{id}/submit"]["post"].is_object());
+        assert!(spec["components"]["schemas"]["FormSchema"].is_object());
+
+        // Only the routes behind the API key middleware ask for a key
+        assert_eq!(spec["components"]["securitySchemes"]["bearer"]["scheme"], "bearer");
+        assert_eq!(spec["components"]["securitySchemes"]["api_key"]["name"], "X-Api-Key");
+        let forms = &spec["paths"]["/api/forms"]["get"];
+        assert_eq!(forms["security"], json!([{ "bearer": [] }, { "api_key": [] }]));
+        assert_eq!(forms["responses"]["401"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/ErrorResponse");
+        assert!(spec["paths"]["/api/forms/{id}/submit"]["post"]["security"].is_null());
+        assert!(spec["paths"]["/healthz"]["get"]["responses"]["401"].is_null());
+    }
+
+    /// Every route declared with an actix attribute must appear in the
+    /// OpenAPI document, so new endpoints cannot ship undocumented.
+    #[actix_web::test]
+    async fn documents_every_route() {
+        let route = regex::Regex::new(r#"#\[(get|post|put|patch|delete)\("([^"]+)"\)\]"#).unwrap();
+        let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+
+        let mut declared = Vec::new();
+        let mut dirs = vec![src.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                // Handlers of these modules are mounted in the `/api` scope
+                let scope = match path.strip_prefix(&src).unwrap().to_str().unwrap() {
+                    "api.rs" | "openapi.rs" => "/api",
+                    _ => "",
+                };
+                let source = std::fs::read_to_string(&path).unwrap();
+                for caps in route.captures_iter(&source) {
+                    declared.push((caps[1].to_string(), format!("{}{}", scope, &caps[2])));
+                }
+            }
+        }
+        assert!(!declared.is_empty());
+
+        let spec = serde_json::to_value(&*openapi::SPEC).unwrap();
+        let undocumented: Vec<_> = declared
+            .iter()
+            .filter(|(method, path)| spec["paths"][path][method].is_null())
+            .collect();
+        assert!(undocumented.is_empty(), "Routes missing from the OpenAPI document: {:?}", undocumented);
+    }
+
+    /// Every operation in the OpenAPI document must be served by the routes
+    /// main mounts, so the document cannot drift from the application.
+    #[actix_web::test]
+    async fn serves_every_documented_route() {
+        let app = test::init_service(
+            App::new()
+                .configure(crate::health::config)
+                .configure(crate::metrics::config)
+                .configure(config)
+                .default_service(web::to(|| async { HttpResponse::ImATeapot().finish() })),
+        )
+        .await;
+
+        let placeholder = regex::Regex::new(r"\{[^}]+\}").unwrap();
+        let spec = serde_json::to_value(&*openapi::SPEC).unwrap();
+        let mut unrouted = Vec::new();
+        for (path, operations) in spec["paths"].as_object().unwrap() {
+            let uri = placeholder.replace_all(path, Uuid::nil().to_string().as_str());
+            for method in operations.as_object().unwrap().keys() {
+                let req = test::TestRequest::default()
+                    .method(method.to_uppercase().parse().unwrap())
+                    .uri(&uri)
+                    .to_request();
+                if test::call_service(&app, req).await.status() == StatusCode::IM_A_TEAPOT {
+                    unrouted.push(format!("{} {}", method, path));
+                }
+            }
+        }
+        assert!(unrouted.is_empty(), "Documented routes the application does not serve: {:?}", unrouted);
+    }
+
+    #[actix_web::test]
+    async fn streams_submissions_and_replays_those_missed() {
+        use actix_web::body::MessageBody;
+
+        /// Evaluates to the next chunk of the streamed body as text.
+        macro_rules! next_chunk {
+            ($body:expr) => {{
+                let chunk = futures::future::poll_fn(|cx| $body.as_mut().poll_next(cx)).await;
+                String::from_utf8(chunk.unwrap().unwrap().to_vec()).unwrap()
+            }};
+        }
+
+        let (app, _index_dir) = test_app!();
+        let id = create_form!(app, FEEDBACK);
+        let uri = format!("/api/forms/{}/responses/stream", id);
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("Content-Type").unwrap(), "text/event-stream");
+        let mut body = std::pin::pin!(resp.into_body());
+        assert!(next_chunk!(body).starts_with("retry: "));
+
+        let first = submit!(app, id, [("name", "John"), ("rating", "4")]);
+        let first: Value = test::read_body_json(first).await;
+        let first_id = first["data"]["id"].as_str().unwrap();
+        let event = next_chunk!(body);
+        assert!(event.starts_with(&format!("id: {}\nevent: response\n", first_id)), "{}", event);
+        assert!(event.contains("\"name\":\"John\""), "{}", event);
+
+        // Reconnecting with the last event seen replays what came after it
+        let second = submit!(app, id, [("name", "Jane"), ("rating", "5")]);
+        let second: Value = test::read_body_json(second).await;
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("Last-Event-ID", first_id))
+            .to_request();
+        let mut body = std::pin::pin!(test::call_service(&app, req).await.into_body());
+        assert!(next_chunk!(body).starts_with("retry: "));
+        let replayed = next_chunk!(body);
+        let second_id = second["data"]["id"].as_str().unwrap();
+        assert!(replayed.starts_with(&format!("id: {}\n", second_id)), "{}", replayed);
+    }
+
+    #[actix_web::test]
+    async fn requires_an_api_key_when_configured() {
+        let store = MemoryStore::default();
+        let (key, secret) = crate::auth::ApiKey::generate("test");
+        store.create_api_key(&key).await.unwrap();
+        let (app, _index_dir) = test_app!(store, true);
+
+        let req = test::TestRequest::get().uri("/api/forms").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers().get("WWW-Authenticate").unwrap(), "Bearer");
+        let body: String = test::read_body_json(resp).await;
+        assert_eq!(body, "Unauthorized: A valid API key is required");
+
+        let req = test::TestRequest::get()
+            .uri("/api/forms")
+            .insert_header(("Authorization", format!("Bearer {}", secret)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        // Respondents need no key
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/forms/{}/render", Uuid::new_v4()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,113 @@
+// src/auth.rs
+//! API keys. Only their SHA-256 hashes are stored; the key itself is shown
+//! once, when `form-portal-admin api-keys create` generates it.
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::middleware::Next;
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{error::AppError, store::FormStore};
+
+const KEY_PREFIX: &str = "fpk_";
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Require an API key for every `/api` route except rendering and
+    /// submitting forms and the API documentation.
+    pub require_api_key: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    /// Hex-encoded SHA-256 of the key.
+    pub key_hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiKey {
+    /// Generates a key named `name`, returning it along with the secret to
+    /// hand out.
+    pub fn generate(name: &str) -> (Self, String) {
+        let secret = format!("{}{}{}", KEY_PREFIX, Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let key = Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            key_hash: hash(&secret),
+            created_at: Utc::now(),
+        };
+        (key, secret)
+    }
+}
+
+pub fn hash(secret: &str) -> String {
+    openssl::sha::sha256(secret.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Routes respondents and documentation readers reach without a key.
+pub fn is_public(method: &Method, route: &str) -> bool {
+    matches!(
+        (method.as_str(), route),
+        ("GET", "/api/forms/{id}/render")
+            | ("POST", "/api/forms/{id}/submit")
+            | ("GET", "/api/openapi.json")
+            | ("GET", "/api/docs")
+    ) || !route.starts_with("/api/")
+}
+
+/// The key from `Authorization: Bearer <key>` or `X-Api-Key: <key>`.
+fn presented_key(req: &ServiceRequest) -> Option<&str> {
+    let headers = req.headers();
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()))
+        .map(str::trim)
+}
+
+/// Middleware rejecting requests to protected routes that lack a valid API
+/// key, when `auth.require_api_key` is set.
+pub async fn require_api_key(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let required = req
+        .app_data::<web::Data<AuthConfig>>()
+        .is_some_and(|config| config.require_api_key);
+    let route = req.match_pattern().unwrap_or_else(|| req.path().to_string());
+    if !required || is_public(req.method(), &route) {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let valid = match (presented_key(&req), req.app_data::<web::Data<dyn FormStore>>()) {
+        (Some(key), Some(store)) => match store.find_api_key(&hash(key)).await {
+            Ok(found) => found.is_some(),
+            Err(e) => {
+                warn!("Failed to look up API key: {}", e);
+                let error = AppError::Unavailable("Cannot verify the API key".to_string());
+                return Ok(req.error_response(error).map_into_right_body());
+            }
+        },
+        _ => false,
+    };
+
+    if !valid {
+        let error = AppError::Unauthorized("A valid API key is required".to_string());
+        return Ok(req.error_response(error).map_into_right_body());
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
@@ -0,0 +1,417 @@
+// src/bin/form-portal-admin.rs
+//! Operator tool working on the configured database directly, with the same
+//! configuration file, environment and flags as the server.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use dotenv::dotenv;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+use form_portal::{
+    auth::ApiKey,
+    config::{Config, Overrides},
+    db, definition, export, jsonschema,
+    filter::ResponseFilter,
+    models::{query::ResponseQuery, FormResponse},
+    search::SearchIndex,
+    store::{self, FormStore, StorageBackend},
+    validation,
+};
+
+const REINDEX_HINT: &str = "Stop the server and run `form_portal rebuild-search-index` to bring the search index up to date";
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Administer a form_portal database")]
+struct Cli {
+    /// TOML configuration file, as given to the server
+    #[arg(short, long, env = "FORM_PORTAL_CONFIG", global = true)]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    overrides: Overrides,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Create, inspect and delete forms
+    #[command(subcommand)]
+    Forms(FormsCommand),
+    /// Export, import and purge responses
+    #[command(subcommand)]
+    Responses(ResponsesCommand),
+    /// Apply pending migrations
+    Migrate {
+        /// Print the pending migrations without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Manage the keys accepted by the API
+    #[command(subcommand)]
+    ApiKeys(ApiKeysCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum FormsCommand {
+    /// Create a form from a JSON, YAML or TOML schema file
+    Create { file: PathBuf },
+    /// Create a form from a JSON Schema (draft 2020-12) of an object
+    ImportJsonSchema {
+        file: PathBuf,
+        /// Print the converted form without creating it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    List,
+    /// Print a form's schema as JSON
+    Show { id: Uuid },
+    /// Delete a form with all its responses
+    Delete {
+        id: Uuid,
+        /// Do not ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ResponsesCommand {
+    /// Write a form's responses to a file, or to stdout
+    Export {
+        form: Uuid,
+        #[arg(long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+        /// Filter expression, as accepted by the API
+        #[arg(long)]
+        filter: Option<String>,
+        /// Sort expression, as accepted by the API
+        #[arg(long)]
+        sort: Option<String>,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Store the responses of an NDJSON export under a form. Nothing is
+    /// stored unless every response passes the form's validation.
+    Import {
+        form: Uuid,
+        file: PathBuf,
+        /// Store the answers as they are, without validating them
+        #[arg(long)]
+        no_validate: bool,
+    },
+    /// Delete responses older than a cutoff
+    Purge {
+        /// Delete responses submitted more than this many days ago
+        #[arg(long, conflicts_with = "before", required_unless_present = "before")]
+        older_than_days: Option<u32>,
+        /// Delete responses submitted before this date or RFC 3339 timestamp
+        #[arg(long, value_parser = parse_cutoff)]
+        before: Option<DateTime<Utc>>,
+        /// Only purge this form; every form otherwise
+        #[arg(long)]
+        form: Option<Uuid>,
+        /// Do not ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    Ndjson,
+}
+
+#[derive(Debug, Subcommand)]
+enum ApiKeysCommand {
+    /// Generate a key; it is printed once and cannot be shown again
+    Create { name: String },
+    List,
+    Revoke { id: Uuid },
+}
+
+fn parse_cutoff(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    Ok(DateTime::parse_from_rfc3339(value)
+        .context("Expected a date (YYYY-MM-DD) or an RFC 3339 timestamp")?
+        .with_timezone(&Utc))
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref(), &cli.overrides).unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {:#}", e);
+        std::process::exit(2);
+    });
+
+    // Logs go to stderr, keeping stdout for exports
+    tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")))
+        .init();
+
+    if let Err(e) = run(cli.command, &config).await {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(command: Command, config: &Config) -> Result<()> {
+    if let Command::Migrate { dry_run } = command {
+        return migrate(config, dry_run).await;
+    }
+
+    if config.storage.backend == StorageBackend::Memory {
+        bail!("The memory backend keeps nothing between runs; point the tool at the server's database");
+    }
+    let store = store::connect(&config.storage).await?;
+    let result = match command {
+        Command::Forms(command) => forms(command, store.as_ref(), &config.search.index_dir).await,
+        Command::Responses(command) => responses(command, store.as_ref(), &config.search.index_dir).await,
+        Command::ApiKeys(command) => api_keys(command, store.as_ref()).await,
+        Command::Migrate { .. } => unreachable!("handled above"),
+    };
+    store.close().await;
+    result
+}
+
+async fn migrate(config: &Config, dry_run: bool) -> Result<()> {
+    match config.storage.backend {
+        StorageBackend::Scylla => db::migrate(&config.storage.scylla, dry_run).await,
+        StorageBackend::Memory => bail!("The memory backend has nothing to migrate"),
+        _ if dry_run => {
+            println!("The SQL backends create any missing tables when connecting; there is nothing to list");
+            Ok(())
+        }
+        _ => {
+            let store: Arc<dyn FormStore> = store::connect(&config.storage).await?;
+            store.close().await;
+            println!("Database schema is up to date");
+            Ok(())
+        }
+    }
+}
+
+async fn forms(command: FormsCommand, store: &dyn FormStore, index_dir: &Path) -> Result<()> {
+    match command {
+        FormsCommand::Create { file } => {
+            let contents = read(&file)?;
+            let schema = definition::parse(&contents, definition::Format::from_path(&file))
+                .with_context(|| format!("Failed to load {}", file.display()))?;
+            definition::validate(&schema).with_context(|| format!("Failed to load {}", file.display()))?;
+            let id = store.create_form_schema(schema).await?;
+            println!("{}", id);
+        }
+        FormsCommand::ImportJsonSchema { file, dry_run } => {
+            let document = serde_json::from_str(&read(&file)?)
+                .with_context(|| format!("{} is not valid JSON", file.display()))?;
+            let (schema, warnings) = jsonschema::import(&document)?;
+            definition::validate(&schema)?;
+            for warning in &warnings {
+                eprintln!("warning: {}", warning);
+            }
+            if dry_run {
+                println!("{}", serde_json::to_string_pretty(&schema)?);
+            } else {
+                println!("{}", store.create_form_schema(schema).await?);
+            }
+        }
+        FormsCommand::List => {
+            for id in store.list_form_ids().await? {
+                let schema = store.get_form_schema(id).await?;
+                let responses = store.get_form_responses(id).await?.len();
+                println!("{}  {:>8} responses  {}", id, responses, schema.name);
+            }
+        }
+        FormsCommand::Show { id } => {
+            let schema = store.get_form_schema(id).await?;
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+        }
+        FormsCommand::Delete { id, yes } => {
+            let schema = store.get_form_schema(id).await?;
+            let responses = store.get_form_responses(id).await?.len();
+            let index = open_search_index(index_dir)?;
+            if !yes && !confirm(&format!("Delete form \"{}\" and its {} responses?", schema.name, responses))? {
+                bail!("Aborted");
+            }
+            store.delete_form(id).await?;
+            index.reindex_form(&schema, &[])?;
+            println!("Deleted form {}", id);
+        }
+    }
+    Ok(())
+}
+
+/// The search index, so deleted responses can be removed from it too. The
+/// server holds it while running, and must be stopped first.
+fn open_search_index(dir: &Path) -> Result<SearchIndex> {
+    SearchIndex::open(dir).context("Stop the server first, so deleted responses can be removed from the search index")
+}
+
+fn read(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+async fn responses(command: ResponsesCommand, store: &dyn FormStore, index_dir: &Path) -> Result<()> {
+    match command {
+        ResponsesCommand::Export {
+            form,
+            format,
+            filter,
+            sort,
+            output,
+        } => {
+            let schema = store.get_form_schema(form).await?;
+            let filter = ResponseFilter::compile(
+                &schema,
+                &ResponseQuery {
+                    filter,
+                    sort,
+                    ..Default::default()
+                },
+            )?;
+            let mut responses = store.get_form_responses(form).await?;
+            filter.apply(&mut responses);
+
+            let body = match format {
+                Format::Csv => export::to_csv(&schema, &responses)?,
+                Format::Ndjson => export::to_ndjson(&responses)?,
+            };
+            match output {
+                Some(path) => {
+                    fs::write(&path, body).with_context(|| format!("Failed to write {}", path.display()))?;
+                    eprintln!("Exported {} responses to {}", responses.len(), path.display());
+                }
+                None => io::stdout().write_all(&body)?,
+            }
+        }
+        ResponsesCommand::Import { form, file, no_validate } => {
+            let schema = store.get_form_schema(form).await?;
+            let contents = read(&file)?;
+
+            let mut responses = Vec::new();
+            let mut invalid = Vec::new();
+            for (number, line) in contents.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let mut response: FormResponse = serde_json::from_str(line)
+                    .with_context(|| format!("Invalid response on line {} of {}", number + 1, file.display()))?;
+                response.form_id = form;
+                response.created_at = response.created_at.or_else(|| Some(Utc::now()));
+                if !no_validate {
+                    let answers = validation::submission_from_data(&schema.fields, &response.data);
+                    match validation::coerce_submission(&schema, &answers) {
+                        Ok(data) => response.data = data,
+                        Err(e) => invalid.push(format!("line {}: {}", number + 1, e)),
+                    }
+                }
+                responses.push((number, response));
+            }
+            if !invalid.is_empty() {
+                bail!(
+                    "{} of {} responses fail validation, nothing was imported (use --no-validate to store them anyway):\n{}",
+                    invalid.len(),
+                    responses.len(),
+                    invalid.join("\n")
+                );
+            }
+
+            let imported = responses.len();
+            for (number, response) in responses {
+                store
+                    .submit_form_response(response)
+                    .await
+                    .with_context(|| format!("Failed to import line {}", number + 1))?;
+            }
+            println!("Imported {} responses", imported);
+            eprintln!("{}", REINDEX_HINT);
+        }
+        ResponsesCommand::Purge {
+            older_than_days,
+            before,
+            form,
+            yes,
+        } => {
+            let cutoff = match (before, older_than_days) {
+                (Some(before), _) => before,
+                (None, Some(days)) => Utc::now() - Duration::days(days.into()),
+                (None, None) => unreachable!("clap requires one of them"),
+            };
+            let forms = match form {
+                Some(form) => {
+                    store.get_form_schema(form).await?;
+                    vec![form]
+                }
+                None => store.list_form_ids().await?,
+            };
+
+            let scope = match form {
+                Some(form) => format!("form {}", form),
+                None => format!("all {} forms", forms.len()),
+            };
+            let index = open_search_index(index_dir)?;
+            if !yes && !confirm(&format!("Delete the responses of {} submitted before {}?", scope, cutoff.to_rfc3339()))? {
+                bail!("Aborted");
+            }
+
+            let mut purged = 0;
+            for id in forms {
+                let deleted = store.delete_responses_before(id, cutoff).await?;
+                if deleted > 0 {
+                    let schema = store.get_form_schema(id).await?;
+                    index.reindex_form(&schema, &store.get_form_responses(id).await?)?;
+                }
+                purged += deleted;
+            }
+            println!("Deleted {} responses", purged);
+        }
+    }
+    Ok(())
+}
+
+async fn api_keys(command: ApiKeysCommand, store: &dyn FormStore) -> Result<()> {
+    match command {
+        ApiKeysCommand::Create { name } => {
+            let (key, secret) = ApiKey::generate(&name);
+            store.create_api_key(&key).await?;
+            eprintln!("Created API key {} ({}); it will not be shown again:", key.id, key.name);
+            println!("{}", secret);
+        }
+        ApiKeysCommand::List => {
+            for key in store.list_api_keys().await? {
+                println!("{}  {}  {}", key.id, key.created_at.format("%Y-%m-%d %H:%M"), key.name);
+            }
+        }
+        ApiKeysCommand::Revoke { id } => {
+            if !store.delete_api_key(id).await? {
+                bail!("No API key with ID {}", id);
+            }
+            println!("Revoked API key {}", id);
+        }
+    }
+    Ok(())
+}
+
+/// Asks on the terminal, accepting `y` or `yes`.
+fn confirm(question: &str) -> Result<bool> {
+    eprint!("{} [y/N] ", question);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
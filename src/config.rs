@@ -0,0 +1,467 @@
+// src/config.rs
+//! Service configuration. Settings come from, in increasing precedence:
+//! built-in defaults, the TOML file given with `--config`, environment
+//! variables, and command-line flags.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use scylla::statement::Consistency;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::AuthConfig,
+    db::{self, Replication},
+    notifications::{SmtpConfig, SmtpSecurity},
+    shutdown::ShutdownConfig,
+    store::{StorageBackend, StorageConfig},
+    telemetry::{LogFormat, LoggingConfig},
+};
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Dynamic form service")]
+pub struct Cli {
+    /// TOML configuration file
+    #[arg(short, long, env = "FORM_PORTAL_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective configuration, with secrets redacted, and exit
+    #[arg(long, global = true)]
+    pub print_config: bool,
+
+    #[command(flatten)]
+    pub overrides: Overrides,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Apply pending ScyllaDB migrations
+    Migrate {
+        /// Print the pending migrations without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Re-index the stored responses of every form; the server must be stopped
+    RebuildSearchIndex,
+}
+
+/// Settings that can be overridden from the environment or the command line.
+#[derive(Debug, Args)]
+pub struct Overrides {
+    /// Address to listen on
+    #[arg(long, env = "LISTEN_ADDR", help_heading = "Server")]
+    host: Option<String>,
+    /// Port to listen on
+    #[arg(long, env = "PORT", help_heading = "Server")]
+    port: Option<u16>,
+    /// Number of HTTP worker threads [default: number of CPUs]
+    #[arg(long, env = "WORKERS", help_heading = "Server")]
+    workers: Option<usize>,
+    /// Largest accepted request body, in bytes
+    #[arg(long, env = "MAX_BODY_BYTES", help_heading = "Server")]
+    max_body_bytes: Option<usize>,
+    /// Directory with a `form.hbs` overriding the built-in form template
+    #[arg(long, env = "TEMPLATE_DIR", help_heading = "Server")]
+    template_dir: Option<PathBuf>,
+    /// Directory of the full-text search index
+    #[arg(long, env = "SEARCH_INDEX_DIR", help_heading = "Server")]
+    search_index_dir: Option<PathBuf>,
+    /// Milliseconds between search index commits
+    #[arg(long, env = "SEARCH_COMMIT_INTERVAL_MS", help_heading = "Server")]
+    search_commit_interval_ms: Option<u64>,
+    /// Seconds in-flight requests and queued emails get to finish on shutdown
+    #[arg(long, env = "SHUTDOWN_TIMEOUT", help_heading = "Server")]
+    shutdown_timeout: Option<u64>,
+    /// Seconds readiness fails on SIGTERM before the listener closes
+    #[arg(long, env = "SHUTDOWN_READINESS_DELAY", help_heading = "Server")]
+    shutdown_readiness_delay: Option<u64>,
+    /// Require an API key on the management routes under /api
+    #[arg(long, env = "REQUIRE_API_KEY", value_name = "BOOL", value_parser = clap::builder::BoolishValueParser::new(), help_heading = "Server")]
+    require_api_key: Option<bool>,
+
+    #[arg(long, env = "LOG_FORMAT", value_enum, help_heading = "Logging")]
+    log_format: Option<LogFormat>,
+    /// Log filter, e.g. `info` or `form_portal=debug`; `RUST_LOG` wins over it
+    #[arg(long, env = "LOG_LEVEL", help_heading = "Logging")]
+    log_level: Option<String>,
+    /// OpenTelemetry collector to export traces to (OTLP/gRPC)
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT", help_heading = "Logging")]
+    otlp_endpoint: Option<String>,
+
+    #[arg(long, env = "STORAGE_BACKEND", value_enum, help_heading = "Storage")]
+    storage_backend: Option<StorageBackend>,
+    /// Connection URL of the postgres and sqlite backends
+    #[arg(long, env = "DATABASE_URL", help_heading = "Storage")]
+    database_url: Option<String>,
+    #[arg(long, env = "MIGRATE_ON_STARTUP", value_name = "BOOL", value_parser = clap::builder::BoolishValueParser::new(), help_heading = "Storage")]
+    migrate_on_startup: Option<bool>,
+    #[arg(long, env = "DB_CONNECT_ATTEMPTS", help_heading = "Storage")]
+    db_connect_attempts: Option<u32>,
+
+    /// Comma-separated contact points
+    #[arg(long, env = "SCYLLA_NODES", value_delimiter = ',', help_heading = "ScyllaDB")]
+    scylla_nodes: Option<Vec<String>>,
+    #[arg(long, env = "SCYLLA_USERNAME", help_heading = "ScyllaDB")]
+    scylla_username: Option<String>,
+    #[arg(long, env = "SCYLLA_PASSWORD", hide_env_values = true, help_heading = "ScyllaDB")]
+    scylla_password: Option<String>,
+    #[arg(long, env = "SCYLLA_TLS", value_name = "BOOL", value_parser = clap::builder::BoolishValueParser::new(), help_heading = "ScyllaDB")]
+    scylla_tls: Option<bool>,
+    #[arg(long, env = "SCYLLA_TLS_CA", help_heading = "ScyllaDB")]
+    scylla_tls_ca: Option<String>,
+    #[arg(long, env = "SCYLLA_TLS_CERT", help_heading = "ScyllaDB")]
+    scylla_tls_cert: Option<String>,
+    #[arg(long, env = "SCYLLA_TLS_KEY", help_heading = "ScyllaDB")]
+    scylla_tls_key: Option<String>,
+    #[arg(long, env = "SCYLLA_LOCAL_DC", help_heading = "ScyllaDB")]
+    scylla_local_dc: Option<String>,
+    #[arg(long, env = "SCYLLA_READ_CONSISTENCY", value_parser = db::parse_consistency, help_heading = "ScyllaDB")]
+    scylla_read_consistency: Option<Consistency>,
+    #[arg(long, env = "SCYLLA_WRITE_CONSISTENCY", value_parser = db::parse_consistency, help_heading = "ScyllaDB")]
+    scylla_write_consistency: Option<Consistency>,
+    #[arg(long, env = "SCYLLA_KEYSPACE", help_heading = "ScyllaDB")]
+    scylla_keyspace: Option<String>,
+    /// A replication factor, or `dc1:3,dc2:3` for NetworkTopologyStrategy
+    #[arg(long, env = "SCYLLA_REPLICATION", value_parser = parse_replication, help_heading = "ScyllaDB")]
+    scylla_replication: Option<Replication>,
+
+    #[arg(long, env = "SMTP_HOST", help_heading = "Email")]
+    smtp_host: Option<String>,
+    #[arg(long, env = "SMTP_PORT", help_heading = "Email")]
+    smtp_port: Option<u16>,
+    #[arg(long, env = "SMTP_SECURITY", value_enum, help_heading = "Email")]
+    smtp_security: Option<SmtpSecurity>,
+    #[arg(long, env = "SMTP_USERNAME", help_heading = "Email")]
+    smtp_username: Option<String>,
+    #[arg(long, env = "SMTP_PASSWORD", hide_env_values = true, help_heading = "Email")]
+    smtp_password: Option<String>,
+    #[arg(long, env = "SMTP_FROM", help_heading = "Email")]
+    smtp_from: Option<String>,
+    #[arg(long, env = "SMTP_MAX_ATTEMPTS", help_heading = "Email")]
+    smtp_max_attempts: Option<u32>,
+    #[arg(long, env = "SMTP_QUEUE_SIZE", help_heading = "Email")]
+    smtp_queue_size: Option<usize>,
+}
+
+fn parse_replication(value: &str) -> Result<Replication> {
+    value.parse()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub storage: StorageConfig,
+    pub search: SearchConfig,
+    pub templates: TemplateConfig,
+    pub limits: LimitsConfig,
+    pub logging: LoggingConfig,
+    pub smtp: SmtpConfig,
+    pub shutdown: ShutdownConfig,
+    pub auth: AuthConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// Number of HTTP worker threads; one per CPU when unset.
+    pub workers: Option<usize>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            workers: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    pub index_dir: PathBuf,
+    /// How often new responses are committed to the index, in milliseconds;
+    /// a response can be found once its commit ran.
+    pub commit_interval_ms: u64,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            index_dir: PathBuf::from("data/search-index"),
+            commit_interval_ms: 1000,
+        }
+    }
+}
+
+impl SearchConfig {
+    pub fn commit_interval(&self) -> Duration {
+        Duration::from_millis(self.commit_interval_ms)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplateConfig {
+    /// Directory with a `form.hbs` that replaces the built-in form template.
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Largest accepted JSON or form request body, in bytes.
+    pub max_body_bytes: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 1024 * 1024,
+        }
+    }
+}
+
+impl Config {
+    /// Layers the configuration file, environment and flags over the
+    /// defaults, then validates the result.
+    pub fn load(path: Option<&Path>, overrides: &Overrides) -> Result<Self> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        overrides.apply(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read configuration file {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid configuration file {}", path.display()))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.server.host.trim().is_empty() {
+            bail!("server.host must not be empty");
+        }
+        if self.server.port == 0 {
+            bail!("server.port must not be 0");
+        }
+        if self.server.workers == Some(0) {
+            bail!("server.workers must be at least 1");
+        }
+        if self.limits.max_body_bytes == 0 {
+            bail!("limits.max_body_bytes must be at least 1");
+        }
+        if self.search.commit_interval_ms == 0 {
+            bail!("search.commit_interval_ms must be at least 1");
+        }
+        if let Some(dir) = &self.templates.dir {
+            if !dir.is_dir() {
+                bail!("templates.dir {} is not a directory", dir.display());
+            }
+        }
+        self.logging.validate().context("Invalid logging configuration")?;
+        self.storage.validate().context("Invalid storage configuration")?;
+        self.smtp.validate().context("Invalid SMTP configuration")?;
+        Ok(())
+    }
+
+    /// The configuration as TOML, with passwords replaced.
+    pub fn to_redacted_toml(&self) -> Result<String> {
+        let mut config = self.clone();
+        for secret in [&mut config.storage.scylla.password, &mut config.smtp.password] {
+            if secret.is_some() {
+                *secret = Some(REDACTED.to_string());
+            }
+        }
+        Ok(toml::to_string_pretty(&config)?)
+    }
+}
+
+impl Overrides {
+    fn apply(&self, config: &mut Config) {
+        fn set<T: Clone>(target: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *target = value.clone();
+            }
+        }
+        fn set_some<T: Clone>(target: &mut Option<T>, value: &Option<T>) {
+            if value.is_some() {
+                *target = value.clone();
+            }
+        }
+
+        let server = &mut config.server;
+        set(&mut server.host, &self.host);
+        set(&mut server.port, &self.port);
+        set_some(&mut server.workers, &self.workers);
+        set(&mut config.limits.max_body_bytes, &self.max_body_bytes);
+        set_some(&mut config.templates.dir, &self.template_dir);
+        set(&mut config.search.index_dir, &self.search_index_dir);
+        set(&mut config.search.commit_interval_ms, &self.search_commit_interval_ms);
+        set(&mut config.shutdown.timeout_secs, &self.shutdown_timeout);
+        set(&mut config.shutdown.readiness_delay_secs, &self.shutdown_readiness_delay);
+        set(&mut config.auth.require_api_key, &self.require_api_key);
+
+        let logging = &mut config.logging;
+        set(&mut logging.format, &self.log_format);
+        set(&mut logging.level, &self.log_level);
+        set_some(&mut logging.otlp_endpoint, &self.otlp_endpoint);
+
+        let storage = &mut config.storage;
+        set(&mut storage.backend, &self.storage_backend);
+        set_some(&mut storage.database_url, &self.database_url);
+        set(&mut storage.migrate_on_startup, &self.migrate_on_startup);
+        set(&mut storage.connect_attempts, &self.db_connect_attempts);
+
+        let scylla = &mut storage.scylla;
+        set(&mut scylla.nodes, &self.scylla_nodes);
+        set_some(&mut scylla.username, &self.scylla_username);
+        set_some(&mut scylla.password, &self.scylla_password);
+        set_some(&mut scylla.tls.enabled, &self.scylla_tls);
+        set_some(&mut scylla.tls.ca_file, &self.scylla_tls_ca);
+        set_some(&mut scylla.tls.cert_file, &self.scylla_tls_cert);
+        set_some(&mut scylla.tls.key_file, &self.scylla_tls_key);
+        set_some(&mut scylla.local_datacenter, &self.scylla_local_dc);
+        set(&mut scylla.read_consistency, &self.scylla_read_consistency);
+        set(&mut scylla.write_consistency, &self.scylla_write_consistency);
+        set(&mut scylla.keyspace, &self.scylla_keyspace);
+        set(&mut scylla.replication, &self.scylla_replication);
+
+        let smtp = &mut config.smtp;
+        set_some(&mut smtp.host, &self.smtp_host);
+        set_some(&mut smtp.port, &self.smtp_port);
+        set(&mut smtp.security, &self.smtp_security);
+        set_some(&mut smtp.username, &self.smtp_username);
+        set_some(&mut smtp.password, &self.smtp_password);
+        set(&mut smtp.from, &self.smtp_from);
+        set(&mut smtp.max_attempts, &self.smtp_max_attempts);
+        set(&mut smtp.queue_size, &self.smtp_queue_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::Mutex;
+    use tempfile::NamedTempFile;
+
+    /// Every variable read through `Overrides` is process-wide, so tests that
+    /// load a configuration take turns.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    /// Loads a configuration from the given file contents and flags, with
+    /// the given environment variables set meanwhile.
+    fn load(file: Option<&str>, env: &[(&str, &str)], args: &[&str]) -> Result<Config> {
+        let _turn = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        let file = file.map(|contents| {
+            let mut file = NamedTempFile::new().unwrap();
+            file.write_all(contents.as_bytes()).unwrap();
+            file
+        });
+        for (name, value) in env {
+            std::env::set_var(name, value);
+        }
+        let loaded = Cli::try_parse_from(std::iter::once("form_portal").chain(args.iter().copied()))
+            .map_err(anyhow::Error::from)
+            .and_then(|cli| Config::load(file.as_ref().map(|f| f.path()), &cli.overrides));
+        for (name, _) in env {
+            std::env::remove_var(name);
+        }
+        loaded
+    }
+
+    fn rejected(file: Option<&str>, args: &[&str]) -> String {
+        format!("{:#}", load(file, &[], args).unwrap_err())
+    }
+
+    #[test]
+    fn layers_file_environment_and_flags_over_the_defaults() {
+        let defaults = load(None, &[], &[]).unwrap();
+        assert_eq!((defaults.server.host.as_str(), defaults.server.port), ("127.0.0.1", 8080));
+        assert_eq!(defaults.limits.max_body_bytes, 1024 * 1024);
+
+        let file = "[server]\nhost = \"0.0.0.0\"\nport = 8081\nworkers = 3\n\n\
+                    [limits]\nmax_body_bytes = 2048\n\n\
+                    [storage.scylla]\nkeyspace = \"from_file\"\n";
+        let env = [("PORT", "8082"), ("MAX_BODY_BYTES", "4096"), ("SCYLLA_KEYSPACE", "from_env")];
+        let config = load(Some(file), &env, &["--port", "8083"]).unwrap();
+
+        // The file wins over the defaults
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert_eq!(config.server.workers, Some(3));
+        // The environment wins over the file
+        assert_eq!(config.limits.max_body_bytes, 4096);
+        assert_eq!(config.storage.scylla.keyspace, "from_env");
+        // Flags win over the environment
+        assert_eq!(config.server.port, 8083);
+        // What nothing sets keeps its default
+        assert_eq!(config.shutdown.timeout_secs, defaults.shutdown.timeout_secs);
+        assert_eq!(config.search.index_dir, defaults.search.index_dir);
+    }
+
+    #[test]
+    fn rejects_invalid_keyspaces() {
+        let too_long = "k".repeat(49);
+        for keyspace in ["1forms", "form-portal", "forms; DROP KEYSPACE system", too_long.as_str()] {
+            let error = rejected(None, &["--scylla-keyspace", keyspace]);
+            assert!(error.contains("Invalid keyspace name"), "{}: {}", keyspace, error);
+        }
+        let error = rejected(Some("[storage.scylla]\nkeyspace = \"\"\n"), &[]);
+        assert!(error.contains("Invalid keyspace name"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_invalid_datacenters() {
+        let error = rejected(None, &["--scylla-replication", "dc1:3,dc'2:3"]);
+        assert!(error.contains("Invalid datacenter name: dc'2"), "{}", error);
+
+        let error = rejected(Some("[storage.scylla]\nreplication = \"eu west:3\"\n"), &[]);
+        assert!(error.contains("Invalid datacenter name: eu west"), "{}", error);
+
+        let error = rejected(Some("[storage.scylla]\nreplication = \"dc1:three\"\n"), &[]);
+        assert!(error.contains("Invalid replication factor for dc1"), "{}", error);
+
+        let error = rejected(None, &["--scylla-local-dc="]);
+        assert!(error.contains("Invalid datacenter name"), "{}", error);
+
+        let config = load(None, &[], &["--scylla-local-dc", "eu-west_1", "--scylla-replication", "eu-west_1:3"]).unwrap();
+        assert_eq!(config.storage.scylla.local_datacenter.as_deref(), Some("eu-west_1"));
+        assert_eq!(
+            config.storage.scylla.replication,
+            Replication::NetworkTopology(vec![("eu-west_1".to_string(), 3)])
+        );
+    }
+
+    #[test]
+    fn rejects_other_invalid_values() {
+        for (args, message) in [
+            (&["--port", "0"][..], "server.port must not be 0"),
+            (&["--workers", "0"], "server.workers must be at least 1"),
+            (&["--max-body-bytes", "0"], "limits.max_body_bytes must be at least 1"),
+            (&["--search-commit-interval-ms", "0"], "search.commit_interval_ms must be at least 1"),
+            (&["--scylla-replication", "0"], "The replication factor must be at least 1"),
+            (&["--scylla-username", "forms"], "username and password must be set together"),
+        ] {
+            let error = rejected(None, args);
+            assert!(error.contains(message), "{:?}: {}", args, error);
+        }
+
+        let error = rejected(Some("[server]\nprot = 8080\n"), &[]);
+        assert!(error.contains("unknown field `prot`"), "{}", error);
+    }
+}
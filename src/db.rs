@@ -1,28 +1,304 @@
 // src/db.rs
-use anyhow::Result;
-use scylla::{Session, FromRow};
-use scylla::frame::response::result::CqlValue;
-use serde_json::Value;
-use std::sync::Arc;
-use uuid::Uuid;
-use chrono::Utc;
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tracing::{info, instrument, warn};
+use openssl::ssl::{SslContext, SslContextBuilder, SslFiletype, SslMethod, SslVerifyMode};
+use regex::Regex;
+use scylla::frame::value::Timestamp;
+use scylla::load_balancing::DefaultPolicy;
+use scylla::prepared_statement::PreparedStatement;
+use scylla::statement::Consistency;
+use scylla::transport::errors::QueryError;
+use scylla::{ExecutionProfile, FromRow, Session, SessionBuilder};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+use uuid::Uuid;
 
 use crate::{
+    auth::ApiKey,
     error::AppError,
-    models::{FormResponse, FormSchema},
+    metrics::time_db,
+    migrations,
+    models::{FormResponse, FormSchema, NotificationSettings},
+    store::FormStore,
 };
 
-// Keep FromRow derive for potential future use or if parts still use it,
-// but the manual parsing logic below overrides it for now.
+/// How to reach the ScyllaDB cluster and lay out the keyspace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScyllaConfig {
+    /// Contact points as `host:port`; the rest of the cluster is discovered.
+    pub nodes: Vec<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls: TlsConfig,
+    /// Datacenter to route requests to; nodes elsewhere are not contacted.
+    pub local_datacenter: Option<String>,
+    #[serde(with = "consistency")]
+    pub read_consistency: Consistency,
+    #[serde(with = "consistency")]
+    pub write_consistency: Consistency,
+    pub keyspace: String,
+    /// Replication of the keyspace, used only when it is first created.
+    #[serde(with = "replication")]
+    pub replication: Replication,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Defaults to on when a CA or client certificate is given.
+    pub enabled: Option<bool>,
+    /// CA bundle used to verify the nodes; the system store when absent.
+    pub ca_file: Option<String>,
+    /// Client certificate and key, for clusters requiring mutual TLS.
+    pub cert_file: Option<String>,
+    pub key_file: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+            .unwrap_or(self.ca_file.is_some() || self.cert_file.is_some())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Replication {
+    Simple(u32),
+    NetworkTopology(Vec<(String, u32)>),
+}
+
+impl Default for ScyllaConfig {
+    fn default() -> Self {
+        Self {
+            nodes: vec!["127.0.0.1:9042".to_string()],
+            username: None,
+            password: None,
+            tls: TlsConfig::default(),
+            local_datacenter: None,
+            read_consistency: Consistency::LocalQuorum,
+            write_consistency: Consistency::LocalQuorum,
+            keyspace: "form_portal".to_string(),
+            replication: Replication::Simple(1),
+        }
+    }
+}
+
+impl ScyllaConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.nodes.is_empty() {
+            bail!("At least one ScyllaDB node is required");
+        }
+        if self.username.is_some() != self.password.is_some() {
+            bail!("The ScyllaDB username and password must be set together");
+        }
+        if self.tls.cert_file.is_some() != self.tls.key_file.is_some() {
+            bail!("The ScyllaDB client certificate and key must be set together");
+        }
+        // The keyspace is interpolated into every statement
+        let identifier = Regex::new(r"^[A-Za-z][A-Za-z0-9_]{0,47}$").unwrap();
+        if !identifier.is_match(&self.keyspace) {
+            bail!("Invalid keyspace name: {}", self.keyspace);
+        }
+        if let Some(datacenter) = &self.local_datacenter {
+            check_datacenter(datacenter)?;
+        }
+        match &self.replication {
+            Replication::Simple(0) => bail!("The replication factor must be at least 1"),
+            Replication::NetworkTopology(dcs) if dcs.is_empty() => {
+                bail!("NetworkTopologyStrategy needs at least one datacenter")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn ssl_context(&self) -> Result<Option<SslContext>> {
+        if !self.tls.is_enabled() {
+            return Ok(None);
+        }
+        let tls = &self.tls;
+
+        let mut builder = SslContextBuilder::new(SslMethod::tls())?;
+        builder.set_verify(SslVerifyMode::PEER);
+        match &tls.ca_file {
+            Some(ca_file) => builder
+                .set_ca_file(ca_file)
+                .with_context(|| format!("Failed to load CA file {}", ca_file))?,
+            None => builder.set_default_verify_paths()?,
+        }
+        if let (Some(cert_file), Some(key_file)) = (&tls.cert_file, &tls.key_file) {
+            builder
+                .set_certificate_chain_file(cert_file)
+                .with_context(|| format!("Failed to load client certificate {}", cert_file))?;
+            builder
+                .set_private_key_file(key_file, SslFiletype::PEM)
+                .with_context(|| format!("Failed to load client key {}", key_file))?;
+        }
+        Ok(Some(builder.build()))
+    }
+}
+
+impl Replication {
+    fn to_cql(&self) -> String {
+        match self {
+            Replication::Simple(factor) => {
+                format!("{{'class': 'SimpleStrategy', 'replication_factor': {}}}", factor)
+            }
+            Replication::NetworkTopology(dcs) => {
+                let factors: Vec<String> = dcs.iter().map(|(dc, factor)| format!("'{}': {}", dc, factor)).collect();
+                format!("{{'class': 'NetworkTopologyStrategy', {}}}", factors.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Replication {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Replication::Simple(factor) => write!(f, "{}", factor),
+            Replication::NetworkTopology(dcs) => {
+                let pairs: Vec<String> = dcs.iter().map(|(dc, factor)| format!("{}:{}", dc, factor)).collect();
+                write!(f, "{}", pairs.join(","))
+            }
+        }
+    }
+}
+
+impl FromStr for Replication {
+    type Err = anyhow::Error;
+
+    /// Parses either a bare replication factor (`SimpleStrategy`) or a list
+    /// of `datacenter:factor` pairs (`NetworkTopologyStrategy`), e.g. `dc1:3,dc2:3`.
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if let Ok(factor) = value.parse() {
+            return Ok(Replication::Simple(factor));
+        }
+
+        value
+            .split(',')
+            .map(|pair| {
+                let (dc, factor) = pair
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("Expected `datacenter:factor`, got `{}`", pair.trim()))?;
+                let dc = dc.trim();
+                check_datacenter(dc)?;
+                let factor = factor
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid replication factor for {}", dc))?;
+                Ok((dc.to_string(), factor))
+            })
+            .collect::<Result<_>>()
+            .map(Replication::NetworkTopology)
+    }
+}
+
+/// Only plain datacenter names are accepted, as those of the replication
+/// settings are interpolated into CQL.
+fn check_datacenter(name: &str) -> Result<()> {
+    let datacenter = Regex::new(r"^[A-Za-z0-9_.-]+$").unwrap();
+    if !datacenter.is_match(name) {
+        bail!("Invalid datacenter name: {}", name);
+    }
+    Ok(())
+}
+
+pub fn parse_consistency(level: &str) -> Result<Consistency> {
+    Ok(match level.trim().to_uppercase().as_str() {
+        "ANY" => Consistency::Any,
+        "ONE" => Consistency::One,
+        "TWO" => Consistency::Two,
+        "THREE" => Consistency::Three,
+        "QUORUM" => Consistency::Quorum,
+        "ALL" => Consistency::All,
+        "LOCAL_QUORUM" => Consistency::LocalQuorum,
+        "EACH_QUORUM" => Consistency::EachQuorum,
+        "LOCAL_ONE" => Consistency::LocalOne,
+        other => bail!("Unknown consistency level: {}", other),
+    })
+}
+
+/// Consistency levels as written in CQL, e.g. `LOCAL_QUORUM`.
+mod consistency {
+    use scylla::statement::Consistency;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(level: &Consistency, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = match level {
+            Consistency::Any => "ANY",
+            Consistency::One => "ONE",
+            Consistency::Two => "TWO",
+            Consistency::Three => "THREE",
+            Consistency::Quorum => "QUORUM",
+            Consistency::All => "ALL",
+            Consistency::LocalQuorum => "LOCAL_QUORUM",
+            Consistency::EachQuorum => "EACH_QUORUM",
+            Consistency::LocalOne => "LOCAL_ONE",
+        };
+        serializer.serialize_str(name)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Consistency, D::Error> {
+        super::parse_consistency(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// Replication in its textual form, so it reads the same in files and flags.
+mod replication {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::Replication;
+
+    pub fn serialize<S: Serializer>(replication: &Replication, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(replication)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Replication, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Factor(u32),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Factor(factor) => Ok(Replication::Simple(factor)),
+            Raw::Text(text) => text.parse().map_err(D::Error::custom),
+        }
+    }
+}
+
 #[derive(FromRow)]
 struct FormSchemaRow {
     id: Uuid,
     name: String,
     description: Option<String>,
     fields: String,
-    created_at: Option<chrono::DateTime<chrono::Utc>>,
-    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    created_at: Option<Timestamp>,
+    updated_at: Option<Timestamp>,
+}
+
+impl TryFrom<FormSchemaRow> for FormSchema {
+    type Error = AppError;
+
+    fn try_from(row: FormSchemaRow) -> Result<Self, AppError> {
+        Ok(FormSchema {
+            id: Some(row.id),
+            name: row.name,
+            description: row.description,
+            fields: from_json(&row.fields, "fields")?,
+            created_at: row.created_at.map(to_datetime),
+            updated_at: row.updated_at.map(to_datetime),
+        })
+    }
 }
 
 #[derive(FromRow)]
@@ -30,215 +306,683 @@ struct FormResponseRow {
     id: Uuid,
     form_id: Uuid,
     data: String,
-    created_at: chrono::DateTime<chrono::Utc>,
+    created_at: Timestamp,
 }
 
-pub async fn init_database(session: &Session) -> Result<()> {
-    // Create keyspace
-    session
-        .query(
-            "CREATE KEYSPACE IF NOT EXISTS form_portal WITH REPLICATION = {'class': 'SimpleStrategy', 'replication_factor': 1}",
-            &[],
-        )
-        .await?;
+impl TryFrom<FormResponseRow> for FormResponse {
+    type Error = AppError;
+
+    fn try_from(row: FormResponseRow) -> Result<Self, AppError> {
+        Ok(FormResponse {
+            id: Some(row.id),
+            form_id: row.form_id,
+            data: from_json(&row.data, "response data")?,
+            created_at: Some(to_datetime(row.created_at)),
+        })
+    }
+}
+
+/// CQL timestamps arrive as a duration since the epoch, which chrono's
+/// `FromCqlVal` impl does not accept.
+fn to_datetime(timestamp: Timestamp) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(timestamp.0.num_milliseconds()).unwrap_or_default()
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T, what: &str) -> Result<String, AppError> {
+    serde_json::to_string(value).map_err(|e| AppError::InternalError(format!("Failed to serialize {}: {}", what, e)))
+}
+
+fn from_json<T: DeserializeOwned>(json: &str, what: &str) -> Result<T, AppError> {
+    serde_json::from_str(json).map_err(|e| AppError::InternalError(format!("Failed to deserialize {}: {}", what, e)))
+}
+
+#[derive(FromRow)]
+struct ApiKeyRow {
+    key_hash: String,
+    id: Uuid,
+    name: String,
+    created_at: Timestamp,
+}
+
+impl From<ApiKeyRow> for ApiKey {
+    fn from(row: ApiKeyRow) -> Self {
+        ApiKey {
+            id: row.id,
+            name: row.name,
+            key_hash: row.key_hash,
+            created_at: to_datetime(row.created_at),
+        }
+    }
+}
 
-    // Create form_schemas table
+fn row_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalError(format!("Failed to decode row: {}", e))
+}
+
+/// Statements on the fixed tables, prepared once when connecting.
+struct Statements {
+    insert_schema: PreparedStatement,
+    select_schema: PreparedStatement,
+    select_form_ids: PreparedStatement,
+    upsert_notifications: PreparedStatement,
+    select_notifications: PreparedStatement,
+    delete_schema: PreparedStatement,
+    delete_notifications: PreparedStatement,
+    insert_api_key: PreparedStatement,
+    select_api_keys: PreparedStatement,
+    select_api_key: PreparedStatement,
+    delete_api_key: PreparedStatement,
+}
+
+impl Statements {
+    async fn prepare(session: &Session, config: &ScyllaConfig) -> Result<Self, QueryError> {
+        let (keyspace, read, write) = (&config.keyspace, config.read_consistency, config.write_consistency);
+        Ok(Self {
+            insert_schema: prepare(
+                session,
+                format!("INSERT INTO {}.form_schemas (id, name, description, fields, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)", keyspace),
+                write,
+            )
+            .await?,
+            select_schema: prepare(
+                session,
+                format!("SELECT id, name, description, fields, created_at, updated_at FROM {}.form_schemas WHERE id = ?", keyspace),
+                read,
+            )
+            .await?,
+            select_form_ids: prepare(session, format!("SELECT id FROM {}.form_schemas", keyspace), read).await?,
+            upsert_notifications: prepare(
+                session,
+                format!("INSERT INTO {}.form_notifications (form_id, settings, updated_at) VALUES (?, ?, ?)", keyspace),
+                write,
+            )
+            .await?,
+            select_notifications: prepare(
+                session,
+                format!("SELECT settings FROM {}.form_notifications WHERE form_id = ?", keyspace),
+                read,
+            )
+            .await?,
+            delete_schema: prepare(session, format!("DELETE FROM {}.form_schemas WHERE id = ?", keyspace), write).await?,
+            delete_notifications: prepare(
+                session,
+                format!("DELETE FROM {}.form_notifications WHERE form_id = ?", keyspace),
+                write,
+            )
+            .await?,
+            insert_api_key: prepare(
+                session,
+                format!("INSERT INTO {}.api_keys (key_hash, id, name, created_at) VALUES (?, ?, ?, ?)", keyspace),
+                write,
+            )
+            .await?,
+            select_api_keys: prepare(
+                session,
+                format!("SELECT key_hash, id, name, created_at FROM {}.api_keys", keyspace),
+                read,
+            )
+            .await?,
+            select_api_key: prepare(
+                session,
+                format!("SELECT key_hash, id, name, created_at FROM {}.api_keys WHERE key_hash = ?", keyspace),
+                read,
+            )
+            .await?,
+            delete_api_key: prepare(session, format!("DELETE FROM {}.api_keys WHERE key_hash = ?", keyspace), write)
+                .await?,
+        })
+    }
+}
+
+/// Statements on one form's responses table, prepared on first use.
+struct ResponseStatements {
+    insert: PreparedStatement,
+    select_all: PreparedStatement,
+    select_created_at: PreparedStatement,
+    delete: PreparedStatement,
+}
+
+async fn prepare(session: &Session, cql: String, consistency: Consistency) -> Result<PreparedStatement, QueryError> {
+    let mut statement = session.prepare(cql).await?;
+    statement.set_consistency(consistency);
+    // Writes use client-generated ids, so every statement is safe to retry
+    statement.set_is_idempotent(true);
+    Ok(statement)
+}
+
+/// How long `close` waits for running queries before letting go of the session.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// ScyllaDB implementation of [`FormStore`]. Responses of each form live in
+/// their own `form_responses_<id>` table.
+pub struct ScyllaStore {
+    /// Taken on `close`, after which queries fail.
+    session: Mutex<Option<Arc<Session>>>,
+    config: ScyllaConfig,
+    statements: Statements,
+    response_statements: RwLock<HashMap<Uuid, Arc<ResponseStatements>>>,
+}
+
+impl ScyllaStore {
+    /// Connects and prepares the keyspace, applying pending migrations
+    /// unless `migrate` is false.
+    #[instrument(skip_all, fields(keyspace = %config.keyspace))]
+    pub async fn connect(config: ScyllaConfig, migrate: bool) -> Result<Self> {
+        let session = connect_session(&config).await?;
+
+        // Initialize the keyspace and tables
+        init_database(&session, &config, migrate).await?;
+
+        let statements = Statements::prepare(&session, &config).await?;
+
+        Ok(Self {
+            session: Mutex::new(Some(Arc::new(session))),
+            config,
+            statements,
+            response_statements: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// The session, unless the store was closed.
+    fn session(&self) -> Result<Arc<Session>, AppError> {
+        self.session
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .ok_or_else(|| AppError::DbError("The database session is closed".to_string()))
+    }
+
+    #[instrument(skip(self))]
+    async fn response_statements(&self, form_id: Uuid) -> Result<Arc<ResponseStatements>, AppError> {
+        if let Some(statements) = self.response_statements.read().await.get(&form_id) {
+            return Ok(statements.clone());
+        }
+
+        let table_name = response_table(&self.config.keyspace, form_id);
+        let prepare_error = |e: QueryError| AppError::DbError(format!("Failed to prepare form response statements: {}", e));
+        let statements = Arc::new(ResponseStatements {
+            insert: prepare(
+                &*self.session()?,
+                format!("INSERT INTO {} (id, form_id, data, created_at) VALUES (?, ?, ?, ?)", table_name),
+                self.config.write_consistency,
+            )
+            .await
+            .map_err(prepare_error)?,
+            select_all: prepare(
+                &*self.session()?,
+                format!("SELECT id, form_id, data, created_at FROM {}", table_name),
+                self.config.read_consistency,
+            )
+            .await
+            .map_err(prepare_error)?,
+            select_created_at: prepare(
+                &*self.session()?,
+                format!("SELECT id, created_at FROM {}", table_name),
+                self.config.read_consistency,
+            )
+            .await
+            .map_err(prepare_error)?,
+            delete: prepare(
+                &*self.session()?,
+                format!("DELETE FROM {} WHERE id = ?", table_name),
+                self.config.write_consistency,
+            )
+            .await
+            .map_err(prepare_error)?,
+        });
+
+        self.response_statements
+            .write()
+            .await
+            .insert(form_id, statements.clone());
+        Ok(statements)
+    }
+}
+
+#[instrument(skip_all, fields(nodes = ?config.nodes))]
+pub async fn connect_session(config: &ScyllaConfig) -> Result<Session> {
+    let mut policy = DefaultPolicy::builder().token_aware(true);
+    if let Some(datacenter) = &config.local_datacenter {
+        policy = policy.prefer_datacenter(datacenter.clone());
+    }
+    // Schema changes and migrations run with the write consistency; prepared
+    // statements carry their own
+    let profile = ExecutionProfile::builder()
+        .load_balancing_policy(policy.build())
+        .consistency(config.write_consistency)
+        .build();
+
+    let mut builder = SessionBuilder::new()
+        .known_nodes(&config.nodes)
+        .default_execution_profile_handle(profile.into_handle())
+        .ssl_context(config.ssl_context()?);
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        builder = builder.user(username, password);
+    }
+
+    Ok(builder.build().await?)
+}
+
+/// Runs the `migrate` command: applies pending migrations, or only lists
+/// them with `dry_run`.
+#[instrument(skip(config))]
+pub async fn migrate(config: &ScyllaConfig, dry_run: bool) -> Result<()> {
+    let session = connect_session(config).await?;
+
+    if dry_run {
+        migrations::run(&session, &config.keyspace, true).await?;
+    } else {
+        init_database(&session, config, true).await?;
+    }
+
+    Ok(())
+}
+
+#[instrument(skip(session, config))]
+pub async fn init_database(session: &Session, config: &ScyllaConfig, migrate: bool) -> Result<()> {
+    // Create keyspace; the replication of an existing one is left untouched
     session
         .query(
-            "CREATE TABLE IF NOT EXISTS form_portal.form_schemas (
-                id uuid PRIMARY KEY,
-                name text,
-                description text,
-                fields text,
-                created_at timestamp,
-                updated_at timestamp
-            )",
+            format!(
+                "CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {}",
+                config.keyspace,
+                config.replication.to_cql()
+            ),
             &[],
         )
         .await?;
 
+    if migrate {
+        migrations::run(session, &config.keyspace, false).await?;
+    } else {
+        let pending = migrations::pending(session, &config.keyspace).await?;
+        if !pending.is_empty() {
+            warn!("{} database migrations are pending; run `form_portal migrate`", pending.len());
+        }
+    }
+
     Ok(())
 }
 
-pub async fn create_form_schema(session: &Arc<Session>, schema: FormSchema) -> Result<Uuid, AppError> {
-    let id = schema.id.unwrap_or_else(Uuid::new_v4);
+fn response_table(keyspace: &str, form_id: Uuid) -> String {
+    format!("{}.form_responses_{}", keyspace, form_id.to_string().replace("-", ""))
+}
 
-    let fields_json = serde_json::to_string(&schema.fields)
-        .map_err(|e| AppError::InternalError(format!("Failed to serialize fields: {}", e)))?;
+/// Each form stores its responses in a table of its own.
+#[instrument(skip(session, keyspace))]
+pub async fn create_response_table(session: &Session, keyspace: &str, form_id: Uuid) -> Result<(), AppError> {
+    let cql = format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            id uuid PRIMARY KEY,
+            form_id uuid,
+            data text,
+            created_at timestamp
+        )",
+        response_table(keyspace, form_id)
+    );
+    time_db("create_response_table", session.query(cql, &[]))
+        .await
+        .map_err(|e| AppError::DbError(format!("Failed to create form responses table: {}", e)))?;
 
-    let now = Utc::now();
+    Ok(())
+}
 
-    session
-        .query(
-            "INSERT INTO form_portal.form_schemas (id, name, description, fields, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
-            (
-                id,
-                schema.name,
-                schema.description,
-                fields_json,
-                now,
-                now,
+#[async_trait]
+impl FormStore for ScyllaStore {
+    #[instrument(skip_all, fields(name = %schema.name))]
+    async fn create_form_schema(&self, schema: FormSchema) -> Result<Uuid, AppError> {
+        let id = schema.id.unwrap_or_else(Uuid::new_v4);
+        let fields_json = to_json(&schema.fields, "fields")?;
+        let now = Utc::now();
+
+        time_db(
+            "insert_schema",
+            self.session()?.execute(
+                &self.statements.insert_schema,
+                (id, schema.name, schema.description, fields_json, now, now),
             ),
         )
         .await
         .map_err(|e| AppError::DbError(format!("Failed to insert form schema: {}", e)))?;
 
-    // Create a table for form responses
-    let table_name = format!("form_portal.form_responses_{}", id.to_string().replace("-", ""));
+        // Create a table for form responses
+        create_response_table(&*self.session()?, &self.config.keyspace, id).await?;
 
-    session
-        .query(
-            format!(
-                "CREATE TABLE IF NOT EXISTS {} (
-                    id uuid PRIMARY KEY,
-                    form_id uuid,
-                    data text,
-                    created_at timestamp
-                )",
-                table_name
-            ).as_str(),
-            &[],
+        Ok(id)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_form_schema(&self, id: Uuid) -> Result<FormSchema, AppError> {
+        let row = time_db("select_schema", self.session()?.execute(&self.statements.select_schema, (id,)))
+            .await
+            .map_err(|e| AppError::DbError(format!("Failed to fetch form schema: {}", e)))?
+            .maybe_first_row_typed::<FormSchemaRow>()
+            .map_err(row_error)?
+            .ok_or_else(|| AppError::NotFound(format!("Form schema with ID {} not found", id)))?;
+
+        row.try_into()
+    }
+
+    #[instrument(skip(self))]
+    async fn list_form_ids(&self) -> Result<Vec<Uuid>, AppError> {
+        time_db("select_form_ids", self.session()?.execute(&self.statements.select_form_ids, &[]))
+            .await
+            .map_err(|e| AppError::DbError(format!("Failed to list form schemas: {}", e)))?
+            .rows_typed_or_empty::<(Uuid,)>()
+            .map(|row| row.map(|(id,)| id).map_err(row_error))
+            .collect()
+    }
+
+    #[instrument(skip_all, fields(form_id = %response.form_id))]
+    async fn submit_form_response(&self, response: FormResponse) -> Result<Uuid, AppError> {
+        let id = response.id.unwrap_or_else(Uuid::new_v4);
+        let data_json = to_json(&response.data, "form data")?;
+        let created_at = response.created_at.unwrap_or_else(Utc::now);
+
+        let statements = self.response_statements(response.form_id).await?;
+        time_db(
+            "insert_response",
+            self.session()?.execute(&statements.insert, (id, response.form_id, data_json, created_at)),
         )
         .await
-        .map_err(|e| AppError::DbError(format!("Failed to create form responses table: {}", e)))?;
+        .map_err(|e| AppError::DbError(format!("Failed to insert form response: {}", e)))?;
 
-    Ok(id)
-}
+        Ok(id)
+    }
 
-// Fix in get_form_schema function:
-pub async fn get_form_schema(session: &Arc<Session>, id: Uuid) -> Result<FormSchema, AppError> {
-    let result = session
-        .query(
-            "SELECT id, name, description, fields, created_at, updated_at FROM form_portal.form_schemas WHERE id = ?",
-            (id,),
+    #[instrument(skip(self))]
+    async fn get_form_responses(&self, form_id: Uuid) -> Result<Vec<FormResponse>, AppError> {
+        let statements = self.response_statements(form_id).await?;
+        let mut responses = time_db("select_responses", self.session()?.execute(&statements.select_all, &[]))
+            .await
+            .map_err(|e| AppError::DbError(format!("Failed to fetch form responses: {}", e)))?
+            .rows_typed_or_empty::<FormResponseRow>()
+            .map(|row| row.map_err(row_error)?.try_into())
+            .collect::<Result<Vec<FormResponse>, AppError>>()?;
+        // Rows come back in token order; return them oldest first like the
+        // other stores
+        responses.sort_by_key(|r| (r.created_at, r.id));
+        Ok(responses)
+    }
+
+    #[instrument(skip(self, settings))]
+    async fn set_notification_settings(
+        &self,
+        form_id: Uuid,
+        settings: &NotificationSettings,
+    ) -> Result<(), AppError> {
+        let settings_json = to_json(settings, "notification settings")?;
+
+        time_db(
+            "upsert_notifications",
+            self.session()?.execute(&self.statements.upsert_notifications, (form_id, settings_json, Utc::now())),
         )
         .await
-        .map_err(|e| AppError::DbError(format!("Failed to fetch form schema: {}", e)))?;
-
-    let row = result.first_row()
-        .map_err(|_| AppError::NotFound(format!("Form schema with ID {} not found", id)))?;
-
-    // Parse the row manually using pattern matching for timestamp
-    let schema_row = FormSchemaRow {
-        id: row.columns[0].as_ref().and_then(|v| v.as_uuid()).ok_or_else(||
-            AppError::InternalError("Failed to get id column".to_string()))?,
-        name: row.columns[1].as_ref().and_then(|v| v.as_text()).ok_or_else(||
-            AppError::InternalError("Failed to get name column".to_string()))?.to_string(),
-        description: row.columns[2].as_ref().and_then(|v| v.as_text()).map(|s| s.to_string()),
-        fields: row.columns[3].as_ref().and_then(|v| v.as_text()).ok_or_else(||
-            AppError::InternalError("Failed to get fields column".to_string()))?.to_string(),
-        created_at: row.columns[4].as_ref().and_then(|v| match v {
-            CqlValue::Timestamp(ts) => {
-                // Convert TimeDelta to milliseconds i64
-                let millis = ts.num_milliseconds();
-                Some(chrono::DateTime::<chrono::Utc>::from_timestamp_millis(millis).unwrap_or_default())
-            },
-            _ => None,
-        }),
-        updated_at: row.columns[5].as_ref().and_then(|v| match v {
-            CqlValue::Timestamp(ts) => {
-                // Convert TimeDelta to milliseconds i64
-                let millis = ts.num_milliseconds();
-                Some(chrono::DateTime::<chrono::Utc>::from_timestamp_millis(millis).unwrap_or_default())
-            },
-            _ => None,
-        }),
-    };
-
-    let fields = serde_json::from_str(&schema_row.fields)
-        .map_err(|e| AppError::InternalError(format!("Failed to deserialize fields: {}", e)))?;
-
-    let schema = FormSchema {
-        id: Some(schema_row.id),
-        name: schema_row.name,
-        description: schema_row.description,
-        fields,
-        created_at: schema_row.created_at,
-        updated_at: schema_row.updated_at,
-    };
-
-    Ok(schema)
-}
-
-pub async fn submit_form_response(
-    session: &Arc<Session>,
-    response: FormResponse,
-) -> Result<Uuid, AppError> {
-    let id = response.id.unwrap_or_else(Uuid::new_v4);
-    let form_id = response.form_id;
-
-    let table_name = format!("form_portal.form_responses_{}", form_id.to_string().replace("-", ""));
-
-    let data_json = serde_json::to_string(&response.data)
-        .map_err(|e| AppError::InternalError(format!("Failed to serialize form data: {}", e)))?;
-
-    let now = Utc::now();
+        .map_err(|e| AppError::DbError(format!("Failed to store notification settings: {}", e)))?;
 
-    session
-        .query(
-            format!(
-                "INSERT INTO {} (id, form_id, data, created_at) VALUES (?, ?, ?, ?)",
-                table_name
-            ).as_str(),
-            (id, form_id, data_json, now),
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_notification_settings(&self, form_id: Uuid) -> Result<Option<NotificationSettings>, AppError> {
+        let row = time_db(
+            "select_notifications",
+            self.session()?.execute(&self.statements.select_notifications, (form_id,)),
         )
         .await
-        .map_err(|e| AppError::DbError(format!("Failed to insert form response: {}", e)))?;
+        .map_err(|e| AppError::DbError(format!("Failed to fetch notification settings: {}", e)))?
+        .maybe_first_row_typed::<(String,)>()
+        .map_err(row_error)?;
 
-    Ok(id)
-}
+        row.map(|(settings,)| from_json(&settings, "notification settings")).transpose()
+    }
 
-pub async fn get_form_responses(
-    session: &Arc<Session>,
-    form_id: Uuid,
-) -> Result<Vec<FormResponse>, AppError> {
-    let table_name = format!("form_portal.form_responses_{}", form_id.to_string().replace("-", ""));
+    #[instrument(skip(self))]
+    async fn delete_form(&self, id: Uuid) -> Result<(), AppError> {
+        self.get_form_schema(id).await?;
 
-    let result = session
-        .query(
-            format!(
-                "SELECT id, form_id, data, created_at FROM {}",
-                table_name
-            ).as_str(),
-            &[],
+        let cql = format!("DROP TABLE IF EXISTS {}", response_table(&self.config.keyspace, id));
+        time_db("drop_response_table", self.session()?.query(cql, &[]))
+            .await
+            .map_err(|e| AppError::DbError(format!("Failed to drop form responses table: {}", e)))?;
+        self.response_statements.write().await.remove(&id);
+
+        time_db(
+            "delete_notifications",
+            self.session()?.execute(&self.statements.delete_notifications, (id,)),
+        )
+        .await
+        .map_err(|e| AppError::DbError(format!("Failed to delete notification settings: {}", e)))?;
+        time_db("delete_schema", self.session()?.execute(&self.statements.delete_schema, (id,)))
+            .await
+            .map_err(|e| AppError::DbError(format!("Failed to delete form schema: {}", e)))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_responses_before(&self, form_id: Uuid, before: DateTime<Utc>) -> Result<usize, AppError> {
+        let statements = self.response_statements(form_id).await?;
+        // Responses are keyed by id alone, so the old ones are found by a scan
+        let expired: Vec<Uuid> = time_db("select_response_dates", self.session()?.execute(&statements.select_created_at, &[]))
+            .await
+            .map_err(|e| AppError::DbError(format!("Failed to fetch form responses: {}", e)))?
+            .rows_typed_or_empty::<(Uuid, Timestamp)>()
+            .filter_map(|row| match row {
+                Ok((id, created_at)) if to_datetime(created_at) < before => Some(Ok(id)),
+                Ok(_) => None,
+                Err(e) => Some(Err(row_error(e))),
+            })
+            .collect::<Result<_, _>>()?;
+
+        for id in &expired {
+            time_db("delete_response", self.session()?.execute(&statements.delete, (id,)))
+                .await
+                .map_err(|e| AppError::DbError(format!("Failed to delete form response: {}", e)))?;
+        }
+        Ok(expired.len())
+    }
+
+    #[instrument(skip_all, fields(name = %key.name))]
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), AppError> {
+        time_db(
+            "insert_api_key",
+            self.session()?.execute(
+                &self.statements.insert_api_key,
+                (&key.key_hash, key.id, &key.name, key.created_at),
+            ),
         )
         .await
-        .map_err(|e| AppError::DbError(format!("Failed to fetch form responses: {}", e)))?;
-
-    let mut responses = Vec::new();
-
-    // In get_form_responses function:
-    for row in result.rows.unwrap_or_default() {
-        // Parse the row manually using pattern matching for timestamp
-        let response_row = FormResponseRow {
-            id: row.columns[0].as_ref().and_then(|v| v.as_uuid()).ok_or_else(||
-                AppError::InternalError("Failed to get id column".to_string()))?,
-            form_id: row.columns[1].as_ref().and_then(|v| v.as_uuid()).ok_or_else(||
-                AppError::InternalError("Failed to get form_id column".to_string()))?,
-            data: row.columns[2].as_ref().and_then(|v| v.as_text()).ok_or_else(||
-                AppError::InternalError("Failed to get data column".to_string()))?.to_string(),
-            created_at: row.columns[3].as_ref().and_then(|v| match v {
-                CqlValue::Timestamp(ts) => {
-                    // Convert TimeDelta to milliseconds i64
-                    let millis = ts.num_milliseconds();
-                    Some(chrono::DateTime::<chrono::Utc>::from_timestamp_millis(millis).unwrap_or_default())
-                },
-                _ => None,
-            }).ok_or_else(|| AppError::InternalError("Failed to get created_at column or invalid type".to_string()))?,
-    };
-
-        // Rest of the function as before
-        let data: HashMap<String, Value> = serde_json::from_str(&response_row.data)
-            .map_err(|e| AppError::InternalError(format!("Failed to deserialize response data: {}", e)))?;
-
-        let response = FormResponse {
-            id: Some(response_row.id),
-            form_id: response_row.form_id,
-            data,
-            created_at: Some(response_row.created_at),
+        .map_err(|e| AppError::DbError(format!("Failed to insert API key: {}", e)))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, AppError> {
+        time_db("select_api_keys", self.session()?.execute(&self.statements.select_api_keys, &[]))
+            .await
+            .map_err(|e| AppError::DbError(format!("Failed to list API keys: {}", e)))?
+            .rows_typed_or_empty::<ApiKeyRow>()
+            .map(|row| row.map(ApiKey::from).map_err(row_error))
+            .collect()
+    }
+
+    #[instrument(skip_all)]
+    async fn find_api_key(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError> {
+        let row = time_db("select_api_key", self.session()?.execute(&self.statements.select_api_key, (key_hash,)))
+            .await
+            .map_err(|e| AppError::DbError(format!("Failed to fetch API key: {}", e)))?
+            .maybe_first_row_typed::<ApiKeyRow>()
+            .map_err(row_error)?;
+
+        Ok(row.map(ApiKey::from))
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_api_key(&self, id: Uuid) -> Result<bool, AppError> {
+        // Keys are looked up by hash, so the few there are get scanned for the id
+        let Some(key) = self.list_api_keys().await?.into_iter().find(|k| k.id == id) else {
+            return Ok(false);
         };
+        time_db("delete_api_key", self.session()?.execute(&self.statements.delete_api_key, (&key.key_hash,)))
+            .await
+            .map_err(|e| AppError::DbError(format!("Failed to delete API key: {}", e)))?;
 
-        responses.push(response);
+        Ok(true)
     }
-    Ok(responses)
-}
\ No newline at end of file
+
+    #[instrument(skip(self))]
+    async fn check_ready(&self) -> Result<(), AppError> {
+        let pending = time_db("check_ready", migrations::pending(&*self.session()?, &self.config.keyspace))
+            .await
+            .map_err(|e| AppError::DbError(format!("Failed to reach the database: {}", e)))?;
+        if !pending.is_empty() {
+            return Err(AppError::DbError(format!("{} database migrations are pending", pending.len())));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn close(&self) {
+        let Some(mut session) = self.session.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+            return;
+        };
+        // Queries still running hold their own handle; the connections
+        // close once the last handle is dropped
+        let deadline = Instant::now() + CLOSE_TIMEOUT;
+        loop {
+            match Arc::try_unwrap(session) {
+                Ok(session) => {
+                    drop(session);
+                    info!("Closed the database session");
+                    return;
+                }
+                Err(shared) if Instant::now() < deadline => {
+                    session = shared;
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                Err(_) => {
+                    warn!("Closing the database session with queries still running");
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected(config: ScyllaConfig) -> String {
+        config.validate().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn reads_replication_as_a_factor_or_per_datacenter() {
+        assert_eq!("3".parse::<Replication>().unwrap(), Replication::Simple(3));
+        let replication: Replication = " dc1:3, dc2:2 ".parse().unwrap();
+        assert_eq!(
+            replication,
+            Replication::NetworkTopology(vec![("dc1".to_string(), 3), ("dc2".to_string(), 2)])
+        );
+        assert_eq!(replication.to_string(), "dc1:3,dc2:2");
+        assert_eq!(
+            replication.to_cql(),
+            "{'class': 'NetworkTopologyStrategy', 'dc1': 3, 'dc2': 2}"
+        );
+        assert_eq!(Replication::Simple(1).to_cql(), "{'class': 'SimpleStrategy', 'replication_factor': 1}");
+
+        for invalid in ["dc1", "dc1:many", "dc'1:3"] {
+            assert!(invalid.parse::<Replication>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn reads_consistency_levels_as_written_in_cql() {
+        assert_eq!(parse_consistency("local_quorum").unwrap(), Consistency::LocalQuorum);
+        assert_eq!(parse_consistency(" ONE ").unwrap(), Consistency::One);
+        assert!(parse_consistency("MOST").is_err());
+
+        let config: ScyllaConfig = toml::from_str(
+            "read_consistency = \"LOCAL_ONE\"\nreplication = \"dc1:3\"\n[tls]\nca_file = \"ca.pem\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.read_consistency, Consistency::LocalOne);
+        assert_eq!(config.write_consistency, Consistency::LocalQuorum);
+        assert_eq!(config.replication, Replication::NetworkTopology(vec![("dc1".to_string(), 3)]));
+        assert!(config.tls.is_enabled());
+        let config: ScyllaConfig = toml::from_str("replication = 2\n").unwrap();
+        assert_eq!(config.replication, Replication::Simple(2));
+        assert!(!config.tls.is_enabled());
+    }
+
+    #[test]
+    fn rejects_incomplete_or_unsafe_settings() {
+        assert!(ScyllaConfig::default().validate().is_ok());
+
+        let config = ScyllaConfig { nodes: Vec::new(), ..ScyllaConfig::default() };
+        assert!(rejected(config).contains("At least one ScyllaDB node"));
+        let config = ScyllaConfig { username: Some("app".to_string()), ..ScyllaConfig::default() };
+        assert!(rejected(config).contains("username and password must be set together"));
+        let mut config = ScyllaConfig::default();
+        config.tls.cert_file = Some("client.pem".to_string());
+        assert!(rejected(config).contains("certificate and key must be set together"));
+        let config = ScyllaConfig { local_datacenter: Some("dc 1".to_string()), ..ScyllaConfig::default() };
+        assert!(rejected(config).contains("Invalid datacenter name"));
+        let config = ScyllaConfig { replication: Replication::Simple(0), ..ScyllaConfig::default() };
+        assert!(rejected(config).contains("at least 1"));
+        let config = ScyllaConfig {
+            replication: Replication::NetworkTopology(Vec::new()),
+            ..ScyllaConfig::default()
+        };
+        assert!(rejected(config).contains("at least one datacenter"));
+    }
+
+    #[test]
+    fn decodes_rows_into_models() {
+        let (id, form_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let row = FormResponseRow {
+            id,
+            form_id,
+            data: r#"{"name":"Ada","rating":4}"#.to_string(),
+            created_at: Timestamp(chrono::Duration::milliseconds(1_714_555_800_123)),
+        };
+        let response = FormResponse::try_from(row).unwrap();
+        assert_eq!((response.id, response.form_id), (Some(id), form_id));
+        assert_eq!(response.data["rating"], 4);
+        assert_eq!(response.created_at.unwrap().to_rfc3339(), "2024-05-01T09:30:00.123+00:00");
+
+        let row = FormSchemaRow {
+            id,
+            name: "Signup".to_string(),
+            description: None,
+            fields: r#"[{"id":"name","label":"Name","field_type":"Text","required":true}]"#.to_string(),
+            created_at: None,
+            updated_at: None,
+        };
+        let schema = FormSchema::try_from(row).unwrap();
+        assert_eq!(schema.fields[0].id, "name");
+        assert!(schema.created_at.is_none());
+
+        let row = FormResponseRow {
+            id,
+            form_id,
+            data: "not json".to_string(),
+            created_at: Timestamp(chrono::Duration::zero()),
+        };
+        let error = FormResponse::try_from(row).unwrap_err().to_string();
+        assert!(error.contains("Failed to deserialize response data"), "{}", error);
+    }
+
+    #[test]
+    fn enables_tls_when_certificates_are_given_unless_turned_off() {
+        let mut tls = TlsConfig::default();
+        assert!(!tls.is_enabled());
+        tls.ca_file = Some("ca.pem".to_string());
+        assert!(tls.is_enabled());
+        tls.enabled = Some(false);
+        assert!(!tls.is_enabled());
+    }
+}
@@ -0,0 +1,271 @@
+// src/definition.rs
+//! Form definitions written as JSON, YAML or TOML documents.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::{
+    error::AppError,
+    models::{FieldType, FormField, FormSchema},
+    validation::{compile_pattern, RATING_LIMIT},
+};
+
+/// Characters field and row ids cannot contain: submissions name nested
+/// answers `field[row]`, and exports and filters refer to them as `field.row`.
+pub const RESERVED_ID_CHARS: &[char] = &['[', ']', '.'];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    /// The format of a request body; JSON when no content type is given.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let essence = content_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+        match essence.as_str() {
+            "" | "application/json" => Some(Format::Json),
+            "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => Some(Format::Yaml),
+            "application/toml" | "text/toml" => Some(Format::Toml),
+            other if other.ends_with("+json") => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    /// The format of a file, by extension; JSON for unknown extensions.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(str::to_lowercase).as_deref() {
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+}
+
+pub fn parse(text: &str, format: Format) -> Result<FormSchema, AppError> {
+    let invalid = |e: &dyn std::fmt::Display| AppError::BadRequest(format!("Invalid form schema: {}", e));
+    match format {
+        Format::Json => serde_json::from_str(text).map_err(|e| invalid(&e)),
+        Format::Yaml => serde_yaml::from_str(text).map_err(|e| invalid(&e)),
+        Format::Toml => toml::from_str(text).map_err(|e| invalid(&e)),
+    }
+}
+
+/// Rejects definitions no submission could satisfy or that would render
+/// broken, listing every problem found.
+pub fn validate(schema: &FormSchema) -> Result<(), AppError> {
+    let mut errors = Vec::new();
+    if schema.fields.is_empty() {
+        errors.push("the form has no fields".to_string());
+    }
+    check_fields(&schema.fields, "", &mut errors);
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!("Invalid form schema: {}", errors.join("; "))))
+    }
+}
+
+fn check_fields(fields: &[FormField], prefix: &str, errors: &mut Vec<String>) {
+    let mut ids = HashSet::new();
+    for field in fields {
+        let name = format!("{}{}", prefix, field.id);
+        if field.id.trim().is_empty() {
+            errors.push(format!("{}: every field needs an id", if prefix.is_empty() { "fields" } else { prefix }));
+        } else if field.id.contains(RESERVED_ID_CHARS) {
+            errors.push(format!("{}: the id cannot contain `[`, `]` or `.`", name));
+        } else if !ids.insert(field.id.as_str()) {
+            errors.push(format!("{}: the id is used more than once", name));
+        }
+        check_field(field, &name, errors);
+    }
+}
+
+fn check_field(field: &FormField, name: &str, errors: &mut Vec<String>) {
+    let mut fail = |message: &str| errors.push(format!("{}: {}", name, message));
+
+    let needs_options = matches!(
+        field.field_type,
+        FieldType::Select | FieldType::Radio | FieldType::MultiSelect | FieldType::Ranking | FieldType::Matrix
+    );
+    match &field.options {
+        Some(options) if needs_options && options.is_empty() => fail("needs at least one option"),
+        None if needs_options => fail("needs `options`"),
+        Some(options) => {
+            let mut values = HashSet::new();
+            if let Some(option) = options.iter().find(|o| !values.insert(o.value.as_str())) {
+                fail(&format!("the option `{}` is listed more than once", option.value));
+            }
+        }
+        None => {}
+    }
+
+    if let (Some(min), Some(max)) = (field.min, field.max) {
+        if min > max {
+            fail("`min` is greater than `max`");
+        }
+    }
+    if matches!(field.field_type, FieldType::Rating)
+        && field.max.is_some_and(|max| max.fract() != 0.0 || !(1.0..=RATING_LIMIT as f64).contains(&max))
+    {
+        fail(&format!("`max` of a rating must be a whole number from 1 to {}", RATING_LIMIT));
+    }
+    if field.step.is_some_and(|step| step <= 0.0) {
+        fail("`step` must be positive");
+    }
+    if let (Some(min), Some(max)) = (field.min_selections, field.max_selections) {
+        if min > max {
+            fail("`min_selections` is greater than `max_selections`");
+        }
+    }
+    let options = field.options.as_ref().map_or(0, Vec::len);
+    if field.min_selections.is_some_and(|min| min > options) {
+        fail("`min_selections` is greater than the number of options");
+    }
+    if let (Some(min), Some(max)) = (field.min_items, field.max_items) {
+        if min > max {
+            fail("`min_items` is greater than `max_items`");
+        }
+    }
+    if let Some(Err(reason)) = field.validation.as_deref().map(compile_pattern) {
+        fail(&format!("`validation` is not a valid pattern: {}", reason));
+    }
+
+    match field.field_type {
+        FieldType::Matrix => match &field.rows {
+            Some(rows) if !rows.is_empty() => {
+                let mut ids = HashSet::new();
+                if let Some(row) = rows.iter().find(|r| !ids.insert(r.id.as_str())) {
+                    fail(&format!("the row `{}` is listed more than once", row.id));
+                }
+                if let Some(row) = rows.iter().find(|r| r.id.contains(RESERVED_ID_CHARS)) {
+                    fail(&format!("the row `{}` cannot contain `[`, `]` or `.`", row.id));
+                }
+            }
+            _ => fail("needs at least one of `rows`"),
+        },
+        FieldType::Group => match &field.fields {
+            Some(fields) if !fields.is_empty() => check_fields(fields, &format!("{}.", name), errors),
+            _ => fail("needs at least one of `fields`"),
+        },
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(yaml: &str) -> Result<(), String> {
+        let schema = parse(yaml, Format::Yaml).unwrap();
+        validate(&schema).map_err(|e| e.to_string())
+    }
+
+    fn assert_rejected(yaml: &str, message: &str) {
+        let error = check(yaml).unwrap_err();
+        assert!(error.contains(message), "{}", error);
+    }
+
+    #[test]
+    fn accepts_a_valid_form() {
+        let yaml = "name: Signup\nfields:\n\
+            \x20 - { id: name, label: Name, field_type: Text, required: true }\n\
+            \x20 - { id: age, label: Age, field_type: Number, required: false, min: 0, max: 130 }\n\
+            \x20 - { id: agree, label: I agree, field_type: Checkbox, required: true }\n";
+        assert_eq!(check(yaml), Ok(()));
+    }
+
+    #[test]
+    fn rejects_a_form_without_fields() {
+        assert_rejected("name: Empty\nfields: []\n", "the form has no fields");
+    }
+
+    #[test]
+    fn rejects_duplicate_field_ids() {
+        let yaml = "name: Signup\nfields:\n\
+            \x20 - { id: name, label: Name, field_type: Text, required: true }\n\
+            \x20 - { id: name, label: Nickname, field_type: Text, required: false }\n";
+        assert_rejected(yaml, "name: the id is used more than once");
+    }
+
+    #[test]
+    fn rejects_ids_that_clash_with_answer_paths() {
+        for id in ["kids[0]", "service]", "service.speed"] {
+            let yaml = format!("name: Signup\nfields:\n  - {{ id: '{}', label: Name, field_type: Text, required: true }}\n", id);
+            assert_rejected(&yaml, &format!("{}: the id cannot contain `[`, `]` or `.`", id));
+        }
+        let yaml = "name: Survey\nfields:\n\
+            \x20 - id: service\n\
+            \x20   label: Service\n\
+            \x20   field_type: Matrix\n\
+            \x20   required: true\n\
+            \x20   rows: [{ id: speed.max, label: Speed }]\n\
+            \x20   options: [{ value: good, label: Good }]\n";
+        assert_rejected(yaml, "service: the row `speed.max` cannot contain `[`, `]` or `.`");
+    }
+
+    #[test]
+    fn rejects_choices_without_options() {
+        for field_type in ["Select", "Radio", "MultiSelect", "Ranking"] {
+            let yaml = format!(
+                "name: Lunch\nfields:\n  - {{ id: mains, label: Mains, field_type: {}, required: true }}\n",
+                field_type
+            );
+            assert_rejected(&yaml, "mains: needs `options`");
+        }
+        let yaml = "name: Lunch\nfields:\n  - { id: mains, label: Mains, field_type: Select, required: true, options: [] }\n";
+        assert_rejected(yaml, "mains: needs at least one option");
+    }
+
+    #[test]
+    fn rejects_inverted_bounds() {
+        let yaml = "name: Survey\nfields:\n  - { id: age, label: Age, field_type: Number, required: true, min: 10, max: 1 }\n";
+        assert_rejected(yaml, "age: `min` is greater than `max`");
+
+        let yaml = "name: Survey\nfields:\n\
+            \x20 - id: mains\n\
+            \x20   label: Mains\n\
+            \x20   field_type: MultiSelect\n\
+            \x20   required: true\n\
+            \x20   min_selections: 2\n\
+            \x20   max_selections: 1\n\
+            \x20   options: [{ value: a, label: A }, { value: b, label: B }]\n";
+        assert_rejected(yaml, "mains: `min_selections` is greater than `max_selections`");
+    }
+
+    #[test]
+    fn rejects_ratings_with_too_many_stars() {
+        let yaml = "name: Survey\nfields:\n  - { id: stars, label: Stars, field_type: Rating, required: true, max: 4e9 }\n";
+        assert_rejected(yaml, "stars: `max` of a rating must be a whole number from 1 to 10");
+    }
+
+    #[test]
+    fn rejects_a_matrix_without_rows() {
+        let yaml = "name: Survey\nfields:\n\
+            \x20 - { id: service, label: Service, field_type: Matrix, required: true, options: [{ value: a, label: A }] }\n";
+        assert_rejected(yaml, "service: needs at least one of `rows`");
+    }
+
+    #[test]
+    fn rejects_a_group_without_fields_and_checks_nested_fields() {
+        let yaml = "name: Enrolment\nfields:\n  - { id: dependents, label: Dependents, field_type: Group, required: false }\n";
+        assert_rejected(yaml, "dependents: needs at least one of `fields`");
+
+        let yaml = "name: Enrolment\nfields:\n\
+            \x20 - id: dependents\n\
+            \x20   label: Dependents\n\
+            \x20   field_type: Group\n\
+            \x20   required: false\n\
+            \x20   fields: [{ id: relationship, label: Relationship, field_type: Radio, required: true }]\n";
+        assert_rejected(yaml, "dependents.relationship: needs `options`");
+    }
+
+    #[test]
+    fn rejects_invalid_validation_patterns() {
+        let yaml = "name: Survey\nfields:\n  - { id: code, label: Code, field_type: Text, required: true, validation: 'a(' }\n";
+        assert_rejected(yaml, "code: `validation` is not a valid pattern: unclosed group");
+    }
+}
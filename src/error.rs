@@ -1,5 +1,5 @@
 // src/error.rs
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{http::header, HttpResponse, ResponseError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,6 +18,12 @@ pub enum AppError {
     
     #[error("Bad request: {0}")]
     BadRequest(String),
+    
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
 }
 
 impl ResponseError for AppError {
@@ -28,6 +34,10 @@ impl ResponseError for AppError {
             AppError::NotFound(_) => HttpResponse::NotFound().json(self.to_string()),
             AppError::InternalError(_) => HttpResponse::InternalServerError().json(self.to_string()),
             AppError::BadRequest(_) => HttpResponse::BadRequest().json(self.to_string()),
+            AppError::Unauthorized(_) => HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .json(self.to_string()),
+            AppError::Unavailable(_) => HttpResponse::ServiceUnavailable().json(self.to_string()),
         }
     }
 }
\ No newline at end of file
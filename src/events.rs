@@ -0,0 +1,193 @@
+// src/events.rs
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::web::Bytes;
+use futures::stream::{self, Stream, StreamExt};
+use tracing::warn;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::models::FormResponse;
+
+const CHANNEL_CAPACITY: usize = 256;
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+const RETRY_MILLIS: u64 = 3000;
+
+/// In-process fan-out of newly stored responses to live subscribers.
+#[derive(Clone)]
+pub struct ResponseEvents {
+    sender: broadcast::Sender<FormResponse>,
+    closing: Arc<watch::Sender<bool>>,
+}
+
+/// A live subscription, ended early when the events are closed.
+pub struct Subscription {
+    responses: broadcast::Receiver<FormResponse>,
+    closing: watch::Receiver<bool>,
+}
+
+impl Default for ResponseEvents {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let (closing, _) = watch::channel(false);
+        Self {
+            sender,
+            closing: Arc::new(closing),
+        }
+    }
+}
+
+impl ResponseEvents {
+    pub fn publish(&self, response: FormResponse) {
+        // An error only means nobody is listening right now
+        let _ = self.sender.send(response);
+    }
+
+    pub fn subscribe(&self) -> Subscription {
+        Subscription {
+            responses: self.sender.subscribe(),
+            closing: self.closing.subscribe(),
+        }
+    }
+
+    /// Ends every subscription, so long-lived streams do not hold up shutdown.
+    pub fn close(&self) {
+        self.closing.send_replace(true);
+    }
+}
+
+/// Returns the responses that follow `last_event_id`, ordered by submission
+/// time. An unknown id yields nothing, as there is no position to resume from.
+pub fn backfill_after(mut responses: Vec<FormResponse>, last_event_id: Uuid) -> Vec<FormResponse> {
+    responses.sort_by_key(|r| (r.created_at, r.id));
+    match responses.iter().position(|r| r.id == Some(last_event_id)) {
+        Some(index) => responses.split_off(index + 1),
+        None => Vec::new(),
+    }
+}
+
+/// Builds the Server-Sent Events body for a form: the backfilled responses
+/// first, then live submissions until the client disconnects.
+pub fn sse_stream(
+    form_id: Uuid,
+    backfill: Vec<FormResponse>,
+    subscription: Subscription,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    let seen: HashSet<Uuid> = backfill.iter().filter_map(|r| r.id).collect();
+
+    let preamble = stream::once(async { Ok(Bytes::from(format!("retry: {}\n\n", RETRY_MILLIS))) });
+    let backfill = stream::iter(backfill.into_iter().map(|r| Ok(encode_event(&r))));
+
+    let live = stream::unfold((subscription, seen), move |(mut subscription, mut seen)| async move {
+        loop {
+            if *subscription.closing.borrow_and_update() {
+                return None;
+            }
+            let next = tokio::select! {
+                next = tokio::time::timeout(KEEPALIVE_INTERVAL, subscription.responses.recv()) => next,
+                // Loop around to the check above
+                _ = subscription.closing.changed() => continue,
+            };
+            match next {
+                Err(_) => return Some((Ok(Bytes::from_static(b": keepalive\n\n")), (subscription, seen))),
+                Ok(Ok(response)) => {
+                    if response.form_id != form_id {
+                        continue;
+                    }
+                    // Skip events already delivered by the backfill
+                    if let Some(id) = response.id {
+                        if seen.remove(&id) {
+                            continue;
+                        }
+                    }
+                    return Some((Ok(encode_event(&response)), (subscription, seen)));
+                }
+                Ok(Err(RecvError::Lagged(skipped))) => {
+                    // Ending the stream makes the client reconnect with its
+                    // Last-Event-ID, which backfills whatever was missed
+                    warn!("SSE subscriber for form {} lagged by {} events, closing", form_id, skipped);
+                    return None;
+                }
+                Ok(Err(RecvError::Closed)) => return None,
+            }
+        }
+    });
+
+    preamble.chain(backfill).chain(live)
+}
+
+fn encode_event(response: &FormResponse) -> Bytes {
+    let data = serde_json::to_string(response).unwrap_or_else(|_| "null".to_string());
+    let id = response.id.map(|id| id.to_string()).unwrap_or_default();
+    Bytes::from(format!("id: {}\nevent: response\ndata: {}\n\n", id, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn response(form_id: Uuid, id: u128, second: u32) -> FormResponse {
+        FormResponse {
+            id: Some(Uuid::from_u128(id)),
+            form_id,
+            data: Default::default(),
+            created_at: Some(Utc.with_ymd_and_hms(2024, 5, 1, 9, 0, second).unwrap()),
+        }
+    }
+
+    fn ids(responses: &[FormResponse]) -> Vec<u128> {
+        responses.iter().map(|r| r.id.unwrap().as_u128()).collect()
+    }
+
+    async fn next_event(stream: &mut (impl Stream<Item = Result<Bytes, actix_web::Error>> + Unpin)) -> Option<String> {
+        let chunk = stream.next().await?.unwrap();
+        Some(String::from_utf8(chunk.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn backfills_the_responses_after_the_last_event() {
+        let form_id = Uuid::new_v4();
+        // Stores may hand responses over in any order
+        let responses = vec![response(form_id, 3, 30), response(form_id, 1, 10), response(form_id, 2, 20)];
+
+        assert_eq!(ids(&backfill_after(responses.clone(), Uuid::from_u128(1))), vec![2, 3]);
+        assert!(backfill_after(responses.clone(), Uuid::from_u128(3)).is_empty());
+        assert!(backfill_after(responses, Uuid::from_u128(9)).is_empty());
+    }
+
+    #[test]
+    fn orders_responses_submitted_at_the_same_time_by_id() {
+        let form_id = Uuid::new_v4();
+        let responses = vec![response(form_id, 6, 20), response(form_id, 5, 10), response(form_id, 4, 10)];
+
+        assert_eq!(ids(&backfill_after(responses.clone(), Uuid::from_u128(4))), vec![5, 6]);
+        assert_eq!(ids(&backfill_after(responses, Uuid::from_u128(5))), vec![6]);
+    }
+
+    #[actix_web::test]
+    async fn streams_the_backfill_then_live_responses_of_the_form() {
+        let events = ResponseEvents::default();
+        let form_id = Uuid::new_v4();
+        let stream = sse_stream(form_id, vec![response(form_id, 1, 10)], events.subscribe());
+        let mut stream = std::pin::pin!(stream);
+
+        assert_eq!(next_event(&mut stream).await.unwrap(), "retry: 3000\n\n");
+        let backfilled = next_event(&mut stream).await.unwrap();
+        let expected = format!("id: {}\nevent: response\ndata: {{", Uuid::from_u128(1));
+        assert!(backfilled.starts_with(&expected), "{}", backfilled);
+
+        // Responses of other forms, and those already backfilled, are skipped
+        events.publish(response(Uuid::new_v4(), 7, 20));
+        events.publish(response(form_id, 1, 10));
+        events.publish(response(form_id, 2, 20));
+        let live = next_event(&mut stream).await.unwrap();
+        assert!(live.starts_with(&format!("id: {}\n", Uuid::from_u128(2))), "{}", live);
+
+        events.close();
+        assert!(next_event(&mut stream).await.is_none());
+    }
+}
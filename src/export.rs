@@ -0,0 +1,114 @@
+// src/export.rs
+//! File exports of a form's responses for `GET /api/forms/{id}/responses/export`.
+
+use serde_json::Value;
+
+use crate::{
+    error::AppError,
+    jsonschema,
+    models::{FieldType, FormField, FormResponse, FormSchema},
+};
+
+/// One row per response: its id and creation time, then one column per
+/// field of the schema, in schema order. A `Matrix` field gets one column
+/// per row, named `<field>.<row>`, and a `Group` field gets the columns of
+/// its fields once per item, named `<field>.<n>.<nested field>` from 1 up to
+/// the largest number of items among the exported responses.
+pub fn to_csv(schema: &FormSchema, responses: &[FormResponse]) -> Result<Vec<u8>, AppError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    let columns: Vec<Column> = schema.fields.iter().flat_map(|field| columns(field, responses)).collect();
+    let mut header = vec!["id".to_string(), "created_at".to_string()];
+    header.extend(columns.iter().map(|c| c.name.clone()));
+    writer.write_record(&header).map_err(export_error)?;
+
+    for response in responses {
+        let mut row = vec![
+            response.id.map(|id| id.to_string()).unwrap_or_default(),
+            response.created_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        ];
+        row.extend(
+            columns
+                .iter()
+                .map(|c| cell(response.data.get(c.field).and_then(|v| v.pointer(&c.pointer)))),
+        );
+        writer.write_record(&row).map_err(export_error)?;
+    }
+
+    writer.into_inner().map_err(export_error)
+}
+
+struct Column<'a> {
+    name: String,
+    field: &'a str,
+    /// Where the cell is within the field's answer.
+    pointer: String,
+}
+
+fn columns<'a>(field: &'a FormField, responses: &[FormResponse]) -> Vec<Column<'a>> {
+    let values: Vec<&Value> = responses.iter().filter_map(|r| r.data.get(&field.id)).collect();
+    paths(field, &values)
+        .into_iter()
+        .map(|(name, pointer)| Column {
+            name,
+            field: &field.id,
+            pointer,
+        })
+        .collect()
+}
+
+/// Column names and the JSON pointers of their cells within the answers.
+fn paths(field: &FormField, values: &[&Value]) -> Vec<(String, String)> {
+    match (&field.field_type, &field.rows) {
+        (FieldType::Matrix, Some(rows)) => rows
+            .iter()
+            .map(|row| (format!("{}.{}", field.id, row.id), format!("/{}", jsonschema::escape(&row.id))))
+            .collect(),
+        (FieldType::Group, _) => {
+            let items = values.iter().filter_map(|v| v.as_array()).map(Vec::len).max().unwrap_or(0);
+            let fields = field.fields.as_deref().unwrap_or_default();
+            (0..items)
+                .flat_map(|index| fields.iter().map(move |nested| (index, nested)))
+                .flat_map(|(index, nested)| {
+                    let nested_values: Vec<&Value> =
+                        values.iter().filter_map(|v| v.get(index)?.get(&nested.id)).collect();
+                    paths(nested, &nested_values).into_iter().map(move |(name, pointer)| {
+                        (
+                            format!("{}.{}.{}", field.id, index + 1, name),
+                            format!("/{}/{}{}", index, jsonschema::escape(&nested.id), pointer),
+                        )
+                    })
+                })
+                .collect()
+        }
+        _ => vec![(field.id.clone(), String::new())],
+    }
+}
+
+/// One JSON-encoded response per line, suitable for re-importing.
+pub fn to_ndjson(responses: &[FormResponse]) -> Result<Vec<u8>, AppError> {
+    let mut out = Vec::new();
+    for response in responses {
+        serde_json::to_writer(&mut out, response).map_err(export_error)?;
+        out.push(b'\n');
+    }
+    Ok(out)
+}
+
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        // Selections, such as those of a MultiSelect, in their stored order
+        Some(Value::Array(items)) if items.iter().all(|v| !v.is_array() && !v.is_object()) => items
+            .iter()
+            .map(|v| cell(Some(v)))
+            .collect::<Vec<_>>()
+            .join("; "),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn export_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalError(format!("Export failed: {}", e))
+}
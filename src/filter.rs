@@ -0,0 +1,967 @@
+// src/filter.rs
+//! Filter expressions and sorting for `GET /api/forms/{id}/responses`.
+//!
+//! ```text
+//! rating <= 2 and comments is not empty and created_at >= now-7d
+//! country in ('DE', 'FR') or not (email contains '@example.com')
+//! ```
+//!
+//! Expressions are parsed and type-checked against the form schema once,
+//! then evaluated against each stored response.
+
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde_json::Value;
+
+use crate::{
+    error::AppError,
+    models::{query::ResponseQuery, FieldType, FormResponse, FormSchema},
+    summary::is_blank,
+};
+
+pub const CREATED_AT: &str = "created_at";
+
+/// How deeply `not`s and parentheses may nest in a filter.
+const MAX_DEPTH: usize = 64;
+
+/// A compiled filter and sort order for one form.
+#[derive(Debug)]
+pub struct ResponseFilter {
+    expr: Option<Expr>,
+    sort: Option<SortKey>,
+}
+
+impl ResponseFilter {
+    pub fn compile(schema: &FormSchema, query: &ResponseQuery) -> Result<Self, AppError> {
+        let expr = match query.filter.as_deref().map(str::trim) {
+            Some(filter) if !filter.is_empty() => Some(Parser::new(schema, filter)?.parse()?),
+            _ => None,
+        };
+
+        let sort = match query.sort.as_deref().map(str::trim) {
+            Some(sort) if !sort.is_empty() => {
+                let (name, descending) = match sort.strip_prefix('-') {
+                    Some(name) => (name, true),
+                    None => (sort.strip_prefix('+').unwrap_or(sort), false),
+                };
+                Some(SortKey {
+                    field: resolve_field(schema, name)?,
+                    descending,
+                })
+            }
+            _ => None,
+        };
+
+        Ok(Self { expr, sort })
+    }
+
+    /// Bounds on `created_at` that every matching response lies within, taken
+    /// from comparisons the whole filter depends on, for the store to apply.
+    pub fn created_range(&self) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+        let (mut from, mut to) = (None, None);
+        let conjuncts = match &self.expr {
+            Some(Expr::And(exprs)) => exprs.iter().collect(),
+            Some(expr) => vec![expr],
+            None => Vec::new(),
+        };
+        for expr in conjuncts {
+            if let Expr::Compare { field: FieldRef::CreatedAt, op, value: Literal::Timestamp(ts) } = expr {
+                if matches!(op, CmpOp::Eq | CmpOp::Gt | CmpOp::Ge) {
+                    from = from.max(Some(*ts));
+                }
+                if matches!(op, CmpOp::Eq | CmpOp::Lt | CmpOp::Le) {
+                    to = Some(to.map_or(*ts, |to: DateTime<Utc>| to.min(*ts)));
+                }
+            }
+        }
+        (from, to)
+    }
+
+    pub fn matches(&self, response: &FormResponse) -> bool {
+        self.expr.as_ref().is_none_or(|expr| expr.eval(response))
+    }
+
+    /// Filters and sorts the responses in place. Only the `created_at` bounds
+    /// of [`created_range`](Self::created_range) narrow what stores load, so
+    /// this holds every other response in that range in memory at once.
+    pub fn apply(&self, responses: &mut Vec<FormResponse>) {
+        responses.retain(|r| self.matches(r));
+
+        if let Some(sort) = &self.sort {
+            responses.sort_by(|a, b| {
+                // Blank values always go last, whatever the direction
+                match (sort.field.sort_value(a), sort.field.sort_value(b)) {
+                    (Some(a), Some(b)) if sort.descending => b.cmp(&a),
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            });
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SortKey {
+    field: FieldRef,
+    descending: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ValueKind {
+    Number,
+    Date,
+    Timestamp,
+    Text,
+    Choice,
+    Flag,
+}
+
+#[derive(Debug, Clone)]
+enum FieldRef {
+    CreatedAt,
+    /// A field, or with `row` set, one row of a `Matrix` field.
+    Data { id: String, row: Option<String>, kind: ValueKind, options: Option<Vec<String>> },
+}
+
+fn resolve_field(schema: &FormSchema, name: &str) -> Result<FieldRef, AppError> {
+    if name == CREATED_AT {
+        return Ok(FieldRef::CreatedAt);
+    }
+
+    // Rows of a matrix are referred to as `<field>.<row>`
+    let (field, row) = match schema.fields.iter().find(|f| f.id == name) {
+        Some(field) => (field, None),
+        None => name
+            .split_once('.')
+            .and_then(|(id, row)| {
+                let field = schema.fields.iter().find(|f| f.id == id)?;
+                field.rows.as_ref()?.iter().find(|r| r.id == row)?;
+                Some((field, Some(row.to_string())))
+            })
+            .ok_or_else(|| AppError::ValidationError(format!("Unknown field in filter: {}", name)))?,
+    };
+
+    let kind = match field.field_type {
+        FieldType::Matrix if row.is_none() => {
+            return Err(AppError::ValidationError(format!(
+                "Field {} is a matrix; filter on one of its rows as {}.<row id>",
+                name, name
+            )))
+        }
+        FieldType::Matrix if field.multiple => ValueKind::Flag,
+        FieldType::Matrix => ValueKind::Choice,
+        FieldType::Number | FieldType::Range | FieldType::Rating | FieldType::Nps => ValueKind::Number,
+        FieldType::Date => ValueKind::Date,
+        FieldType::DateTime => ValueKind::Timestamp,
+        // Times are stored as HH:MM:SS, so they order correctly as text
+        FieldType::Text
+        | FieldType::Email
+        | FieldType::Textarea
+        | FieldType::Phone
+        | FieldType::Url
+        | FieldType::Time
+        | FieldType::Color => ValueKind::Text,
+        // Items of a group are matched against their JSON text
+        FieldType::Group => ValueKind::Text,
+        FieldType::Select | FieldType::Radio => ValueKind::Choice,
+        // Answers holding several options filter like checkboxes
+        FieldType::Checkbox | FieldType::MultiSelect | FieldType::Ranking => ValueKind::Flag,
+    };
+
+    Ok(FieldRef::Data {
+        id: field.id.clone(),
+        row,
+        kind,
+        options: field
+            .options
+            .as_ref()
+            .map(|options| options.iter().map(|o| o.value.clone()).collect()),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Number(OrderedF64),
+    Date(NaiveDate),
+    Timestamp(DateTime<Utc>),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct OrderedF64(f64);
+
+impl Eq for OrderedF64 {}
+
+impl PartialOrd for OrderedF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl FieldRef {
+    fn value<'a>(&self, response: &'a FormResponse) -> Option<&'a Value> {
+        match self {
+            FieldRef::CreatedAt => None,
+            FieldRef::Data { id, row, .. } => {
+                let value = response.data.get(id)?;
+                match row {
+                    Some(row) => value.get(row),
+                    None => Some(value),
+                }
+                .filter(|v| !is_blank(v))
+            }
+        }
+    }
+
+    fn sort_value(&self, response: &FormResponse) -> Option<SortValue> {
+        match self {
+            FieldRef::CreatedAt => response.created_at.map(SortValue::Timestamp),
+            FieldRef::Data { kind, .. } => {
+                let value = self.value(response)?;
+                match kind {
+                    ValueKind::Number => as_number(value).map(|n| SortValue::Number(OrderedF64(n))),
+                    ValueKind::Date => as_date(value).map(SortValue::Date),
+                    ValueKind::Timestamp => as_timestamp(value).map(SortValue::Timestamp),
+                    _ => Some(SortValue::Text(as_text(value))),
+                }
+            }
+        }
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_date(value: &Value) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.as_str()?.trim(), "%Y-%m-%d").ok()
+}
+
+fn as_timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.as_str()?.trim())
+        .ok()
+        .map(|ts| ts.with_timezone(&Utc))
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// The individual values selected by a checkbox answer.
+fn flag_values(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter().map(as_text).collect(),
+        other => vec![as_text(other)],
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn test(self, ordering: Ordering) -> bool {
+        match self {
+            CmpOp::Eq => ordering == Ordering::Equal,
+            CmpOp::Ne => ordering != Ordering::Equal,
+            CmpOp::Lt => ordering == Ordering::Less,
+            CmpOp::Le => ordering != Ordering::Greater,
+            CmpOp::Gt => ordering == Ordering::Greater,
+            CmpOp::Ge => ordering != Ordering::Less,
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        !matches!(self, CmpOp::Eq | CmpOp::Ne)
+    }
+}
+
+/// A literal already converted to the type of the field it is compared with.
+#[derive(Debug, Clone)]
+enum Literal {
+    Number(f64),
+    Date(NaiveDate),
+    Timestamp(DateTime<Utc>),
+    Text(String),
+}
+
+#[derive(Debug)]
+enum Expr {
+    /// Chains of `and` and `or` are kept flat, so only `not` and parentheses nest.
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare { field: FieldRef, op: CmpOp, value: Literal },
+    Contains { field: FieldRef, needle: String },
+    In { field: FieldRef, values: Vec<Literal> },
+    IsEmpty { field: FieldRef },
+}
+
+impl Expr {
+    fn eval(&self, response: &FormResponse) -> bool {
+        match self {
+            Expr::And(exprs) => exprs.iter().all(|e| e.eval(response)),
+            Expr::Or(exprs) => exprs.iter().any(|e| e.eval(response)),
+            Expr::Not(e) => !e.eval(response),
+            Expr::Compare { field, op, value } => compare(field, response, value).is_some_and(|o| op.test(o)),
+            Expr::Contains { field, needle } => match field.value(response) {
+                Some(value) => match field {
+                    FieldRef::Data { kind: ValueKind::Flag, .. } => {
+                        flag_values(value).iter().any(|v| v == needle)
+                    }
+                    _ => as_text(value).to_lowercase().contains(needle.as_str()),
+                },
+                None => false,
+            },
+            Expr::In { field, values } => values
+                .iter()
+                .any(|v| compare(field, response, v) == Some(Ordering::Equal)),
+            Expr::IsEmpty { field } => match field {
+                FieldRef::CreatedAt => response.created_at.is_none(),
+                FieldRef::Data { .. } => field.value(response).is_none(),
+            },
+        }
+    }
+}
+
+/// Orders the stored value relative to the literal. Missing or unparsable
+/// values compare as `None`, so they match no comparison at all.
+fn compare(field: &FieldRef, response: &FormResponse, literal: &Literal) -> Option<Ordering> {
+    if let (FieldRef::CreatedAt, Literal::Timestamp(ts)) = (field, literal) {
+        return response.created_at.map(|created_at| created_at.cmp(ts));
+    }
+
+    let value = field.value(response)?;
+    match (field, literal) {
+        (_, Literal::Number(n)) => as_number(value).and_then(|v| v.partial_cmp(n)),
+        (_, Literal::Date(d)) => as_date(value).map(|v| v.cmp(d)),
+        (FieldRef::Data { kind: ValueKind::Flag, .. }, Literal::Text(t)) => {
+            // A checkbox equals a value when that value is among those ticked
+            if flag_values(value).iter().any(|v| v == t) {
+                Some(Ordering::Equal)
+            } else {
+                Some(Ordering::Less)
+            }
+        }
+        (_, Literal::Text(t)) => Some(as_text(value).as_str().cmp(t.as_str())),
+        (_, Literal::Timestamp(ts)) => as_timestamp(value).map(|v| v.cmp(ts)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(f64),
+    Op(CmpOp),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, AppError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    _ => Token::Comma,
+                });
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        // A doubled quote inside a string is an escaped quote
+                        Some(q) if q == c && chars.peek() == Some(&c) => {
+                            chars.next();
+                            s.push(c);
+                        }
+                        Some(q) if q == c => break,
+                        Some(other) => s.push(other),
+                        None => return Err(filter_error("unterminated string")),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let followed_by_eq = chars.peek() == Some(&'=');
+                let op = match (c, followed_by_eq) {
+                    ('=', _) => CmpOp::Eq,
+                    ('!', true) => CmpOp::Ne,
+                    ('<', true) => CmpOp::Le,
+                    ('<', false) => CmpOp::Lt,
+                    ('>', true) => CmpOp::Ge,
+                    ('>', false) => CmpOp::Gt,
+                    _ => return Err(filter_error("expected `!=`")),
+                };
+                // `=` and `==` are both accepted for equality
+                if followed_by_eq {
+                    chars.next();
+                }
+                tokens.push(Token::Op(op));
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let mut s = String::new();
+                s.push(c);
+                chars.next();
+                while let Some(&d) = chars.peek() {
+                    if d.is_ascii_alphanumeric() || matches!(d, '.' | '-' | ':' | '+') {
+                        s.push(d);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                // Unquoted dates such as 2025-05-04 are read as strings
+                match s.parse() {
+                    Ok(n) => tokens.push(Token::Number(n)),
+                    Err(_) => tokens.push(Token::Str(s)),
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut s = String::new();
+                while let Some(&d) = chars.peek() {
+                    if d.is_alphanumeric() || matches!(d, '_' | '-' | '.') {
+                        s.push(d);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(s));
+            }
+            other => return Err(filter_error(&format!("unexpected character `{}`", other))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn filter_error(message: &str) -> AppError {
+    AppError::BadRequest(format!("Invalid filter: {}", message))
+}
+
+struct Parser<'a> {
+    schema: &'a FormSchema,
+    tokens: Vec<Token>,
+    pos: usize,
+    /// `not`s and parentheses around the current position.
+    depth: usize,
+    now: DateTime<Utc>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(schema: &'a FormSchema, input: &str) -> Result<Self, AppError> {
+        Ok(Self {
+            schema,
+            tokens: tokenize(input)?,
+            pos: 0,
+            depth: 0,
+            now: Utc::now(),
+        })
+    }
+
+    fn parse(mut self) -> Result<Expr, AppError> {
+        let expr = self.parse_or()?;
+        match self.peek() {
+            None => Ok(expr),
+            Some(token) => Err(filter_error(&format!("unexpected {:?}", token))),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(s)) if s.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), AppError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(filter_error(&format!("expected {:?}, found {:?}", expected, other))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, AppError> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.eat_keyword("or") {
+            exprs.push(self.parse_and()?);
+        }
+        Ok(if exprs.len() == 1 { exprs.remove(0) } else { Expr::Or(exprs) })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, AppError> {
+        let mut exprs = vec![self.parse_unary()?];
+        while self.eat_keyword("and") {
+            exprs.push(self.parse_unary()?);
+        }
+        Ok(if exprs.len() == 1 { exprs.remove(0) } else { Expr::And(exprs) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, AppError> {
+        let negated = self.peek_keyword("not");
+        if !negated && self.peek() != Some(&Token::LParen) {
+            return self.parse_predicate();
+        }
+
+        // Parsing, evaluating and dropping all recurse once per level
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(filter_error(&format!("nested more than {} levels deep", MAX_DEPTH)));
+        }
+        self.pos += 1;
+        let expr = if negated {
+            Expr::Not(Box::new(self.parse_unary()?))
+        } else {
+            let expr = self.parse_or()?;
+            self.expect(Token::RParen)?;
+            expr
+        };
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn parse_predicate(&mut self) -> Result<Expr, AppError> {
+        let name = match self.next() {
+            Some(Token::Ident(name)) => name,
+            other => return Err(filter_error(&format!("expected a field id, found {:?}", other))),
+        };
+        let field = resolve_field(self.schema, &name)?;
+
+        match self.next() {
+            Some(Token::Op(op)) => {
+                let value = self.parse_literal(&field, &name)?;
+                if op.is_ordering() && matches!(&field, FieldRef::Data { kind: ValueKind::Choice | ValueKind::Flag, .. }) {
+                    return Err(AppError::ValidationError(format!(
+                        "Field {} can only be compared with `=` and `!=`",
+                        name
+                    )));
+                }
+                Ok(Expr::Compare { field, op, value })
+            }
+            Some(Token::Ident(kw)) if kw.eq_ignore_ascii_case("contains") => {
+                if !matches!(&field, FieldRef::Data { kind: ValueKind::Text | ValueKind::Flag, .. }) {
+                    return Err(AppError::ValidationError(format!(
+                        "`contains` is not supported for field {}",
+                        name
+                    )));
+                }
+                let needle = match self.parse_literal(&field, &name)? {
+                    Literal::Text(t) => t,
+                    _ => return Err(filter_error("`contains` expects a string")),
+                };
+                // Text matching is case-insensitive, option matching is exact
+                let needle = match &field {
+                    FieldRef::Data { kind: ValueKind::Text, .. } => needle.to_lowercase(),
+                    _ => needle,
+                };
+                Ok(Expr::Contains { field, needle })
+            }
+            Some(Token::Ident(kw)) if kw.eq_ignore_ascii_case("in") => self.parse_in(field, &name),
+            Some(Token::Ident(kw)) if kw.eq_ignore_ascii_case("not") => {
+                if !self.eat_keyword("in") {
+                    return Err(filter_error("expected `in` after `not`"));
+                }
+                Ok(Expr::Not(Box::new(self.parse_in(field, &name)?)))
+            }
+            Some(Token::Ident(kw)) if kw.eq_ignore_ascii_case("is") => {
+                let negated = self.eat_keyword("not");
+                if !self.eat_keyword("empty") {
+                    return Err(filter_error("expected `empty` after `is`"));
+                }
+                let expr = Expr::IsEmpty { field };
+                Ok(if negated { Expr::Not(Box::new(expr)) } else { expr })
+            }
+            other => Err(filter_error(&format!("expected an operator after {}, found {:?}", name, other))),
+        }
+    }
+
+    fn parse_in(&mut self, field: FieldRef, name: &str) -> Result<Expr, AppError> {
+        self.expect(Token::LParen)?;
+        let mut values = vec![self.parse_literal(&field, name)?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            values.push(self.parse_literal(&field, name)?);
+        }
+        self.expect(Token::RParen)?;
+        Ok(Expr::In { field, values })
+    }
+
+    /// Reads the next literal and converts it to the type of `field`.
+    fn parse_literal(&mut self, field: &FieldRef, name: &str) -> Result<Literal, AppError> {
+        let token = self
+            .next()
+            .ok_or_else(|| filter_error(&format!("missing value for {}", name)))?;
+        let mismatch = |expected: &str| {
+            AppError::ValidationError(format!("Field {} expects {} in filter", name, expected))
+        };
+
+        match field {
+            FieldRef::CreatedAt | FieldRef::Data { kind: ValueKind::Timestamp, .. } => match token {
+                Token::Str(s) | Token::Ident(s) => self
+                    .parse_timestamp(&s)
+                    .map(Literal::Timestamp)
+                    .ok_or_else(|| mismatch("a timestamp, a date or `now-<n><unit>`")),
+                _ => Err(mismatch("a timestamp")),
+            },
+            FieldRef::Data { kind: ValueKind::Number, .. } => match token {
+                Token::Number(n) => Ok(Literal::Number(n)),
+                _ => Err(mismatch("a number")),
+            },
+            FieldRef::Data { kind: ValueKind::Date, .. } => match token {
+                Token::Str(s) => NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+                    .map(Literal::Date)
+                    .map_err(|_| mismatch("a date (YYYY-MM-DD)")),
+                _ => Err(mismatch("a date (YYYY-MM-DD)")),
+            },
+            FieldRef::Data { kind, options, .. } => {
+                let text = match token {
+                    Token::Str(s) => s,
+                    Token::Number(n) => n.to_string(),
+                    Token::Ident(s) if *kind == ValueKind::Flag && matches!(s.as_str(), "true" | "false") => s,
+                    _ => return Err(mismatch("a quoted string")),
+                };
+                if *kind == ValueKind::Choice || (*kind == ValueKind::Flag && !matches!(text.as_str(), "true" | "false")) {
+                    if let Some(options) = options {
+                        if !options.contains(&text) {
+                            return Err(AppError::ValidationError(format!(
+                                "`{}` is not an option of field {}",
+                                text, name
+                            )));
+                        }
+                    }
+                }
+                Ok(Literal::Text(text))
+            }
+        }
+    }
+
+    /// Accepts RFC 3339 timestamps, plain dates (midnight UTC) and relative
+    /// times such as `now`, `now-7d` or `now-12h`.
+    fn parse_timestamp(&self, s: &str) -> Option<DateTime<Utc>> {
+        if let Ok(ts) = DateTime::parse_from_rfc3339(s) {
+            return Some(ts.with_timezone(&Utc));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Some(date.and_hms_opt(0, 0, 0)?.and_utc());
+        }
+
+        let rest = s.to_ascii_lowercase();
+        let rest = rest.strip_prefix("now")?;
+        if rest.is_empty() {
+            return Some(self.now);
+        }
+        let rest = rest.strip_prefix('-')?;
+        let (amount, unit) = rest.split_at(rest.len().checked_sub(1)?);
+        let amount: i64 = amount.parse().ok()?;
+        let offset = match unit {
+            "m" => Duration::try_minutes(amount)?,
+            "h" => Duration::try_hours(amount)?,
+            "d" => Duration::try_days(amount)?,
+            "w" => Duration::try_weeks(amount)?,
+            _ => return None,
+        };
+        self.now.checked_sub_signed(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{parse, Format};
+    use chrono::{Datelike, TimeZone};
+    use serde_json::json;
+
+    fn schema(fields: &str) -> FormSchema {
+        parse(&format!("name: Test\nfields:\n{}", fields), Format::Yaml).unwrap()
+    }
+
+    /// Responses with the given data, created a day apart from 2024-05-01.
+    fn responses(data: &[Value]) -> Vec<FormResponse> {
+        data.iter()
+            .zip(1..)
+            .map(|(data, day)| FormResponse {
+                id: None,
+                form_id: uuid::Uuid::nil(),
+                data: serde_json::from_value(data.clone()).unwrap(),
+                created_at: Some(Utc.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap()),
+            })
+            .collect()
+    }
+
+    /// The position, counting from 1, of each response that the filter keeps,
+    /// in the order it sorts them.
+    fn filter(schema: &FormSchema, filter: &str, sort: Option<&str>, data: &[Value]) -> Result<Vec<u32>, String> {
+        let query = ResponseQuery {
+            filter: Some(filter.to_string()),
+            sort: sort.map(str::to_string),
+            ..Default::default()
+        };
+        let filter = ResponseFilter::compile(schema, &query).map_err(|e| e.to_string())?;
+        let mut responses = responses(data);
+        filter.apply(&mut responses);
+        Ok(responses.iter().map(|r| r.created_at.unwrap().day()).collect())
+    }
+
+    #[test]
+    fn compares_numbers_numerically() {
+        let schema = schema(
+            "\x20 - { id: rating, label: Rating, field_type: Number, required: true }\n\
+             \x20 - { id: nps, label: Recommend?, field_type: Nps, required: false }\n",
+        );
+        let data = [json!({ "rating": 5, "nps": 10 }), json!({ "rating": 2.0, "nps": 9 }), json!({ "rating": "10", "nps": 3 })];
+        assert_eq!(filter(&schema, "rating <= 2", None, &data), Ok(vec![2]));
+        assert_eq!(filter(&schema, "rating > 4.5", None, &data), Ok(vec![1, 3]));
+        assert_eq!(filter(&schema, "nps >= 9", None, &data), Ok(vec![1, 2]));
+        assert_eq!(filter(&schema, "rating in (2, 10)", None, &data), Ok(vec![2, 3]));
+    }
+
+    #[test]
+    fn matches_selections_by_option() {
+        let schema = schema(
+            "\x20 - { id: sides, label: Sides, field_type: MultiSelect, required: false,\n\
+             \x20     options: [{ value: rice, label: Rice }, { value: salad, label: Salad }] }\n\
+             \x20 - { id: size, label: Size, field_type: Radio, required: false,\n\
+             \x20     options: [{ value: small, label: Small }, { value: large, label: Large }] }\n",
+        );
+        let data = [json!({ "sides": ["salad", "rice"], "size": "small" }), json!({ "sides": ["rice"], "size": "large" })];
+        assert_eq!(filter(&schema, "sides contains 'salad'", None, &data), Ok(vec![1]));
+        assert_eq!(filter(&schema, "sides = 'rice'", None, &data), Ok(vec![1, 2]));
+        assert_eq!(filter(&schema, "size != 'small'", None, &data), Ok(vec![2]));
+        assert_eq!(
+            filter(&schema, "size = 'medium'", None, &data),
+            Err("Validation error: `medium` is not an option of field size".to_string())
+        );
+    }
+
+    #[test]
+    fn filters_matrix_rows_by_path() {
+        let schema = schema(
+            "\x20 - id: service\n\
+             \x20   label: Service\n\
+             \x20   field_type: Matrix\n\
+             \x20   required: false\n\
+             \x20   rows: [{ id: speed, label: Speed }, { id: price, label: Price }]\n\
+             \x20   options: [{ value: agree, label: Agree }, { value: neutral, label: Neutral }]\n",
+        );
+        let data = [json!({ "service": { "speed": "agree", "price": "neutral" } }), json!({ "service": { "speed": "neutral" } })];
+        assert_eq!(filter(&schema, "service.speed = 'agree'", None, &data), Ok(vec![1]));
+        assert_eq!(filter(&schema, "service.price is empty", None, &data), Ok(vec![2]));
+        let error = filter(&schema, "service = 'agree'", None, &data).unwrap_err();
+        assert!(error.contains("filter on one of its rows as service.<row id>"), "{}", error);
+    }
+
+    fn people() -> FormSchema {
+        schema(
+            "\x20 - { id: name, label: Name, field_type: Text, required: true }\n\
+             \x20 - { id: age, label: Age, field_type: Number, required: false }\n\
+             \x20 - { id: born, label: Born, field_type: Date, required: false }\n\
+             \x20 - { id: seen, label: Seen, field_type: DateTime, required: false }\n\
+             \x20 - { id: agree, label: Agree, field_type: Checkbox, required: false }\n",
+        )
+    }
+
+    fn people_data() -> Vec<Value> {
+        vec![
+            json!({ "name": "Ann O'Hara", "age": 30, "born": "1994-02-01", "seen": "2024-05-01T10:00:00+02:00", "agree": true }),
+            json!({ "name": "bob", "age": 40, "born": "1984-07-12", "seen": "2024-05-01T09:00:00Z" }),
+            json!({ "name": "Cy \"C\" Young", "age": 30, "agree": false }),
+            json!({ "name": "" }),
+        ]
+    }
+
+    #[test]
+    fn binds_and_tighter_than_or_and_not_tightest() {
+        let (schema, data) = (people(), people_data());
+        assert_eq!(filter(&schema, "age = 40 or age = 30 and name contains 'ann'", None, &data), Ok(vec![1, 2]));
+        assert_eq!(filter(&schema, "(age = 40 or age = 30) and name contains 'ann'", None, &data), Ok(vec![1]));
+        assert_eq!(filter(&schema, "not age = 30 and name is not empty", None, &data), Ok(vec![2]));
+        assert_eq!(filter(&schema, "not (age = 30 and agree = true)", None, &data), Ok(vec![2, 3, 4]));
+        assert_eq!(filter(&schema, "AGE = 40 OR Age = 30", None, &data).unwrap_err(), "Validation error: Unknown field in filter: AGE");
+        assert_eq!(filter(&schema, "age = 40 OR age = 30", None, &data), Ok(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn reads_quoted_strings_with_doubled_quotes() {
+        let (schema, data) = (people(), people_data());
+        assert_eq!(filter(&schema, "name = 'Ann O''Hara'", None, &data), Ok(vec![1]));
+        assert_eq!(filter(&schema, r#"name = "Cy ""C"" Young""#, None, &data), Ok(vec![3]));
+        assert_eq!(filter(&schema, r#"name contains '"c"'"#, None, &data), Ok(vec![3]));
+        assert_eq!(filter(&schema, "name in ('bob', 'Ann O''Hara')", None, &data), Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn compares_each_field_type_by_its_values() {
+        let (schema, data) = (people(), people_data());
+        // Text compares exactly, `contains` ignores case
+        assert_eq!(filter(&schema, "name == 'Bob'", None, &data), Ok(vec![]));
+        assert_eq!(filter(&schema, "name contains 'BO'", None, &data), Ok(vec![2]));
+        assert_eq!(filter(&schema, "name < 'C'", None, &data), Ok(vec![1]));
+        // `not in` negates `in`, so unlike `!=` it keeps blank answers
+        assert_eq!(filter(&schema, "name not in ('bob')", None, &data), Ok(vec![1, 3, 4]));
+        // Numbers
+        assert_eq!(filter(&schema, "age != 30", None, &data), Ok(vec![2]));
+        assert_eq!(filter(&schema, "age < 40", None, &data), Ok(vec![1, 3]));
+        assert_eq!(filter(&schema, "age >= 40", None, &data), Ok(vec![2]));
+        // Dates, quoted or not
+        assert_eq!(filter(&schema, "born > 1990-01-01", None, &data), Ok(vec![1]));
+        assert_eq!(filter(&schema, "born <= '1990-01-01'", None, &data), Ok(vec![2]));
+        // Timestamps compare as instants, whatever their offset
+        assert_eq!(filter(&schema, "seen < '2024-05-01T09:00:00Z'", None, &data), Ok(vec![1]));
+        assert_eq!(filter(&schema, "seen >= 2024-05-01T09:00:00Z", None, &data), Ok(vec![2]));
+        // Checkboxes
+        assert_eq!(filter(&schema, "agree = true", None, &data), Ok(vec![1]));
+        // Blank answers match no comparison, only `is empty`
+        assert_eq!(filter(&schema, "agree != true", None, &data), Ok(vec![3]));
+        assert_eq!(filter(&schema, "age is empty", None, &data), Ok(vec![4]));
+        assert_eq!(filter(&schema, "name is empty", None, &data), Ok(vec![4]));
+    }
+
+    #[test]
+    fn compares_created_at_with_absolute_and_relative_times() {
+        let (schema, data) = (people(), people_data());
+        assert_eq!(filter(&schema, "created_at >= 2024-05-03", None, &data), Ok(vec![3, 4]));
+        assert_eq!(filter(&schema, "created_at < '2024-05-02T12:00:00+00:00'", None, &data), Ok(vec![1]));
+        assert_eq!(filter(&schema, "created_at <= now", None, &data), Ok(vec![1, 2, 3, 4]));
+        assert_eq!(filter(&schema, "created_at >= now-1w", None, &data), Ok(vec![]));
+        assert_eq!(
+            filter(&schema, "created_at > now-7y", None, &data),
+            Err("Validation error: Field created_at expects a timestamp, a date or `now-<n><unit>` in filter".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_mismatched_values() {
+        let (schema, data) = (people(), people_data());
+        let rejected = |expr: &str| filter(&schema, expr, None, &data).unwrap_err();
+        assert_eq!(rejected("height > 2"), "Validation error: Unknown field in filter: height");
+        assert_eq!(rejected("name.first = 'Ann'"), "Validation error: Unknown field in filter: name.first");
+        assert_eq!(rejected("age = 'old'"), "Validation error: Field age expects a number in filter");
+        assert_eq!(rejected("born = 1990"), "Validation error: Field born expects a date (YYYY-MM-DD) in filter");
+        assert_eq!(rejected("age contains '3'"), "Validation error: `contains` is not supported for field age");
+        assert_eq!(rejected("agree > true"), "Validation error: Field agree can only be compared with `=` and `!=`");
+        assert_eq!(rejected("name = bob"), "Validation error: Field name expects a quoted string in filter");
+        let sorted = ResponseFilter::compile(&schema, &ResponseQuery { sort: Some("-height".to_string()), ..Default::default() });
+        assert_eq!(sorted.unwrap_err().to_string(), "Validation error: Unknown field in filter: height");
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        let (schema, data) = (people(), people_data());
+        let rejected = |expr: &str| filter(&schema, expr, None, &data).unwrap_err();
+        assert_eq!(rejected("name = 'Ann"), "Bad request: Invalid filter: unterminated string");
+        assert_eq!(rejected("age ! 3"), "Bad request: Invalid filter: expected `!=`");
+        assert_eq!(rejected("age # 3"), "Bad request: Invalid filter: unexpected character `#`");
+        assert_eq!(rejected("age ="), "Bad request: Invalid filter: missing value for age");
+        assert_eq!(rejected("(age = 3"), "Bad request: Invalid filter: expected RParen, found None");
+        assert_eq!(rejected("age = 3)"), "Bad request: Invalid filter: unexpected RParen");
+        assert_eq!(rejected("age = 3 and"), "Bad request: Invalid filter: expected a field id, found None");
+        assert_eq!(rejected("age 3"), "Bad request: Invalid filter: expected an operator after age, found Some(Number(3.0))");
+        assert_eq!(rejected("age not 3"), "Bad request: Invalid filter: expected `in` after `not`");
+        assert_eq!(rejected("age is blank"), "Bad request: Invalid filter: expected `empty` after `is`");
+        // Blank filters keep every response
+        assert_eq!(filter(&schema, "  ", None, &data), Ok(vec![1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn sorts_stably_with_blank_values_last() {
+        let (schema, data) = (people(), people_data());
+        // Ties keep the order the store returned them in, in both directions
+        assert_eq!(filter(&schema, "", Some("age"), &data), Ok(vec![1, 3, 2, 4]));
+        assert_eq!(filter(&schema, "", Some("-age"), &data), Ok(vec![2, 1, 3, 4]));
+        assert_eq!(filter(&schema, "", Some("+born"), &data), Ok(vec![2, 1, 3, 4]));
+        assert_eq!(filter(&schema, "", Some("-born"), &data), Ok(vec![1, 2, 3, 4]));
+        assert_eq!(filter(&schema, "", Some("seen"), &data), Ok(vec![1, 2, 3, 4]));
+        assert_eq!(filter(&schema, "", Some("-created_at"), &data), Ok(vec![4, 3, 2, 1]));
+        // Text sorts by code point, so capitals come first
+        assert_eq!(filter(&schema, "name is not empty", Some("name"), &data), Ok(vec![1, 3, 2]));
+    }
+
+    #[test]
+    fn limits_how_deeply_expressions_nest() {
+        let (schema, data) = (people(), people_data());
+        let nested = |depth: usize, open: &str, close: &str| format!("{}age = 30{}", open.repeat(depth), close.repeat(depth));
+        assert_eq!(filter(&schema, &nested(MAX_DEPTH, "(", ")"), None, &data), Ok(vec![1, 3]));
+        assert_eq!(filter(&schema, &nested(MAX_DEPTH, "not ", ""), None, &data), Ok(vec![1, 3]));
+
+        let error = "Bad request: Invalid filter: nested more than 64 levels deep".to_string();
+        assert_eq!(filter(&schema, &nested(MAX_DEPTH + 1, "(", ")"), None, &data), Err(error.clone()));
+        // Deep enough to overflow the stack of a worker thread without the limit
+        assert_eq!(filter(&schema, &nested(10_000, "not (", ")"), None, &data), Err(error));
+
+        // Long chains do not nest
+        let chain = vec!["age = 30"; 10_000].join(" or ");
+        assert_eq!(filter(&schema, &chain, None, &data), Ok(vec![1, 3]));
+    }
+
+    #[test]
+    fn takes_created_at_bounds_from_the_whole_filter() {
+        let schema = people();
+        let range = |filter: &str| {
+            let query = ResponseQuery { filter: Some(filter.to_string()), ..Default::default() };
+            let (from, to) = ResponseFilter::compile(&schema, &query).unwrap().created_range();
+            (from.map(|ts| ts.to_rfc3339()), to.map(|ts| ts.to_rfc3339()))
+        };
+        let day = |d: u32| Some(Utc.with_ymd_and_hms(2024, 5, d, 0, 0, 0).unwrap().to_rfc3339());
+        assert_eq!(range("age = 30"), (None, None));
+        assert_eq!(range("created_at >= 2024-05-02"), (day(2), None));
+        assert_eq!(
+            range("created_at > 2024-05-01 and age = 30 and created_at >= 2024-05-02 and created_at < 2024-05-09"),
+            (day(2), day(9))
+        );
+        assert_eq!(range("created_at = 2024-05-03"), (day(3), day(3)));
+        // Bounds that only part of the filter needs narrow nothing
+        assert_eq!(range("created_at >= 2024-05-02 or age = 30"), (None, None));
+        assert_eq!(range("not created_at >= 2024-05-02"), (None, None));
+        assert_eq!(range("created_at != 2024-05-02"), (None, None));
+    }
+}
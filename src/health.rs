@@ -0,0 +1,126 @@
+// src/health.rs
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use actix_web::{get, web, HttpResponse, Responder};
+use tracing::{info, warn};
+use serde_json::json;
+
+use crate::{
+    error::AppError,
+    models::{ApiResponse, ErrorResponse},
+    store::FormStore,
+};
+
+const READY_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(healthz).service(readyz);
+}
+
+/// Last observed readiness, so that only changes are logged rather than
+/// every probe, and whether the service is shutting down.
+pub struct Readiness {
+    database: AtomicBool,
+    shutting_down: AtomicBool,
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        // The service only starts once the store has connected
+        Self {
+            database: AtomicBool::new(true),
+            shutting_down: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Readiness {
+    /// Makes readiness fail from now on, so load balancers stop routing here.
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
+
+    fn record(&self, ready: bool, reason: &str) {
+        if self.database.swap(ready, Ordering::Relaxed) != ready {
+            if ready {
+                info!("Database is reachable again, reporting ready");
+            } else {
+                warn!("Database check failed, reporting not ready: {}", reason);
+            }
+        }
+    }
+}
+
+/// Liveness: the process is up and serving requests.
+#[utoipa::path(
+    tag = "operations",
+    responses((status = 200, description = "Alive", body = ApiResponse<serde_json::Value>)),
+)]
+#[get("/healthz")]
+async fn healthz() -> impl Responder {
+    HttpResponse::Ok().json(ApiResponse::success(json!({ "status": "ok" })))
+}
+
+/// Readiness: the service is not shutting down, and the database is
+/// reachable with its schema up to date.
+#[utoipa::path(
+    tag = "operations",
+    responses(
+        (status = 200, description = "Ready for traffic", body = ApiResponse<serde_json::Value>),
+        (status = 503, description = "Shutting down, or the database is unavailable", body = ErrorResponse),
+    ),
+)]
+#[get("/readyz")]
+async fn readyz(store: web::Data<dyn FormStore>, readiness: web::Data<Readiness>) -> Result<HttpResponse, AppError> {
+    if readiness.shutting_down.load(Ordering::Relaxed) {
+        return Err(AppError::Unavailable("Shutting down".to_string()));
+    }
+
+    let result = match tokio::time::timeout(READY_TIMEOUT, store.check_ready()).await {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err("Database check timed out".to_string()),
+    };
+
+    match result {
+        Ok(()) => {
+            readiness.record(true, "");
+            Ok(HttpResponse::Ok().json(ApiResponse::success(json!({ "status": "ready" }))))
+        }
+        Err(reason) => {
+            readiness.record(false, &reason);
+            Err(AppError::Unavailable(reason))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use actix_web::{http::StatusCode, test, App};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn reports_not_ready_once_shutting_down() {
+        let store: Arc<dyn FormStore> = Arc::new(MemoryStore::default());
+        let readiness = web::Data::new(Readiness::default());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(store))
+                .app_data(readiness.clone())
+                .configure(config),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/readyz").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        readiness.begin_shutdown();
+        let req = test::TestRequest::get().uri("/readyz").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: String = test::read_body_json(resp).await;
+        assert_eq!(body, "Service unavailable: Shutting down");
+    }
+}
@@ -0,0 +1,565 @@
+// src/jsonschema.rs
+//! Conversion between forms and JSON Schema (draft 2020-12).
+//!
+//! [`export`] describes the `data` of a valid response to a form. [`import`]
+//! goes the other way: the document must describe an object, each of whose
+//! properties becomes a field. Keywords with no counterpart in [`FormField`]
+//! are reported as warnings rather than failing the import, and properties
+//! that cannot be represented at all are skipped.
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    definition::RESERVED_ID_CHARS,
+    error::AppError,
+    models::{FieldOption, FieldType, FormField, FormSchema},
+    validation::{compile_pattern, group_bounds, rating_max, selection_bounds, NPS_MAX, RANGE_MAX, RANGE_MIN, RANGE_STEP},
+};
+
+const DRAFT_2020_12: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Keywords that only document a schema; they are dropped silently.
+const ANNOTATIONS: &[&str] = &["$comment", "$id", "$schema", "description", "deprecated", "readOnly", "writeOnly"];
+
+/// Nested `$ref`s followed before giving up, which also stops cycles.
+const MAX_REF_DEPTH: usize = 8;
+
+/// A schema of the `data` of valid responses to `schema`. Optional fields
+/// may be missing or `null`.
+pub fn export(schema: &FormSchema) -> Value {
+    let mut document = Map::new();
+    document.insert("$schema".to_string(), json!(DRAFT_2020_12));
+    document.insert("title".to_string(), json!(schema.name));
+    document.extend(object_schema(&schema.fields));
+    if let Some(description) = &schema.description {
+        document.insert("description".to_string(), json!(description));
+    }
+    if let Some(id) = schema.id {
+        document.insert("$id".to_string(), json!(format!("urn:uuid:{}", id)));
+    }
+    Value::Object(document)
+}
+
+/// An object with one property per field, as in a response or a group item.
+fn object_schema(fields: &[FormField]) -> Map<String, Value> {
+    let properties: Map<String, Value> = fields
+        .iter()
+        .map(|field| (field.id.clone(), field_schema(field)))
+        .collect();
+    let required: Vec<&str> = fields.iter().filter(|f| f.required).map(|f| f.id.as_str()).collect();
+
+    let mut schema = Map::new();
+    schema.insert("type".to_string(), json!("object"));
+    schema.insert("properties".to_string(), Value::Object(properties));
+    schema.insert("required".to_string(), json!(required));
+    schema.insert("additionalProperties".to_string(), json!(false));
+    schema
+}
+
+fn field_schema(field: &FormField) -> Value {
+    let choices = field
+        .options
+        .as_ref()
+        .filter(|options| !options.is_empty())
+        .map(|options| options.iter().map(|o| o.value.as_str()).collect::<Vec<_>>());
+
+    let mut schema = match (&field.field_type, choices) {
+        (FieldType::Select | FieldType::Radio | FieldType::Checkbox, Some(values)) => {
+            json!({ "type": "string", "enum": values })
+        }
+        (FieldType::Checkbox, None) => json!({ "type": "boolean" }),
+        (FieldType::MultiSelect | FieldType::Ranking, choices) => {
+            let (min, max) = selection_bounds(field);
+            let mut schema = json!({
+                "type": "array",
+                "items": { "type": "string", "enum": choices.unwrap_or_default() },
+                "uniqueItems": true,
+                "maxItems": max,
+            });
+            if min > 0 {
+                schema["minItems"] = json!(min);
+            }
+            schema
+        }
+        (FieldType::Matrix, choices) => {
+            let values = choices.unwrap_or_default();
+            let answer = if field.multiple {
+                json!({ "type": "array", "items": { "type": "string", "enum": values }, "uniqueItems": true })
+            } else {
+                json!({ "type": "string", "enum": values })
+            };
+            let rows = field.rows.as_deref().unwrap_or_default();
+            let properties: Map<String, Value> = rows
+                .iter()
+                .map(|row| {
+                    let mut schema = answer.clone();
+                    schema["title"] = json!(row.label);
+                    (row.id.clone(), schema)
+                })
+                .collect();
+            let required: Vec<&str> = rows
+                .iter()
+                .filter(|row| row.required.unwrap_or(field.required))
+                .map(|row| row.id.as_str())
+                .collect();
+            json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+                "additionalProperties": false,
+            })
+        }
+        (FieldType::Group, _) => {
+            let (min, max) = group_bounds(field);
+            let mut schema = json!({
+                "type": "array",
+                "items": Value::Object(object_schema(field.fields.as_deref().unwrap_or_default())),
+            });
+            if min > 0 {
+                schema["minItems"] = json!(min);
+            }
+            if max < usize::MAX {
+                schema["maxItems"] = json!(max);
+            }
+            schema
+        }
+        (FieldType::Number, _) => bounded_number(field.min, field.max),
+        (FieldType::Rating, _) => json!({ "type": "integer", "minimum": 1, "maximum": rating_max(field) }),
+        (FieldType::Nps, _) => json!({ "type": "integer", "minimum": 0, "maximum": NPS_MAX }),
+        (FieldType::Range, _) => {
+            let min = field.min.unwrap_or(RANGE_MIN);
+            let step = field.step.unwrap_or(RANGE_STEP);
+            let mut schema = bounded_number(Some(min), Some(field.max.unwrap_or(RANGE_MAX)));
+            // Steps are counted from `min`, which multipleOf can only express from 0
+            if min % step == 0.0 {
+                schema["multipleOf"] = json!(step);
+            }
+            schema
+        }
+        (FieldType::Email, _) => json!({ "type": "string", "format": "email" }),
+        (FieldType::Date, _) => json!({ "type": "string", "format": "date" }),
+        (FieldType::DateTime, _) => json!({ "type": "string", "format": "date-time" }),
+        (FieldType::Url, _) => json!({ "type": "string", "format": "uri" }),
+        // Stricter than `format: time`, which also allows an offset
+        (FieldType::Time, _) => json!({ "type": "string", "pattern": r"^\d{2}:\d{2}:\d{2}$" }),
+        (FieldType::Phone, _) => json!({ "type": "string", "pattern": r"^\+[1-9][0-9]{1,14}$" }),
+        (FieldType::Color, _) => json!({ "type": "string", "pattern": "^#[0-9a-f]{6}$" }),
+        (FieldType::Text | FieldType::Textarea | FieldType::Select | FieldType::Radio, _) => {
+            json!({ "type": "string" })
+        }
+    };
+
+    schema["title"] = json!(field.label);
+    if let (Some(pattern), Some("string")) = (&field.validation, schema["type"].as_str()) {
+        // Both must match when the type already implies a pattern
+        match schema.get("pattern") {
+            Some(_) => schema["allOf"] = json!([{ "pattern": pattern }]),
+            None => schema["pattern"] = json!(pattern),
+        }
+    }
+    // Blank answers to optional fields are stored as null
+    if !field.required && schema["type"] != "boolean" {
+        let kind = schema["type"].clone();
+        schema["type"] = json!([kind, "null"]);
+        if let Some(values) = schema.get_mut("enum").and_then(Value::as_array_mut) {
+            values.push(Value::Null);
+        }
+    }
+    schema
+}
+
+fn bounded_number(min: Option<f64>, max: Option<f64>) -> Value {
+    let mut schema = json!({ "type": "number" });
+    if let Some(min) = min {
+        schema["minimum"] = json!(min);
+    }
+    if let Some(max) = max {
+        schema["maximum"] = json!(max);
+    }
+    schema
+}
+
+/// Converts `document` into a form, returning it with the warnings about
+/// what could not be converted.
+pub fn import(document: &Value) -> Result<(FormSchema, Vec<String>), AppError> {
+    let root = document
+        .as_object()
+        .ok_or_else(|| AppError::BadRequest("A JSON Schema must be an object".to_string()))?;
+    if root.get("type").is_some_and(|t| t != "object") {
+        return Err(AppError::BadRequest("Only schemas of `\"type\": \"object\"` can be imported".to_string()));
+    }
+    if !root.get("properties").is_some_and(Value::is_object) {
+        return Err(AppError::BadRequest("The schema has no `properties` to turn into fields".to_string()));
+    }
+
+    let mut importer = Importer {
+        defs: root.get("$defs").and_then(Value::as_object),
+        warnings: Vec::new(),
+    };
+
+    if let Some(dialect) = root.get("$schema").and_then(Value::as_str) {
+        if dialect.trim_end_matches('#') != DRAFT_2020_12 {
+            importer.warn("/$schema", format!("dialect {} is read as draft 2020-12", dialect));
+        }
+    }
+    importer.unsupported(
+        "",
+        root,
+        &["type", "title", "properties", "required", "$defs"],
+    );
+
+    let fields = importer.fields(root, "");
+    if fields.is_empty() {
+        return Err(AppError::BadRequest(format!(
+            "None of the properties could be converted to a field: {}",
+            importer.warnings.join("; ")
+        )));
+    }
+
+    let schema = FormSchema {
+        id: None,
+        name: root
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or("Imported form")
+            .to_string(),
+        description: root.get("description").and_then(Value::as_str).map(str::to_string),
+        fields,
+        created_at: None,
+        updated_at: None,
+    };
+    Ok((schema, importer.warnings))
+}
+
+struct Importer<'a> {
+    defs: Option<&'a Map<String, Value>>,
+    warnings: Vec<String>,
+}
+
+impl<'a> Importer<'a> {
+    fn warn(&mut self, pointer: &str, message: impl Into<String>) {
+        let pointer = if pointer.is_empty() { "/" } else { pointer };
+        self.warnings.push(format!("{}: {}", pointer, message.into()));
+    }
+
+    /// Warns about every keyword of `schema` that is neither handled nor an
+    /// annotation.
+    fn unsupported(&mut self, pointer: &str, schema: &Map<String, Value>, handled: &[&str]) {
+        for keyword in schema.keys() {
+            if !handled.contains(&keyword.as_str()) && !ANNOTATIONS.contains(&keyword.as_str()) {
+                self.warn(&format!("{}/{}", pointer, escape(keyword)), "not supported");
+            }
+        }
+    }
+
+    /// The property with its local `$ref`s replaced by what they point to.
+    /// Keywords next to a `$ref` take precedence over the referenced ones.
+    fn resolve(&mut self, pointer: &str, property: &Map<String, Value>) -> Option<Map<String, Value>> {
+        let mut resolved = property.clone();
+        for _ in 0..MAX_REF_DEPTH {
+            let Some(reference) = resolved.remove("$ref") else {
+                return Some(resolved);
+            };
+            let target = reference
+                .as_str()
+                .and_then(|r| r.strip_prefix("#/$defs/"))
+                .and_then(|name| self.defs?.get(&unescape(name)))
+                .and_then(Value::as_object);
+            let Some(target) = target else {
+                self.warn(pointer, format!("$ref {} does not point into $defs", reference));
+                return None;
+            };
+            let mut merged = target.clone();
+            merged.extend(resolved);
+            resolved = merged;
+        }
+        self.warn(pointer, "$ref nesting is too deep");
+        None
+    }
+
+    /// The fields of an object schema, one per convertible property.
+    fn fields(&mut self, object: &Map<String, Value>, pointer: &str) -> Vec<FormField> {
+        let required: Vec<&str> = object
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|names| names.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let Some(properties) = object.get("properties").and_then(Value::as_object) else {
+            return Vec::new();
+        };
+
+        properties
+            .iter()
+            .filter_map(|(name, property)| {
+                let pointer = format!("{}/properties/{}", pointer, escape(name));
+                if name.contains(RESERVED_ID_CHARS) {
+                    self.warn(&pointer, "names containing `[`, `]` or `.` cannot be field ids; skipped");
+                    return None;
+                }
+                self.field(name, property, &pointer, required.contains(&name.as_str()))
+            })
+            .collect()
+    }
+
+    fn field(&mut self, name: &str, property: &Value, pointer: &str, required: bool) -> Option<FormField> {
+        let Some(property) = property.as_object() else {
+            self.warn(pointer, "boolean schemas cannot be converted to a field; skipped");
+            return None;
+        };
+        let property = self.resolve(pointer, property)?;
+
+        let mut field = FormField {
+            id: name.to_string(),
+            label: property
+                .get("title")
+                .and_then(Value::as_str)
+                .unwrap_or(name)
+                .to_string(),
+            field_type: FieldType::Text,
+            required,
+            placeholder: property
+                .get("examples")
+                .and_then(|examples| examples.get(0))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            options: None,
+            validation: None,
+            min: None,
+            max: None,
+            step: None,
+            min_selections: None,
+            max_selections: None,
+            rows: None,
+            multiple: false,
+            fields: None,
+            min_items: None,
+            max_items: None,
+        };
+
+        if let Some(options) = self.options(&property) {
+            field.field_type = FieldType::Select;
+            field.options = Some(options);
+            self.unsupported(pointer, &property, &["type", "title", "examples", "enum", "oneOf"]);
+            return Some(field);
+        }
+
+        let items_pointer = format!("{}/items", pointer);
+        let items = match property.get("items").and_then(Value::as_object) {
+            Some(items) => self.resolve(&items_pointer, items),
+            None => None,
+        };
+        let item_options = items.as_ref().and_then(|items| self.options(items));
+        // Arrays of objects become groups of the objects' fields
+        let item_fields = match &items {
+            Some(items) if item_options.is_none() && items.contains_key("properties") => {
+                self.unsupported(&items_pointer, items, &["type", "title", "properties", "required"]);
+                Some(self.fields(items, &items_pointer)).filter(|fields| !fields.is_empty())
+            }
+            _ => None,
+        };
+        let types: Vec<&str> = match property.get("type") {
+            Some(Value::String(t)) => vec![t.as_str()],
+            Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).filter(|t| *t != "null").collect(),
+            _ => Vec::new(),
+        };
+        let handled: &[&str] = match types.as_slice() {
+            ["string"] => {
+                match property.get("format").and_then(Value::as_str) {
+                    Some("email") => field.field_type = FieldType::Email,
+                    Some("date") => field.field_type = FieldType::Date,
+                    Some("date-time") => field.field_type = FieldType::DateTime,
+                    Some("time") => field.field_type = FieldType::Time,
+                    Some("uri") => field.field_type = FieldType::Url,
+                    Some(format) => self.warn(&format!("{}/format", pointer), format!("format {} is imported as text", format)),
+                    None => {}
+                }
+                if let Some(pattern) = property.get("pattern").and_then(Value::as_str) {
+                    match compile_pattern(pattern) {
+                        Ok(_) => field.validation = Some(pattern.to_string()),
+                        Err(reason) => self.warn(&format!("{}/pattern", pointer), format!("{}; dropped", reason)),
+                    }
+                }
+                &["type", "title", "examples", "format", "pattern"]
+            }
+            ["number"] | ["integer"] => {
+                if types == ["integer"] {
+                    self.warn(&format!("{}/type", pointer), "integers are imported as numbers, which also accept fractions");
+                }
+                field.field_type = FieldType::Number;
+                field.min = property.get("minimum").and_then(Value::as_f64);
+                field.max = property.get("maximum").and_then(Value::as_f64);
+                &["type", "title", "examples", "minimum", "maximum"]
+            }
+            ["boolean"] => {
+                field.field_type = FieldType::Checkbox;
+                &["type", "title", "examples"]
+            }
+            ["array"] if item_options.is_some() => {
+                field.field_type = FieldType::MultiSelect;
+                field.options = item_options;
+                field.min_selections = property.get("minItems").and_then(Value::as_u64).map(|n| n as usize);
+                field.max_selections = property.get("maxItems").and_then(Value::as_u64).map(|n| n as usize);
+                &["type", "title", "examples", "items", "minItems", "maxItems", "uniqueItems"]
+            }
+            ["array"] if item_fields.is_some() => {
+                field.field_type = FieldType::Group;
+                field.fields = item_fields;
+                field.min_items = property.get("minItems").and_then(Value::as_u64).map(|n| n as usize);
+                field.max_items = property.get("maxItems").and_then(Value::as_u64).map(|n| n as usize);
+                &["type", "title", "examples", "items", "minItems", "maxItems"]
+            }
+            [] => {
+                self.warn(pointer, "no type given; skipped");
+                return None;
+            }
+            [kind] => {
+                self.warn(pointer, format!("{} values cannot be converted to a field; skipped", kind));
+                return None;
+            }
+            _ => {
+                self.warn(pointer, format!("a choice of types ({}) cannot be converted to a field; skipped", types.join(", ")));
+                return None;
+            }
+        };
+        self.unsupported(pointer, &property, handled);
+        Some(field)
+    }
+
+    /// Choices given as `enum`, or as a `oneOf` of `const`s with titles.
+    fn options(&self, property: &Map<String, Value>) -> Option<Vec<FieldOption>> {
+        if let Some(values) = property.get("enum").and_then(Value::as_array) {
+            return Some(
+                values
+                    .iter()
+                    .filter_map(option_value)
+                    .map(|value| FieldOption {
+                        label: value.clone(),
+                        value,
+                    })
+                    .collect(),
+            );
+        }
+
+        let choices = property.get("oneOf")?.as_array()?;
+        // Any other oneOf is reported as unsupported along with the rest
+        choices
+            .iter()
+            .map(|choice| {
+                let value = option_value(choice.get("const")?)?;
+                let label = choice.get("title").and_then(Value::as_str).unwrap_or(&value).to_string();
+                Some(FieldOption { value, label })
+            })
+            .collect()
+    }
+}
+
+fn option_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Escapes a name for use as a JSON pointer segment.
+pub fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{parse, Format};
+    use uuid::Uuid;
+
+    const TRIP: &str = "name: Trip Booking\nfields:\n\
+        \x20 - { id: email, label: Email, field_type: Email, required: true, validation: '@example\\.com$' }\n\
+        \x20 - { id: phone, label: Phone, field_type: Phone, required: false, validation: '^\\+44' }\n\
+        \x20 - id: seat\n\
+        \x20   label: Seat\n\
+        \x20   field_type: Select\n\
+        \x20   required: false\n\
+        \x20   options: [{ value: aisle, label: Aisle }, { value: window, label: Window }]\n\
+        \x20 - id: service\n\
+        \x20   label: Service\n\
+        \x20   field_type: Matrix\n\
+        \x20   required: true\n\
+        \x20   rows: [{ id: speed, label: Speed }, { id: price, label: Price, required: false }]\n\
+        \x20   options: [{ value: good, label: Good }, { value: bad, label: Bad }]\n\
+        \x20 - id: travellers\n\
+        \x20   label: Travellers\n\
+        \x20   field_type: Group\n\
+        \x20   required: true\n\
+        \x20   max_items: 4\n\
+        \x20   fields:\n\
+        \x20     - { id: name, label: Name, field_type: Text, required: true }\n\
+        \x20     - { id: age, label: Age, field_type: Number, required: false, min: 0 }\n";
+
+    #[test]
+    fn exports_choices_patterns_matrices_and_groups() {
+        let mut schema = parse(TRIP, Format::Yaml).unwrap();
+        schema.id = Some(Uuid::nil());
+        let document = export(&schema);
+
+        assert_eq!(document["$schema"], DRAFT_2020_12);
+        assert_eq!(document["$id"], format!("urn:uuid:{}", Uuid::nil()));
+        assert_eq!(document["title"], "Trip Booking");
+        assert_eq!(document["required"], json!(["email", "service", "travellers"]));
+        assert_eq!(document["additionalProperties"], false);
+
+        let properties = &document["properties"];
+        assert_eq!(
+            properties["email"],
+            json!({ "type": "string", "format": "email", "title": "Email", "pattern": "@example\\.com$" })
+        );
+        // The pattern of the field type and the field's own must both match
+        assert_eq!(
+            properties["phone"],
+            json!({
+                "type": ["string", "null"],
+                "pattern": r"^\+[1-9][0-9]{1,14}$",
+                "allOf": [{ "pattern": r"^\+44" }],
+                "title": "Phone",
+            })
+        );
+        assert_eq!(
+            properties["seat"],
+            json!({ "type": ["string", "null"], "enum": ["aisle", "window", null], "title": "Seat" })
+        );
+        assert_eq!(
+            properties["service"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "speed": { "type": "string", "enum": ["good", "bad"], "title": "Speed" },
+                    "price": { "type": "string", "enum": ["good", "bad"], "title": "Price" },
+                },
+                "required": ["speed"],
+                "additionalProperties": false,
+                "title": "Service",
+            })
+        );
+        assert_eq!(
+            properties["travellers"],
+            json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string", "title": "Name" },
+                        "age": { "type": ["number", "null"], "minimum": 0.0, "title": "Age" },
+                    },
+                    "required": ["name"],
+                    "additionalProperties": false,
+                },
+                "minItems": 1,
+                "maxItems": 4,
+                "title": "Travellers",
+            })
+        );
+    }
+}
@@ -0,0 +1,28 @@
+// src/lib.rs
+//! The form service as a library, shared by the `form_portal` server and the
+//! `form-portal-admin` tool.
+
+pub mod api;
+pub mod auth;
+pub mod config;
+pub mod db;
+pub mod definition;
+pub mod error;
+pub mod events;
+pub mod export;
+pub mod filter;
+pub mod health;
+pub mod jsonschema;
+pub mod metrics;
+pub mod migrations;
+pub mod models;
+pub mod notifications;
+pub mod openapi;
+pub mod search;
+pub mod shutdown;
+pub mod store;
+pub mod summary;
+pub mod telemetry;
+pub mod templates;
+pub mod typescript;
+pub mod validation;
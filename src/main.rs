@@ -1,49 +1,123 @@
 // src/main.rs
-use actix_web::{web, App, HttpServer};
+use actix_web::{middleware, web, App, HttpServer};
+use clap::Parser;
 use dotenv::dotenv;
-use log::info;
-use scylla::{SessionBuilder};
-use std::sync::Arc;
+use tracing::info;
 
-mod api;
-mod db;
-mod error;
-mod models;
-mod templates;
+use form_portal::{api, auth, config, db, events, health, metrics, notifications, search, shutdown, store, telemetry, templates};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
-
-    // Initialize database connection
-    let scylla_uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-    let session = SessionBuilder::new()
-        .known_node(scylla_uri)
-        .build()
-        .await
-        .expect("Failed to connect to ScyllaDB");
+
+    let cli = config::Cli::parse();
+    let config = config::Config::load(cli.config.as_deref(), &cli.overrides).unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {:#}", e);
+        std::process::exit(2);
+    });
+
+    if cli.print_config {
+        print!("{}", config.to_redacted_toml().expect("Failed to serialize configuration"));
+        return Ok(());
+    }
+
+    let _telemetry = telemetry::init(&config.logging).expect("Failed to initialize logging");
+
+    if let Some(config::Command::Migrate { dry_run }) = cli.command {
+        if config.storage.backend != store::StorageBackend::Scylla {
+            eprintln!("Migrations only apply to the scylla backend; the SQL backends create their tables on startup");
+            std::process::exit(2);
+        }
+        db::migrate(&config.storage.scylla, dry_run).await.expect("Failed to run migrations");
+        return Ok(());
+    }
+
+    // Initialize the storage backend (ScyllaDB unless configured otherwise)
+    let store = store::connect(&config.storage).await.expect("Failed to initialize storage backend");
+    
+    // Open the full-text search index
+    let search = search::SearchIndex::open(&config.search.index_dir).expect("Failed to open search index");
     
-    let session = Arc::new(session);
+    if let Some(config::Command::RebuildSearchIndex) = cli.command {
+        let total = search::rebuild(store.as_ref(), &search).await.expect("Failed to rebuild search index");
+        info!("Rebuilt search index with {} responses", total);
+        return Ok(());
+    }
     
-    // Initialize the keyspace and tables
-    db::init_database(&session).await.expect("Failed to initialize database");
+    let store: web::Data<dyn store::FormStore> = web::Data::from(store);
+    let search = web::Data::new(search);
+    actix_web::rt::spawn(search::commit_periodically(search.clone().into_inner(), config.search.commit_interval()));
+    let renderer = web::Data::new(
+        templates::FormRenderer::new(config.templates.dir.as_deref()).expect("Failed to load form template"),
+    );
     
-    // ...existing code...
-    info!("Starting server at http://127.0.0.1:8080");
-
-    let bind_address = std::env::var("LISTEN_ADDR")
-        .or_else(|_| std::env::var("HOST"))
-        .unwrap_or_else(|_| "127.0.0.1".to_string());
-    let bind_addr = format!("{}:8080", bind_address);
-    info!("Binding to {}", bind_addr);
-
-    HttpServer::new(move || {
-        App::new()
-            .app_data(web::Data::new(session.clone()))
-            .configure(api::config)
-    })
-    .bind(bind_addr)?
-    .run()
-    .await
-}
\ No newline at end of file
+    // Start the email notification worker
+    let notifier = notifications::Notifier::start(&config.smtp).expect("Failed to start email notifier");
+
+    let bind_addr = (config.server.host.clone(), config.server.port);
+    info!("Starting server at http://{}:{}", bind_addr.0, bind_addr.1);
+
+    let events = events::ResponseEvents::default();
+    let readiness = web::Data::new(health::Readiness::default());
+    let in_flight = web::Data::new(shutdown::InFlight::default());
+    let max_body_bytes = config.limits.max_body_bytes;
+    let auth = web::Data::new(config.auth.clone());
+
+    // The workers get their own handles; these stay behind for shutdown
+    let mut server = HttpServer::new({
+        let (store, search, notifier, events, readiness, in_flight) = (
+            store.clone(),
+            search.clone(),
+            notifier.clone(),
+            events.clone(),
+            readiness.clone(),
+            in_flight.clone(),
+        );
+        move || {
+            App::new()
+                .app_data(web::JsonConfig::default().limit(max_body_bytes))
+                .app_data(web::FormConfig::default().limit(max_body_bytes))
+                .app_data(web::PayloadConfig::new(max_body_bytes))
+                .app_data(store.clone())
+                .app_data(web::Data::new(notifier.clone()))
+                .app_data(web::Data::new(events.clone()))
+                .app_data(search.clone())
+                .app_data(renderer.clone())
+                .app_data(readiness.clone())
+                .app_data(auth.clone())
+                .app_data(in_flight.clone())
+                .wrap(middleware::from_fn(auth::require_api_key))
+                .wrap(middleware::from_fn(metrics::track_requests))
+                .wrap(middleware::from_fn(telemetry::trace_requests))
+                .wrap(middleware::from_fn(shutdown::track_in_flight))
+                .configure(health::config)
+                .configure(metrics::config)
+                .configure(api::config)
+        }
+    });
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+
+    // Signals are handled in `shutdown` so readiness fails before the listener closes
+    let server = server
+        .disable_signals()
+        .shutdown_timeout(config.shutdown.timeout_secs)
+        .bind(bind_addr)?
+        .run();
+    let stopped_at = shutdown::on_signal(
+        server.handle(),
+        readiness.into_inner(),
+        in_flight.into_inner(),
+        events,
+        &config.shutdown,
+    );
+    server.await?;
+
+    let stopped_at = stopped_at.get().copied().unwrap_or_else(tokio::time::Instant::now);
+    shutdown::drain(&notifier, store.get_ref(), stopped_at + config.shutdown.timeout()).await;
+    if let Err(e) = search.commit() {
+        tracing::warn!("Failed to commit the search index: {}", e);
+    }
+    Ok(())
+}
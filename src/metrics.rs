@@ -0,0 +1,210 @@
+// src/metrics.rs
+use std::future::Future;
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{get, web, HttpResponse, Responder};
+use prometheus::{
+    exponential_buckets, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use uuid::Uuid;
+
+use crate::notifications::Notifier;
+
+/// Process-wide metrics, exposed in the Prometheus text format at `/metrics`.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    db_duration: HistogramVec,
+    submissions: IntCounterVec,
+    validation_failures: IntCounterVec,
+    render_cache: IntCounterVec,
+    notification_queue_depth: IntGauge,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("form_portal".to_string()), None).expect("valid metrics prefix");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route and status")
+                .buckets(exponential_buckets(0.001, 2.5, 10).unwrap()),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let db_duration = HistogramVec::new(
+            HistogramOpts::new("db_query_duration_seconds", "ScyllaDB query latency by operation")
+                .buckets(exponential_buckets(0.0005, 2.5, 10).unwrap()),
+            &["operation"],
+        )
+        .unwrap();
+        let submissions = IntCounterVec::new(
+            Opts::new("form_submissions_total", "Stored form submissions"),
+            &["form_id"],
+        )
+        .unwrap();
+        let validation_failures = IntCounterVec::new(
+            Opts::new("validation_failures_total", "Requests rejected by validation"),
+            &["form_id"],
+        )
+        .unwrap();
+        let render_cache = IntCounterVec::new(
+            Opts::new("render_cache_requests_total", "Form renders served from or missing the cache"),
+            &["result"],
+        )
+        .unwrap();
+        let notification_queue_depth = IntGauge::new(
+            "notification_queue_depth",
+            "Emails waiting in the notification queue",
+        )
+        .unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_duration.clone())).unwrap();
+        registry.register(Box::new(db_duration.clone())).unwrap();
+        registry.register(Box::new(submissions.clone())).unwrap();
+        registry.register(Box::new(validation_failures.clone())).unwrap();
+        registry.register(Box::new(render_cache.clone())).unwrap();
+        registry.register(Box::new(notification_queue_depth.clone())).unwrap();
+
+        Self {
+            registry,
+            http_requests,
+            http_duration,
+            db_duration,
+            submissions,
+            validation_failures,
+            render_cache,
+            notification_queue_depth,
+        }
+    }
+
+    pub fn record_submission(&self, form_id: Uuid) {
+        self.submissions.with_label_values(&[&form_id.to_string()]).inc();
+    }
+
+    pub fn record_validation_failure(&self, form_id: Uuid) {
+        self.validation_failures.with_label_values(&[&form_id.to_string()]).inc();
+    }
+
+    pub fn record_render(&self, cache_hit: bool) {
+        let result = if cache_hit { "hit" } else { "miss" };
+        self.render_cache.with_label_values(&[result]).inc();
+    }
+}
+
+/// Runs a database call, recording its latency under `operation`.
+pub async fn time_db<T>(operation: &str, query: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let result = query.await;
+    METRICS
+        .db_duration
+        .with_label_values(&[operation])
+        .observe(start.elapsed().as_secs_f64());
+    result
+}
+
+/// Middleware recording the count and latency of every request, labelled by
+/// route pattern rather than path to keep the number of series bounded.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+
+    let result = next.call(req).await;
+
+    let status = match &result {
+        Ok(response) => response.status().as_u16(),
+        Err(e) => e.as_response_error().status_code().as_u16(),
+    }
+    .to_string();
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    METRICS.http_requests.with_label_values(&labels).inc();
+    METRICS
+        .http_duration
+        .with_label_values(&labels)
+        .observe(start.elapsed().as_secs_f64());
+
+    result
+}
+
+/// Prometheus metrics in the text exposition format.
+#[utoipa::path(
+    tag = "operations",
+    responses((status = 200, description = "Current metric values", body = String, content_type = "text/plain")),
+)]
+#[get("/metrics")]
+async fn metrics(notifier: web::Data<Notifier>) -> impl Responder {
+    METRICS.notification_queue_depth.set(notifier.queue_depth() as i64);
+
+    match TextEncoder::new().encode_to_string(&METRICS.registry.gather()) {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(body),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(metrics);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{middleware, test, App};
+
+    #[actix_web::test]
+    async fn counts_requests_by_route_pattern() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Notifier::disabled()))
+                .wrap(middleware::from_fn(track_requests))
+                .route("/widgets/{id}", web::get().to(HttpResponse::Ok))
+                .configure(config),
+        )
+        .await;
+
+        for id in ["1", "2"] {
+            let req = test::TestRequest::get().uri(&format!("/widgets/{}", id)).to_request();
+            test::call_service(&app, req).await;
+        }
+        let form_id = Uuid::new_v4();
+        METRICS.record_submission(form_id);
+
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.headers().get("Content-Type").unwrap(), "text/plain; version=0.0.4");
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+        // Both paths share the series of their route
+        let requests = r#"form_portal_http_requests_total{method="GET",route="/widgets/{id}",status="200"} 2"#;
+        assert!(body.contains(requests), "{}", body);
+        assert!(body.contains("form_portal_http_request_duration_seconds_bucket{"), "{}", body);
+        let submissions = format!(r#"form_portal_form_submissions_total{{form_id="{}"}} 1"#, form_id);
+        assert!(body.contains(&submissions), "{}", body);
+        assert!(body.contains("form_portal_notification_queue_depth 0"), "{}", body);
+    }
+
+    #[actix_web::test]
+    async fn times_database_calls_by_operation() {
+        assert_eq!(time_db("test_operation", async { 42 }).await, 42);
+        let count = METRICS.db_duration.with_label_values(&["test_operation"]).get_sample_count();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,325 @@
+// src/migrations.rs
+//! Ordered, versioned schema migrations for the ScyllaDB keyspace.
+//!
+//! Applied versions are recorded in `schema_migrations`; a lightweight
+//! transaction on `schema_migration_lock` keeps concurrently starting
+//! instances from running the same migration twice. New migrations are
+//! appended to [`MIGRATIONS`] with the next version number and must never be
+//! edited once released.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use futures::future::BoxFuture;
+use tracing::{info, warn};
+use scylla::frame::response::result::CqlValue;
+use scylla::Session;
+use uuid::Uuid;
+
+use crate::db;
+
+const LOCK_TTL_SECS: u32 = 600;
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+const LOCK_ATTEMPTS: u32 = 30;
+
+pub enum Step {
+    /// A CQL statement; `{keyspace}` is replaced with the keyspace name.
+    Cql(&'static str),
+    /// Arbitrary code, for data reshaping CQL cannot express.
+    Run {
+        description: &'static str,
+        run: for<'a> fn(&'a Session, &'a str) -> BoxFuture<'a, Result<()>>,
+    },
+}
+
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub steps: &'static [Step],
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_form_schemas",
+        steps: &[Step::Cql(
+            "CREATE TABLE IF NOT EXISTS {keyspace}.form_schemas (
+                id uuid PRIMARY KEY,
+                name text,
+                description text,
+                fields text,
+                created_at timestamp,
+                updated_at timestamp
+            )",
+        )],
+    },
+    Migration {
+        version: 2,
+        name: "create_form_notifications",
+        steps: &[Step::Cql(
+            "CREATE TABLE IF NOT EXISTS {keyspace}.form_notifications (
+                form_id uuid PRIMARY KEY,
+                settings text,
+                updated_at timestamp
+            )",
+        )],
+    },
+    Migration {
+        version: 3,
+        name: "create_missing_response_tables",
+        steps: &[Step::Run {
+            description: "create the responses table of every form that lacks one",
+            run: create_missing_response_tables,
+        }],
+    },
+    Migration {
+        version: 4,
+        name: "create_api_keys",
+        steps: &[Step::Cql(
+            "CREATE TABLE IF NOT EXISTS {keyspace}.api_keys (
+                key_hash text PRIMARY KEY,
+                id uuid,
+                name text,
+                created_at timestamp
+            )",
+        )],
+    },
+];
+
+/// Bookkeeping tables, created before anything else and outside of versioning.
+const BOOTSTRAP: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS {keyspace}.schema_migrations (
+        version int PRIMARY KEY,
+        name text,
+        applied_at timestamp
+    )",
+    "CREATE TABLE IF NOT EXISTS {keyspace}.schema_migration_lock (
+        name text PRIMARY KEY,
+        owner uuid,
+        acquired_at timestamp
+    )",
+];
+
+fn cql(statement: &str, keyspace: &str) -> String {
+    statement.replace("{keyspace}", keyspace)
+}
+
+/// Versions already recorded as applied.
+async fn applied_versions(session: &Session, keyspace: &str) -> Result<HashSet<i32>> {
+    let result = session
+        .query(cql("SELECT version FROM {keyspace}.schema_migrations", keyspace), &[])
+        .await?;
+
+    Ok(result
+        .rows
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|row| match row.columns.first() {
+            Some(Some(CqlValue::Int(version))) => Some(*version),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Migrations not yet applied, in order.
+pub async fn pending(session: &Session, keyspace: &str) -> Result<Vec<&'static Migration>> {
+    let applied = applied_versions(session, keyspace).await?;
+    Ok(not_applied(MIGRATIONS, &applied))
+}
+
+/// The `migrations` whose version is not in `applied`, in order. Applied
+/// versions this build does not know, from a newer release, are ignored.
+fn not_applied<'a>(migrations: &'a [Migration], applied: &HashSet<i32>) -> Vec<&'a Migration> {
+    migrations.iter().filter(|m| !applied.contains(&m.version)).collect()
+}
+
+/// Applies every pending migration. With `dry_run`, prints what would be
+/// executed instead and leaves the database untouched.
+pub async fn run(session: &Session, keyspace: &str, dry_run: bool) -> Result<Vec<&'static Migration>> {
+    if dry_run {
+        // The bookkeeping tables may not exist yet on a fresh keyspace
+        let pending = match pending(session, keyspace).await {
+            Ok(pending) => pending,
+            Err(_) => MIGRATIONS.iter().collect(),
+        };
+        print_plan(&pending, keyspace);
+        return Ok(pending);
+    }
+
+    for statement in BOOTSTRAP {
+        session.query(cql(statement, keyspace), &[]).await?;
+    }
+
+    let owner = acquire_lock(session, keyspace).await?;
+    let result = apply_pending(session, keyspace).await;
+    // The outcome of the migrations matters more; an unreleased lock expires
+    if let Err(e) = release_lock(session, keyspace, owner).await {
+        warn!(
+            "Failed to release the migration lock, it expires within {} seconds: {:#}",
+            LOCK_TTL_SECS, e
+        );
+    }
+    result
+}
+
+async fn apply_pending(session: &Session, keyspace: &str) -> Result<Vec<&'static Migration>> {
+    // Re-read under the lock: another instance may have just finished
+    let pending = pending(session, keyspace).await?;
+
+    for migration in &pending {
+        info!("Applying migration {} {}", migration.version, migration.name);
+        for step in migration.steps {
+            match step {
+                Step::Cql(statement) => {
+                    session.query(cql(statement, keyspace), &[]).await?;
+                }
+                Step::Run { run, .. } => run(session, keyspace).await?,
+            }
+        }
+        session
+            .query(
+                cql(
+                    "INSERT INTO {keyspace}.schema_migrations (version, name, applied_at) VALUES (?, ?, ?)",
+                    keyspace,
+                ),
+                (migration.version, migration.name, Utc::now()),
+            )
+            .await?;
+    }
+
+    if pending.is_empty() {
+        info!("Database schema is up to date");
+    }
+    Ok(pending)
+}
+
+fn print_plan(pending: &[&Migration], keyspace: &str) {
+    if pending.is_empty() {
+        println!("No pending migrations");
+        return;
+    }
+    for migration in pending {
+        println!("-- Migration {} {}", migration.version, migration.name);
+        for step in migration.steps {
+            match step {
+                Step::Cql(statement) => println!("{};", cql(statement, keyspace)),
+                Step::Run { description, .. } => println!("-- (code) {}", description),
+            }
+        }
+        println!();
+    }
+}
+
+async fn acquire_lock(session: &Session, keyspace: &str) -> Result<Uuid> {
+    let owner = Uuid::new_v4();
+
+    for _ in 0..LOCK_ATTEMPTS {
+        // The TTL releases the lock if its holder dies mid-migration
+        let result = session
+            .query(
+                cql(&format!(
+                    "INSERT INTO {{keyspace}}.schema_migration_lock (name, owner, acquired_at) VALUES ('migrations', ?, ?) IF NOT EXISTS USING TTL {}",
+                    LOCK_TTL_SECS
+                ), keyspace),
+                (owner, Utc::now()),
+            )
+            .await?;
+
+        let applied = result
+            .rows
+            .unwrap_or_default()
+            .first()
+            .and_then(|row| row.columns.first().cloned().flatten())
+            .and_then(|v| v.as_boolean())
+            .unwrap_or(false);
+        if applied {
+            return Ok(owner);
+        }
+
+        info!("Waiting for another instance to finish migrating");
+        tokio::time::sleep(LOCK_WAIT).await;
+    }
+
+    bail!("Timed out waiting for the migration lock; if no migration is running, delete the row in schema_migration_lock")
+}
+
+async fn release_lock(session: &Session, keyspace: &str, owner: Uuid) -> Result<()> {
+    session
+        .query(
+            cql(
+                "DELETE FROM {keyspace}.schema_migration_lock WHERE name = 'migrations' IF owner = ?",
+                keyspace,
+            ),
+            (owner,),
+        )
+        .await?;
+    Ok(())
+}
+
+fn create_missing_response_tables<'a>(session: &'a Session, keyspace: &'a str) -> BoxFuture<'a, Result<()>> {
+    Box::pin(async move {
+        let result = session
+            .query(cql("SELECT id FROM {keyspace}.form_schemas", keyspace), &[])
+            .await?;
+        for row in result.rows.unwrap_or_default() {
+            if let Some(Some(CqlValue::Uuid(form_id))) = row.columns.first() {
+                db::create_response_table(session, keyspace, *form_id).await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_contiguous_and_names_unique() {
+        let versions: Vec<i32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        let expected: Vec<i32> = (1..=MIGRATIONS.len() as i32).collect();
+        assert_eq!(versions, expected);
+
+        let names: HashSet<&str> = MIGRATIONS.iter().map(|m| m.name).collect();
+        assert_eq!(names.len(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn pending_migrations_keep_their_order() {
+        let versions = |applied: &[i32]| -> Vec<i32> {
+            not_applied(MIGRATIONS, &applied.iter().copied().collect())
+                .iter()
+                .map(|m| m.version)
+                .collect()
+        };
+        let all: Vec<i32> = MIGRATIONS.iter().map(|m| m.version).collect();
+
+        assert_eq!(versions(&[]), all);
+        assert_eq!(versions(&all), Vec::<i32>::new());
+        // A migration may have been applied out of order, e.g. after a failed run
+        let rest: Vec<i32> = all.iter().copied().filter(|v| ![1, 3].contains(v)).collect();
+        assert_eq!(versions(&[1, 3]), rest);
+        // Versions recorded by a newer release are not ours to apply
+        let newer: Vec<i32> = all.iter().copied().chain([i32::MAX]).collect();
+        assert_eq!(versions(&newer), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn statements_are_qualified_with_the_keyspace() {
+        for migration in MIGRATIONS {
+            for step in migration.steps {
+                if let Step::Cql(statement) = step {
+                    let statement = cql(statement, "forms");
+                    assert!(statement.contains("forms."), "{}", statement);
+                    assert!(!statement.contains("{keyspace}"), "{}", statement);
+                }
+            }
+        }
+        assert_eq!(
+            cql("SELECT version FROM {keyspace}.schema_migrations", "forms"),
+            "SELECT version FROM forms.schema_migrations"
+        );
+    }
+}
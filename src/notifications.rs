@@ -0,0 +1,487 @@
+// src/notifications.rs
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use handlebars::Handlebars;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Address, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use tracing::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{FieldType, FormField, FormSchema, NotificationSettings},
+};
+
+const DEFAULT_SUBJECT: &str = "New response to {{form_name}}";
+const RECEIPT_SUBJECT: &str = "Your response to {{form_name}}";
+const MAX_BACKOFF_SECS: u64 = 300;
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    #[default]
+    None,
+    #[value(name = "starttls")]
+    StartTls,
+    Tls,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpConfig {
+    /// Notifications are disabled when no host is configured.
+    pub host: Option<String>,
+    /// Defaults by security mode when unset.
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub security: SmtpSecurity,
+    pub max_attempts: u32,
+    pub queue_size: usize,
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            host: None,
+            port: None,
+            username: None,
+            password: None,
+            from: "Form Portal <noreply@localhost>".to_string(),
+            security: SmtpSecurity::None,
+            max_attempts: 5,
+            queue_size: 1000,
+        }
+    }
+}
+
+impl SmtpConfig {
+    pub fn port(&self) -> u16 {
+        // MailHog listens on 1025 without TLS, which is the development default
+        self.port.unwrap_or(match self.security {
+            SmtpSecurity::None => 1025,
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+        })
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Err(e) = self.from.parse::<Mailbox>() {
+            anyhow::bail!("Invalid SMTP sender address {}: {}", self.from, e);
+        }
+        if self.username.is_some() != self.password.is_some() {
+            anyhow::bail!("The SMTP username and password must be set together");
+        }
+        if self.max_attempts == 0 || self.queue_size == 0 {
+            anyhow::bail!("The SMTP max attempts and queue size must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Email {
+    to: Vec<Mailbox>,
+    subject: String,
+    body: String,
+    attempts: u32,
+}
+
+/// Handle to the background email queue. Cloning is cheap; all clones feed
+/// the same worker.
+#[derive(Clone)]
+pub struct Notifier {
+    sender: Option<mpsc::Sender<Email>>,
+    /// Emails queued or being retried, i.e. not yet sent or given up on.
+    pending: Arc<AtomicUsize>,
+}
+
+impl Notifier {
+    /// A notifier that drops every email, used when SMTP is not configured.
+    pub fn disabled() -> Self {
+        Self {
+            sender: None,
+            pending: Arc::default(),
+        }
+    }
+
+    /// Number of emails waiting to be sent.
+    pub fn queue_depth(&self) -> usize {
+        self.sender
+            .as_ref()
+            .map_or(0, |sender| sender.max_capacity() - sender.capacity())
+    }
+
+    /// Waits until every queued email has been sent or given up on, for at
+    /// most `timeout`. Returns how many were still pending.
+    pub async fn drain(&self, timeout: Duration) -> usize {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let pending = self.pending.load(Ordering::SeqCst);
+            if pending == 0 || tokio::time::Instant::now() >= deadline {
+                return pending;
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Builds the SMTP transport and spawns the delivery worker.
+    pub fn start(config: &SmtpConfig) -> Result<Self, AppError> {
+        let Some(host) = &config.host else {
+            info!("No SMTP host configured, email notifications are disabled");
+            return Ok(Self::disabled());
+        };
+
+        let from: Mailbox = config
+            .from
+            .parse()
+            .map_err(|e| AppError::InternalError(format!("Invalid SMTP sender address: {}", e)))?;
+
+        let mut builder = match config.security {
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                .map_err(|e| AppError::InternalError(format!("Failed to configure SMTP STARTTLS: {}", e)))?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+                .map_err(|e| AppError::InternalError(format!("Failed to configure SMTP TLS: {}", e)))?,
+        }
+        .port(config.port());
+
+        if let (Some(username), Some(password)) = (config.username.clone(), config.password.clone()) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        let transport = builder.build();
+        let (sender, receiver) = mpsc::channel(config.queue_size);
+
+        info!("Email notifications enabled via {}:{}", host, config.port());
+        let pending = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(run_worker(
+            transport,
+            from,
+            config.max_attempts,
+            sender.clone(),
+            receiver,
+            pending.clone(),
+        ));
+
+        Ok(Self {
+            sender: Some(sender),
+            pending,
+        })
+    }
+
+    /// Queues the owner notification and, if enabled, the respondent receipt
+    /// for a freshly stored response. Never blocks the submission.
+    pub fn notify_submission(
+        &self,
+        schema: &FormSchema,
+        settings: &NotificationSettings,
+        response_id: Uuid,
+        data: &HashMap<String, Value>,
+    ) {
+        if self.sender.is_none() || !settings.enabled {
+            return;
+        }
+
+        let answers = format_answers(schema, data);
+        let context = json!({
+            "form_id": schema.id,
+            "form_name": schema.name,
+            "response_id": response_id,
+            "answers": data,
+        });
+
+        let recipients = parse_mailboxes(&settings.recipients);
+        if !recipients.is_empty() {
+            let subject = render_subject(
+                settings.subject_template.as_deref().unwrap_or(DEFAULT_SUBJECT),
+                &context,
+            );
+            let mut body = format!(
+                "A new response was submitted to \"{}\".\n\nResponse ID: {}\n",
+                schema.name, response_id
+            );
+            if settings.include_answers {
+                body.push('\n');
+                body.push_str(&answers);
+            }
+            self.enqueue(recipients, subject, body);
+        }
+
+        if settings.send_respondent_copy {
+            if let Some(mailbox) = respondent_address(schema, settings, data) {
+                let subject = render_subject(RECEIPT_SUBJECT, &context);
+                let body = format!(
+                    "Thank you for your response to \"{}\".\n\nHere is a copy of what you submitted:\n\n{}",
+                    schema.name, answers
+                );
+                self.enqueue(vec![mailbox], subject, body);
+            }
+        }
+    }
+
+    fn enqueue(&self, to: Vec<Mailbox>, subject: String, body: String) {
+        if let Some(sender) = &self.sender {
+            let email = Email { to, subject, body, attempts: 0 };
+            // Count the email before the worker can see it, so its decrement
+            // never runs first and wraps the counter
+            self.pending.fetch_add(1, Ordering::SeqCst);
+            if let Err(e) = sender.try_send(email) {
+                self.pending.fetch_sub(1, Ordering::SeqCst);
+                error!("Dropping notification email, queue unavailable: {}", e);
+            }
+        }
+    }
+}
+
+async fn run_worker(
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    max_attempts: u32,
+    sender: mpsc::Sender<Email>,
+    mut receiver: mpsc::Receiver<Email>,
+    pending: Arc<AtomicUsize>,
+) {
+    let done = || pending.fetch_sub(1, Ordering::SeqCst);
+
+    while let Some(mut email) = receiver.recv().await {
+        email.attempts += 1;
+
+        let message = match build_message(&from, &email) {
+            Ok(message) => message,
+            Err(e) => {
+                error!("Dropping malformed notification email: {}", e);
+                done();
+                continue;
+            }
+        };
+
+        match transport.send(message).await {
+            Ok(_) => {
+                info!("Sent notification email \"{}\"", email.subject);
+                done();
+            }
+            Err(e) if email.attempts < max_attempts => {
+                let delay = Duration::from_secs((1u64 << email.attempts.min(16)).min(MAX_BACKOFF_SECS));
+                warn!(
+                    "Failed to send notification email (attempt {}/{}), retrying in {:?}: {}",
+                    email.attempts, max_attempts, delay, e
+                );
+                // Re-queue after the backoff without holding up the rest of the queue
+                let sender = sender.clone();
+                let pending = pending.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    if sender.send(email).await.is_err() {
+                        pending.fetch_sub(1, Ordering::SeqCst);
+                    }
+                });
+            }
+            Err(e) => {
+                error!(
+                    "Giving up on notification email \"{}\" after {} attempts: {}",
+                    email.subject, email.attempts, e
+                );
+                done();
+            }
+        }
+    }
+}
+
+fn build_message(from: &Mailbox, email: &Email) -> Result<Message, lettre::error::Error> {
+    let mut builder = Message::builder().from(from.clone()).subject(email.subject.clone());
+    for to in &email.to {
+        builder = builder.to(to.clone());
+    }
+    builder.header(ContentType::TEXT_PLAIN).body(email.body.clone())
+}
+
+/// Checks notification settings against the form they belong to.
+pub fn validate_settings(schema: &FormSchema, settings: &NotificationSettings) -> Result<(), AppError> {
+    for recipient in &settings.recipients {
+        recipient
+            .parse::<Mailbox>()
+            .map_err(|_| AppError::ValidationError(format!("Invalid recipient address: {}", recipient)))?;
+    }
+
+    if let Some(template) = &settings.subject_template {
+        subject_renderer()
+            .render_template(template, &json!({}))
+            .map_err(|e| AppError::ValidationError(format!("Invalid subject template: {}", e)))?;
+    }
+
+    if let Some(field_id) = &settings.respondent_email_field {
+        match schema.fields.iter().find(|f| &f.id == field_id) {
+            Some(field) if matches!(field.field_type, FieldType::Email) => {}
+            Some(_) => {
+                return Err(AppError::ValidationError(format!("Field {} is not an Email field", field_id)))
+            }
+            None => return Err(AppError::ValidationError(format!("Unknown field: {}", field_id))),
+        }
+    } else if settings.send_respondent_copy
+        && !schema.fields.iter().any(|f| matches!(f.field_type, FieldType::Email))
+    {
+        return Err(AppError::ValidationError(
+            "Respondent copies require an Email field in the form".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Subjects are plain text, so values are not HTML-escaped.
+fn subject_renderer() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+}
+
+fn render_subject(template: &str, context: &Value) -> String {
+    let subject = subject_renderer()
+        .render_template(template, context)
+        .unwrap_or_else(|_| template.to_string());
+    // Header injection guard: subjects must stay on a single line
+    subject.replace(['\r', '\n'], " ")
+}
+
+fn parse_mailboxes(addresses: &[String]) -> Vec<Mailbox> {
+    addresses
+        .iter()
+        .filter_map(|a| match a.parse() {
+            Ok(mailbox) => Some(mailbox),
+            Err(_) => {
+                warn!("Skipping invalid notification recipient: {}", a);
+                None
+            }
+        })
+        .collect()
+}
+
+fn respondent_address(
+    schema: &FormSchema,
+    settings: &NotificationSettings,
+    data: &HashMap<String, Value>,
+) -> Option<Mailbox> {
+    let field_id = match &settings.respondent_email_field {
+        Some(id) => id.as_str(),
+        None => schema
+            .fields
+            .iter()
+            .find(|f| matches!(f.field_type, FieldType::Email))?
+            .id
+            .as_str(),
+    };
+
+    let address = data.get(field_id)?.as_str()?.trim().parse::<Address>().ok()?;
+    Some(Mailbox::new(None, address))
+}
+
+fn format_answers(schema: &FormSchema, data: &HashMap<String, Value>) -> String {
+    schema
+        .fields
+        .iter()
+        .map(|field| format!("{}: {}\n", field.label, format_answer(field, data.get(&field.id))))
+        .collect()
+}
+
+fn format_answer(field: &FormField, value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        // Group answers, one numbered line per item
+        Some(Value::Array(items)) if matches!(field.field_type, FieldType::Group) => items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let answers = field
+                    .fields
+                    .iter()
+                    .flatten()
+                    .map(|nested| format!("{}: {}", nested.label, format_answer(nested, item.get(&nested.id))))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                format!("\n  {}. {}", index + 1, answers)
+            })
+            .collect(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string))
+            .collect::<Vec<_>>()
+            .join(", "),
+        // Matrix answers, one `row: answer` per row
+        Some(Value::Object(rows)) => rows
+            .iter()
+            .map(|(row, v)| format!("{}: {}", row, v.as_str().map_or_else(|| v.to_string(), str::to_string)))
+            .collect::<Vec<_>>()
+            .join("; "),
+        Some(other) => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{self, Format};
+
+    fn contact_form() -> FormSchema {
+        let yaml = "name: Contact\nfields:\n\
+            \x20 - { id: name, label: Name, field_type: Text, required: true }\n\
+            \x20 - { id: email, label: Email, field_type: Email, required: false }\n";
+        definition::parse(yaml, Format::Yaml).unwrap()
+    }
+
+    fn settings(value: Value) -> NotificationSettings {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn renders_subjects_as_single_line_plain_text() {
+        let context = json!({ "form_name": "Tom & Jerry", "answers": { "name": "<Ann>\r\nBcc: x@example.com" } });
+        assert_eq!(render_subject(DEFAULT_SUBJECT, &context), "New response to Tom & Jerry");
+        assert_eq!(
+            render_subject("From {{answers.name}}", &context),
+            "From <Ann>  Bcc: x@example.com"
+        );
+        // A template that fails to render is sent as written
+        assert_eq!(render_subject("{{#if}}", &context), "{{#if}}");
+    }
+
+    #[test]
+    fn accepts_valid_settings() {
+        let valid = settings(json!({
+            "recipients": ["Owner <owner@example.com>"],
+            "subject_template": "New feedback from {{answers.name}}",
+            "send_respondent_copy": true,
+        }));
+        assert!(validate_settings(&contact_form(), &valid).is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_subject_templates() {
+        let invalid = settings(json!({ "subject_template": "{{#if answers.name}}unclosed" }));
+        let error = validate_settings(&contact_form(), &invalid).unwrap_err().to_string();
+        assert!(error.starts_with("Validation error: Invalid subject template"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_invalid_recipients_and_respondent_fields() {
+        let form = contact_form();
+        let error = validate_settings(&form, &settings(json!({ "recipients": ["not an address"] }))).unwrap_err();
+        assert_eq!(error.to_string(), "Validation error: Invalid recipient address: not an address");
+
+        let error = validate_settings(&form, &settings(json!({ "respondent_email_field": "name" }))).unwrap_err();
+        assert_eq!(error.to_string(), "Validation error: Field name is not an Email field");
+
+        let error = validate_settings(&form, &settings(json!({ "respondent_email_field": "phone" }))).unwrap_err();
+        assert_eq!(error.to_string(), "Validation error: Unknown field: phone");
+    }
+}
@@ -0,0 +1,144 @@
+// src/openapi.rs
+//! OpenAPI 3 description of the HTTP API, generated from the handler
+//! annotations and the `ToSchema` types, with a Redoc page to browse it.
+
+use std::sync::LazyLock;
+
+use actix_web::http::Method;
+use actix_web::{get, HttpResponse, Responder};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme};
+use utoipa::openapi::{ContentBuilder, Ref, ResponseBuilder};
+use utoipa::OpenApi;
+
+use crate::{api, auth, health, metrics};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Form Portal API",
+        description = "Register dynamic forms, render them, and collect and analyse their responses."
+    ),
+    nest((path = "/api", api = ApiRoutes)),
+    paths(health::healthz, health::readyz, metrics::metrics),
+    tags(
+        (name = "forms", description = "Form schemas and rendering"),
+        (name = "responses", description = "Submitting, listing, searching and summarising responses"),
+        (name = "notifications", description = "Email notifications on new responses"),
+        (name = "operations", description = "Health probes and metrics"),
+        (name = "documentation", description = "This document"),
+    )
+)]
+pub struct ApiDoc;
+
+/// The routes of the `/api` scope, relative to it.
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        api::register_form_schema,
+        api::import_json_schema,
+        api::list_forms,
+        api::get_form_by_id,
+        api::get_response_json_schema,
+        api::get_response_typescript,
+        api::render_form,
+        api::submit_form,
+        api::get_form_responses,
+        api::stream_form_responses,
+        api::export_form_responses,
+        api::search_form_responses,
+        api::get_form_summary,
+        api::get_notification_settings,
+        api::update_notification_settings,
+        spec,
+        docs,
+    )
+)]
+struct ApiRoutes;
+
+pub static SPEC: LazyLock<utoipa::openapi::OpenApi> = LazyLock::new(|| {
+    let mut openapi = ApiDoc::openapi();
+    // utoipa fills this from Cargo.toml, which declares no license
+    openapi.info.license = None;
+    document_api_keys(&mut openapi);
+    openapi
+});
+
+/// Declares the two ways of presenting an API key, and requires one on every
+/// operation the `auth::require_api_key` middleware guards.
+fn document_api_keys(openapi: &mut utoipa::openapi::OpenApi) {
+    let components = openapi.components.get_or_insert_with(Default::default);
+    components.add_security_scheme(
+        "bearer",
+        SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+    );
+    components.add_security_scheme(
+        "api_key",
+        SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(auth::API_KEY_HEADER))),
+    );
+
+    let unauthorized = ResponseBuilder::new()
+        .description("Missing or invalid API key, when keys are required")
+        .content(
+            "application/json",
+            ContentBuilder::new().schema(Some(Ref::from_schema_name("ErrorResponse"))).build(),
+        )
+        .build();
+    let no_scopes: [&str; 0] = [];
+    for (path, item) in openapi.paths.paths.iter_mut() {
+        let operations = [
+            (Method::GET, &mut item.get),
+            (Method::POST, &mut item.post),
+            (Method::PUT, &mut item.put),
+            (Method::PATCH, &mut item.patch),
+            (Method::DELETE, &mut item.delete),
+        ];
+        for (method, operation) in operations {
+            let Some(operation) = operation else {
+                continue;
+            };
+            if auth::is_public(&method, path) {
+                continue;
+            }
+            // Either requirement satisfies the operation
+            operation.security = Some(vec![
+                SecurityRequirement::new("bearer", no_scopes),
+                SecurityRequirement::new("api_key", no_scopes),
+            ]);
+            operation.responses.responses.insert("401".to_string(), unauthorized.clone().into());
+        }
+    }
+}
+
+const DOCS_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>Form Portal API</title>
+  </head>
+  <body>
+    <redoc spec-url="openapi.json"></redoc>
+    <script src="https://cdn.redoc.ly/redoc/v2.1.5/bundles/redoc.standalone.js"></script>
+  </body>
+</html>
+"#;
+
+/// The OpenAPI document.
+#[utoipa::path(
+    tag = "documentation",
+    responses((status = 200, description = "OpenAPI 3 document", content_type = "application/json")),
+)]
+#[get("/openapi.json")]
+pub async fn spec() -> impl Responder {
+    HttpResponse::Ok().json(&*SPEC)
+}
+
+/// Browsable API reference rendered by Redoc.
+#[utoipa::path(
+    tag = "documentation",
+    responses((status = 200, description = "HTML page", body = String, content_type = "text/html")),
+)]
+#[get("/docs")]
+pub async fn docs() -> impl Responder {
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(DOCS_PAGE)
+}
@@ -0,0 +1,411 @@
+// src/search.rs
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use tracing::{info, warn};
+use serde_json::Value;
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{BooleanQuery, ConstScoreQuery, Occur, Query, QueryParser, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value as _, STORED, STRING, TEXT};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::directory::error::LockError;
+use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, TantivyError, Term};
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{query::SearchQuery, search::SearchHit, FieldType, FormField, FormResponse, FormSchema},
+    store::FormStore,
+};
+
+const WRITER_MEMORY_BYTES: usize = 50_000_000;
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+struct IndexFields {
+    response_id: Field,
+    form_id: Field,
+    created_at: Field,
+    body: Field,
+    data: Field,
+}
+
+/// Inverted index over the free-text answers of every form, kept on local disk.
+/// Only one process can have it open, so rebuilding it needs the server stopped.
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    /// Documents are added under the read lock; committing takes the write lock.
+    writer: RwLock<IndexWriter>,
+    /// Whether documents were added since the last commit.
+    pending: AtomicBool,
+    fields: IndexFields,
+}
+
+impl SearchIndex {
+    pub fn open(dir: &Path) -> Result<Self, AppError> {
+        let mut builder = Schema::builder();
+        let fields = IndexFields {
+            response_id: builder.add_text_field("response_id", STRING | STORED),
+            form_id: builder.add_text_field("form_id", STRING),
+            created_at: builder.add_text_field("created_at", STORED),
+            body: builder.add_text_field("body", TEXT | STORED),
+            data: builder.add_text_field("data", STORED),
+        };
+
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::InternalError(format!("Failed to create search index directory: {}", e)))?;
+        let directory = MmapDirectory::open(dir).map_err(search_error)?;
+        let index = Index::open_or_create(directory, builder.build()).map_err(search_error)?;
+
+        let writer = index
+            .writer_with_num_threads(1, WRITER_MEMORY_BYTES)
+            .map_err(|e| match e {
+                TantivyError::LockFailure(LockError::LockBusy, _) => AppError::InternalError(format!(
+                    "The search index in {} is in use by another process, such as a running server",
+                    dir.display()
+                )),
+                e => search_error(e),
+            })?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .map_err(search_error)?;
+
+        Ok(Self {
+            index,
+            reader,
+            writer: RwLock::new(writer),
+            pending: AtomicBool::new(false),
+            fields,
+        })
+    }
+
+    /// Adds a single response. It becomes searchable on the next [`commit`](Self::commit).
+    pub fn index_response(&self, schema: &FormSchema, response: &FormResponse) -> Result<(), AppError> {
+        let writer = self.writer.read().unwrap_or_else(|e| e.into_inner());
+        self.add_document(&writer, schema, response)?;
+        self.pending.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Makes the responses added since the last commit searchable, if any.
+    pub fn commit(&self) -> Result<(), AppError> {
+        if !self.pending.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let mut writer = self.writer.write().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = writer.commit() {
+            self.pending.store(true, Ordering::SeqCst);
+            return Err(search_error(e));
+        }
+        self.reader.reload().map_err(search_error)
+    }
+
+    /// Replaces everything indexed for a form with the given responses and
+    /// commits right away.
+    pub fn reindex_form(&self, schema: &FormSchema, responses: &[FormResponse]) -> Result<(), AppError> {
+        let form_id = schema.id.unwrap_or_default();
+        let mut writer = self.writer.write().unwrap_or_else(|e| e.into_inner());
+
+        writer.delete_term(Term::from_field_text(self.fields.form_id, &form_id.to_string()));
+        for response in responses {
+            self.add_document(&writer, schema, response)?;
+        }
+        self.pending.store(false, Ordering::SeqCst);
+        writer.commit().map_err(search_error)?;
+        self.reader.reload().map_err(search_error)
+    }
+
+    fn add_document(
+        &self,
+        writer: &IndexWriter,
+        schema: &FormSchema,
+        response: &FormResponse,
+    ) -> Result<(), AppError> {
+        let response_id = response
+            .id
+            .ok_or_else(|| AppError::InternalError("Cannot index a response without an id".to_string()))?;
+
+        let data = serde_json::to_string(&response.data)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize response data: {}", e)))?;
+
+        writer
+            .add_document(doc!(
+                self.fields.response_id => response_id.to_string(),
+                self.fields.form_id => response.form_id.to_string(),
+                self.fields.created_at => response.created_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                self.fields.body => free_text(schema, response),
+                self.fields.data => data,
+            ))
+            .map_err(search_error)?;
+
+        Ok(())
+    }
+
+    /// Runs `query` against one form's responses, best matches first, with
+    /// the matching terms highlighted in `<b>` tags.
+    pub fn search(&self, form_id: Uuid, query: &SearchQuery) -> Result<Vec<SearchHit>, AppError> {
+        if query.q.trim().is_empty() {
+            return Err(AppError::BadRequest("Search query must not be empty".to_string()));
+        }
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let parser = QueryParser::for_index(&self.index, vec![self.fields.body]);
+        let text_query = parser
+            .parse_query(&query.q)
+            .map_err(|e| AppError::BadRequest(format!("Invalid search query: {}", e)))?;
+
+        let searcher = self.reader.searcher();
+        let snippets = SnippetGenerator::create(&searcher, &*text_query, self.fields.body).map_err(search_error)?;
+
+        // Restrict to the form without letting the form id affect ranking
+        let form_query: Box<dyn Query> = Box::new(ConstScoreQuery::new(
+            Box::new(TermQuery::new(
+                Term::from_field_text(self.fields.form_id, &form_id.to_string()),
+                IndexRecordOption::Basic,
+            )),
+            0.0,
+        ));
+        let query = BooleanQuery::new(vec![(Occur::Must, text_query), (Occur::Must, form_query)]);
+
+        let top_docs = searcher
+            .search(&query, &TopDocs::with_limit(limit))
+            .map_err(search_error)?;
+
+        let mut hits = Vec::with_capacity(top_docs.len());
+        for (score, address) in top_docs {
+            let doc: TantivyDocument = searcher.doc(address).map_err(search_error)?;
+            let text = |field: Field| doc.get_first(field).and_then(|v| v.as_str()).unwrap_or_default();
+
+            let response_id = Uuid::parse_str(text(self.fields.response_id))
+                .map_err(|e| AppError::InternalError(format!("Corrupt search document: {}", e)))?;
+
+            hits.push(SearchHit {
+                response_id,
+                score,
+                snippet: snippets.snippet_from_doc(&doc).to_html(),
+                created_at: chrono::DateTime::parse_from_rfc3339(text(self.fields.created_at))
+                    .ok()
+                    .map(|t| t.with_timezone(&chrono::Utc)),
+                data: serde_json::from_str(text(self.fields.data)).unwrap_or(Value::Null),
+            });
+        }
+
+        Ok(hits)
+    }
+}
+
+/// The answers worth searching: those of free-text fields, including the
+/// ones inside `Group` items, one per line.
+fn free_text(schema: &FormSchema, response: &FormResponse) -> String {
+    let mut lines = Vec::new();
+    collect_text(&schema.fields, &|id| response.data.get(id), &mut lines);
+    lines.join("\n")
+}
+
+fn collect_text<'a>(
+    fields: &[FormField],
+    answer_to: &dyn Fn(&str) -> Option<&'a Value>,
+    lines: &mut Vec<&'a str>,
+) {
+    for field in fields {
+        let Some(answer) = answer_to(&field.id) else {
+            continue;
+        };
+        match field.field_type {
+            FieldType::Text | FieldType::Email | FieldType::Textarea => lines.extend(answer.as_str()),
+            FieldType::Group => {
+                let fields = field.fields.as_deref().unwrap_or_default();
+                for item in answer.as_array().into_iter().flatten().filter_map(Value::as_object) {
+                    collect_text(fields, &|id| item.get(id), lines);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn search_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalError(format!("Search index error: {}", e))
+}
+
+/// Commits the index every `interval`, so submissions share commits instead
+/// of each paying for one.
+pub async fn commit_periodically(index: Arc<SearchIndex>, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let index = index.clone();
+        match tokio::task::spawn_blocking(move || index.commit()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to commit the search index: {}", e),
+            Err(e) => warn!("Failed to commit the search index: {}", e),
+        }
+    }
+}
+
+/// Re-indexes the stored responses of every form, returning how many were indexed.
+pub async fn rebuild(store: &dyn FormStore, index: &SearchIndex) -> Result<usize, AppError> {
+    let mut total = 0;
+    for form_id in store.list_form_ids().await? {
+        let schema = store.get_form_schema(form_id).await?;
+        let responses = store.get_form_responses(form_id).await?;
+        index.reindex_form(&schema, &responses)?;
+        info!("Indexed {} responses of form {}", responses.len(), form_id);
+        total += responses.len();
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{parse, Format};
+    use chrono::{Duration as ChronoDuration, Utc};
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn schema() -> FormSchema {
+        let yaml = "name: Feedback\nfields:\n\
+            \x20 - { id: name, label: Name, field_type: Text, required: true }\n\
+            \x20 - { id: comments, label: Comments, field_type: Textarea, required: false }\n\
+            \x20 - { id: rating, label: Rating, field_type: Select, required: false, options: [{ value: refund, label: Refund }] }\n";
+        let mut schema = parse(yaml, Format::Yaml).unwrap();
+        schema.id = Some(Uuid::new_v4());
+        schema
+    }
+
+    fn response(schema: &FormSchema, name: &str, comments: &str) -> FormResponse {
+        FormResponse {
+            id: Some(Uuid::new_v4()),
+            form_id: schema.id.unwrap(),
+            data: [
+                ("name".to_string(), json!(name)),
+                ("comments".to_string(), json!(comments)),
+                ("rating".to_string(), json!("refund")),
+            ]
+            .into(),
+            created_at: Some(Utc::now()),
+        }
+    }
+
+    fn query(q: &str) -> SearchQuery {
+        SearchQuery { q: q.to_string(), limit: None }
+    }
+
+    fn ids(hits: &[SearchHit]) -> Vec<Uuid> {
+        hits.iter().map(|h| h.response_id).collect()
+    }
+
+    #[test]
+    fn finds_responses_once_committed() {
+        let dir = TempDir::new().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        let form = schema();
+        let late = response(&form, "Ada", "The parcel arrived late, I want a refund");
+        index.index_response(&form, &late).unwrap();
+        index.index_response(&form, &response(&form, "Grace", "Great service")).unwrap();
+
+        assert!(index.search(form.id.unwrap(), &query("refund")).unwrap().is_empty());
+        index.commit().unwrap();
+
+        // Both chose `refund`, but choice answers are not free text
+        let hits = index.search(form.id.unwrap(), &query("refund")).unwrap();
+        assert_eq!(ids(&hits), vec![late.id.unwrap()]);
+        assert!(hits[0].snippet.contains("<b>refund</b>"), "{}", hits[0].snippet);
+        assert_eq!(hits[0].data["name"], "Ada");
+        assert_eq!(index.search(form.id.unwrap(), &query("-refund +service")).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn keeps_forms_apart_and_ranks_better_matches_first() {
+        let dir = TempDir::new().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        let (form, other) = (schema(), schema());
+        let once = response(&form, "Ada", "late delivery, then more waiting");
+        let twice = response(&form, "Grace", "late and late again");
+        index.index_response(&form, &once).unwrap();
+        index.index_response(&form, &twice).unwrap();
+        index.index_response(&other, &response(&other, "Edsger", "late")).unwrap();
+        index.commit().unwrap();
+
+        let hits = index.search(form.id.unwrap(), &query("late")).unwrap();
+        assert_eq!(ids(&hits), vec![twice.id.unwrap(), once.id.unwrap()]);
+        let limited = SearchQuery { q: "late".to_string(), limit: Some(1) };
+        assert_eq!(index.search(form.id.unwrap(), &limited).unwrap().len(), 1);
+        let phrase = index.search(form.id.unwrap(), &query("\"late delivery\"")).unwrap();
+        assert_eq!(ids(&phrase), vec![once.id.unwrap()]);
+    }
+
+    #[test]
+    fn reindexing_replaces_the_documents_of_a_form() {
+        let dir = TempDir::new().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        let form = schema();
+        index.index_response(&form, &response(&form, "Ada", "stale answer")).unwrap();
+        index.commit().unwrap();
+
+        let mut fresh = response(&form, "Grace", "fresh answer");
+        fresh.created_at = Some(Utc::now() - ChronoDuration::days(1));
+        index.reindex_form(&form, std::slice::from_ref(&fresh)).unwrap();
+
+        let hits = index.search(form.id.unwrap(), &query("answer")).unwrap();
+        assert_eq!(ids(&hits), vec![fresh.id.unwrap()]);
+        assert_eq!(hits[0].created_at, fresh.created_at);
+    }
+
+    #[test]
+    fn indexes_the_text_answers_of_group_items() {
+        let dir = TempDir::new().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        let yaml = "name: Household\nfields:\n\
+            \x20 - id: members\n\
+            \x20   label: Members\n\
+            \x20   field_type: Group\n\
+            \x20   required: false\n\
+            \x20   fields:\n\
+            \x20     - { id: name, label: Name, field_type: Text, required: true }\n\
+            \x20     - { id: role, label: Role, field_type: Select, required: false, options: [{ value: parent, label: Parent }] }\n";
+        let mut form = parse(yaml, Format::Yaml).unwrap();
+        form.id = Some(Uuid::new_v4());
+        let household = FormResponse {
+            id: Some(Uuid::new_v4()),
+            form_id: form.id.unwrap(),
+            data: [(
+                "members".to_string(),
+                json!([{ "name": "Ada", "role": "parent" }, { "name": "Byron" }]),
+            )]
+            .into(),
+            created_at: Some(Utc::now()),
+        };
+        index.index_response(&form, &household).unwrap();
+        index.commit().unwrap();
+
+        let hits = index.search(form.id.unwrap(), &query("byron")).unwrap();
+        assert_eq!(ids(&hits), vec![household.id.unwrap()]);
+        assert!(index.search(form.id.unwrap(), &query("parent")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_empty_and_malformed_queries() {
+        let dir = TempDir::new().unwrap();
+        let index = SearchIndex::open(dir.path()).unwrap();
+        let form_id = Uuid::new_v4();
+        assert!(matches!(index.search(form_id, &query("  ")), Err(AppError::BadRequest(_))));
+        assert!(matches!(index.search(form_id, &query("body:(")), Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn reports_an_index_held_by_another_writer() {
+        let dir = TempDir::new().unwrap();
+        let _server = SearchIndex::open(dir.path()).unwrap();
+        let error = SearchIndex::open(dir.path()).err().unwrap().to_string();
+        assert!(error.contains("in use by another process"), "{}", error);
+    }
+}
@@ -0,0 +1,269 @@
+// src/shutdown.rs
+//! Graceful shutdown: on SIGTERM the service first fails readiness so load
+//! balancers stop sending traffic, then stops accepting connections, lets
+//! in-flight requests finish and drains the email queue, all within one
+//! configurable timeout.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServerHandle, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::{events::ResponseEvents, health::Readiness, notifications::Notifier, store::FormStore};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    /// How long in-flight requests and queued emails get to finish once the
+    /// listener is closed.
+    pub timeout_secs: u64,
+    /// How long readiness fails before the listener is closed on SIGTERM,
+    /// giving load balancers time to notice.
+    pub readiness_delay_secs: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 30,
+            readiness_delay_secs: 5,
+        }
+    }
+}
+
+impl ShutdownConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// Requests being handled. Shutdown waits for these itself: an actix worker
+/// may exit as soon as the listener closes, dropping the requests it was
+/// still handling.
+#[derive(Default)]
+pub struct InFlight {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+impl InFlight {
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Waits until no request is in flight, returning false on timeout.
+    async fn wait_idle(&self, timeout: Duration) -> bool {
+        let idle = async {
+            loop {
+                let notified = self.idle.notified();
+                if self.count() == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        tokio::time::timeout(timeout, idle).await.is_ok()
+    }
+}
+
+struct InFlightGuard(web::Data<InFlight>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+/// Middleware counting requests in [`InFlight`], when the app has one.
+pub async fn track_in_flight(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let _guard = req.app_data::<web::Data<InFlight>>().cloned().map(|in_flight| {
+        in_flight.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(in_flight)
+    });
+    next.call(req).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Signal {
+    Terminate,
+    Interrupt,
+}
+
+async fn signal() -> Signal {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = terminate.recv() => Signal::Terminate,
+                _ = tokio::signal::ctrl_c() => Signal::Interrupt,
+            },
+            Err(e) => {
+                warn!("Cannot listen for SIGTERM, only Ctrl-C stops the service: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                Signal::Interrupt
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        Signal::Interrupt
+    }
+}
+
+/// Stops the server gracefully on SIGTERM or Ctrl-C. The returned cell is set
+/// to the moment the listener closed; the shutdown timeout counts from there.
+pub fn on_signal(
+    server: ServerHandle,
+    readiness: Arc<Readiness>,
+    in_flight: Arc<InFlight>,
+    events: ResponseEvents,
+    config: &ShutdownConfig,
+) -> Arc<OnceLock<Instant>> {
+    stop_on(signal(), server, readiness, in_flight, events, config)
+}
+
+fn stop_on(
+    signal: impl Future<Output = Signal> + Send + 'static,
+    server: ServerHandle,
+    readiness: Arc<Readiness>,
+    in_flight: Arc<InFlight>,
+    events: ResponseEvents,
+    config: &ShutdownConfig,
+) -> Arc<OnceLock<Instant>> {
+    let stopped_at = Arc::new(OnceLock::new());
+    let (delay, timeout) = (Duration::from_secs(config.readiness_delay_secs), config.timeout());
+
+    let stopped = stopped_at.clone();
+    tokio::spawn(async move {
+        let signal = signal.await;
+        readiness.begin_shutdown();
+
+        // Interactive stops skip the wait, nobody is routing traffic here
+        if signal == Signal::Terminate && !delay.is_zero() {
+            info!("SIGTERM received, failing readiness for {:?} before stopping", delay);
+            tokio::time::sleep(delay).await;
+        }
+
+        info!("Stopping, waiting up to {:?} for in-flight work", timeout);
+        // Live response streams never finish on their own
+        events.close();
+        server.pause().await;
+        let _ = stopped.set(Instant::now());
+        if !in_flight.wait_idle(timeout).await {
+            warn!("Stopping with {} requests still in flight", in_flight.count());
+        }
+        server.stop(true).await;
+    });
+
+    stopped_at
+}
+
+/// Finishes background work once the server has stopped: waits for queued
+/// emails until the deadline, then closes the store's connections.
+pub async fn drain(notifier: &Notifier, store: &dyn FormStore, deadline: Instant) {
+    let remaining = notifier.drain(deadline.saturating_duration_since(Instant::now())).await;
+    if remaining > 0 {
+        warn!("Shutting down with {} notification emails unsent", remaining);
+    }
+
+    store.close().await;
+    info!("Shutdown complete");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::SqliteStore;
+    use actix_web::{middleware, App, HttpServer};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio::sync::oneshot;
+
+    #[actix_web::test]
+    async fn finishes_in_flight_requests_then_closes_the_store() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let in_flight = web::Data::new(InFlight::default());
+        let server = HttpServer::new({
+            let in_flight = in_flight.clone();
+            move || {
+                App::new()
+                    .app_data(in_flight.clone())
+                    .wrap(middleware::from_fn(track_in_flight))
+                    .route(
+                        "/slow",
+                        web::get().to(|| async {
+                            tokio::time::sleep(Duration::from_millis(300)).await;
+                            "done"
+                        }),
+                    )
+            }
+        })
+        .workers(1)
+        .disable_signals()
+        .shutdown_timeout(5)
+        .listen(listener)
+        .unwrap()
+        .run();
+
+        let config = ShutdownConfig { timeout_secs: 5, readiness_delay_secs: 0 };
+        let (terminate, terminated) = oneshot::channel::<()>();
+        let trigger = async move {
+            let _ = terminated.await;
+            Signal::Terminate
+        };
+        let stopped_at = stop_on(
+            trigger,
+            server.handle(),
+            Arc::default(),
+            in_flight.into_inner(),
+            ResponseEvents::default(),
+            &config,
+        );
+        let server = actix_web::rt::spawn(server);
+
+        let request = actix_web::rt::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        });
+
+        // Stop while the request is being handled
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        terminate.send(()).unwrap();
+        server.await.unwrap().unwrap();
+
+        let response = request.await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("done"), "{}", response);
+
+        let store = SqliteStore::connect("sqlite::memory:").await.unwrap();
+        assert!(store.check_ready().await.is_ok());
+        let deadline = *stopped_at.get().expect("the listener was closed") + config.timeout();
+        drain(&Notifier::disabled(), &store, deadline).await;
+        assert!(store.check_ready().await.is_err());
+    }
+}
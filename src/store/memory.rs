@@ -0,0 +1,141 @@
+// src/store/memory.rs
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::FormStore;
+use crate::{
+    auth::ApiKey,
+    error::AppError,
+    models::{FormResponse, FormSchema, NotificationSettings},
+};
+
+/// Non-persistent store for development and tests; everything is lost on restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    schemas: RwLock<HashMap<Uuid, FormSchema>>,
+    responses: RwLock<HashMap<Uuid, Vec<FormResponse>>>,
+    notifications: RwLock<HashMap<Uuid, NotificationSettings>>,
+    api_keys: RwLock<Vec<ApiKey>>,
+}
+
+#[async_trait]
+impl FormStore for MemoryStore {
+    async fn create_form_schema(&self, mut schema: FormSchema) -> Result<Uuid, AppError> {
+        let id = schema.id.unwrap_or_else(Uuid::new_v4);
+        let now = Utc::now();
+
+        schema.id = Some(id);
+        schema.created_at = Some(now);
+        schema.updated_at = Some(now);
+
+        self.schemas.write().unwrap().insert(id, schema);
+        self.responses.write().unwrap().entry(id).or_default();
+
+        Ok(id)
+    }
+
+    async fn get_form_schema(&self, id: Uuid) -> Result<FormSchema, AppError> {
+        self.schemas
+            .read()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Form schema with ID {} not found", id)))
+    }
+
+    async fn list_form_ids(&self) -> Result<Vec<Uuid>, AppError> {
+        Ok(self.schemas.read().unwrap().keys().copied().collect())
+    }
+
+    async fn submit_form_response(&self, mut response: FormResponse) -> Result<Uuid, AppError> {
+        let id = response.id.unwrap_or_else(Uuid::new_v4);
+
+        response.id = Some(id);
+        response.created_at = Some(response.created_at.unwrap_or_else(Utc::now));
+
+        self.responses
+            .write()
+            .unwrap()
+            .get_mut(&response.form_id)
+            .ok_or_else(|| AppError::NotFound(format!("Form schema with ID {} not found", response.form_id)))?
+            .push(response);
+
+        Ok(id)
+    }
+
+    async fn get_form_responses(&self, form_id: Uuid) -> Result<Vec<FormResponse>, AppError> {
+        let mut responses = self
+            .responses
+            .read()
+            .unwrap()
+            .get(&form_id)
+            .cloned()
+            .unwrap_or_default();
+        // Submissions may carry their own creation time, so insertion order
+        // is not necessarily oldest first
+        responses.sort_by_key(|r| (r.created_at, r.id));
+        Ok(responses)
+    }
+
+    async fn set_notification_settings(
+        &self,
+        form_id: Uuid,
+        settings: &NotificationSettings,
+    ) -> Result<(), AppError> {
+        self.notifications.write().unwrap().insert(form_id, settings.clone());
+        Ok(())
+    }
+
+    async fn get_notification_settings(&self, form_id: Uuid) -> Result<Option<NotificationSettings>, AppError> {
+        Ok(self.notifications.read().unwrap().get(&form_id).cloned())
+    }
+
+    async fn delete_form(&self, id: Uuid) -> Result<(), AppError> {
+        self.schemas
+            .write()
+            .unwrap()
+            .remove(&id)
+            .ok_or_else(|| AppError::NotFound(format!("Form schema with ID {} not found", id)))?;
+        self.responses.write().unwrap().remove(&id);
+        self.notifications.write().unwrap().remove(&id);
+        Ok(())
+    }
+
+    async fn delete_responses_before(&self, form_id: Uuid, before: DateTime<Utc>) -> Result<usize, AppError> {
+        let mut responses = self.responses.write().unwrap();
+        let Some(responses) = responses.get_mut(&form_id) else {
+            return Ok(0);
+        };
+        let count = responses.len();
+        responses.retain(|r| r.created_at.is_some_and(|created_at| created_at >= before));
+        Ok(count - responses.len())
+    }
+
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), AppError> {
+        self.api_keys.write().unwrap().push(key.clone());
+        Ok(())
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, AppError> {
+        Ok(self.api_keys.read().unwrap().clone())
+    }
+
+    async fn find_api_key(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError> {
+        Ok(self.api_keys.read().unwrap().iter().find(|k| k.key_hash == key_hash).cloned())
+    }
+
+    async fn delete_api_key(&self, id: Uuid) -> Result<bool, AppError> {
+        let mut keys = self.api_keys.write().unwrap();
+        let count = keys.len();
+        keys.retain(|k| k.id != id);
+        Ok(keys.len() < count)
+    }
+
+    async fn check_ready(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+}
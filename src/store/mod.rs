@@ -0,0 +1,385 @@
+// src/store/mod.rs
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tracing::warn;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    auth::ApiKey,
+    db::{ScyllaConfig, ScyllaStore},
+    error::AppError,
+    models::{FormResponse, FormSchema, NotificationSettings},
+};
+
+mod memory;
+mod postgres;
+mod sql;
+mod sqlite;
+
+pub use memory::MemoryStore;
+pub use postgres::PostgresStore;
+pub use sqlite::SqliteStore;
+
+/// Persistence for form schemas, their responses and per-form settings.
+#[async_trait]
+pub trait FormStore: Send + Sync {
+    async fn create_form_schema(&self, schema: FormSchema) -> Result<Uuid, AppError>;
+
+    async fn get_form_schema(&self, id: Uuid) -> Result<FormSchema, AppError>;
+
+    async fn list_form_ids(&self) -> Result<Vec<Uuid>, AppError>;
+
+    async fn submit_form_response(&self, response: FormResponse) -> Result<Uuid, AppError>;
+
+    async fn get_form_responses(&self, form_id: Uuid) -> Result<Vec<FormResponse>, AppError>;
+
+    /// The responses of a form created from `from` up to and including `to`,
+    /// oldest first; a missing bound is open. Stores that can select by
+    /// creation time should, so filtered listings load fewer responses.
+    async fn get_form_responses_between(
+        &self,
+        form_id: Uuid,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<FormResponse>, AppError> {
+        let mut responses = self.get_form_responses(form_id).await?;
+        responses.retain(|r| {
+            r.created_at.is_some_and(|created_at| {
+                from.is_none_or(|from| created_at >= from) && to.is_none_or(|to| created_at <= to)
+            })
+        });
+        Ok(responses)
+    }
+
+    async fn set_notification_settings(
+        &self,
+        form_id: Uuid,
+        settings: &NotificationSettings,
+    ) -> Result<(), AppError>;
+
+    async fn get_notification_settings(&self, form_id: Uuid) -> Result<Option<NotificationSettings>, AppError>;
+
+    /// Deletes a form along with its responses and settings.
+    async fn delete_form(&self, id: Uuid) -> Result<(), AppError>;
+
+    /// Deletes the responses of a form created before `before`, returning
+    /// how many were deleted.
+    async fn delete_responses_before(&self, form_id: Uuid, before: DateTime<Utc>) -> Result<usize, AppError>;
+
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), AppError>;
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, AppError>;
+
+    async fn find_api_key(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError>;
+
+    /// Returns whether a key with this id existed.
+    async fn delete_api_key(&self, id: Uuid) -> Result<bool, AppError>;
+
+    /// Fails when the database cannot be reached or its schema is not up to date.
+    async fn check_ready(&self) -> Result<(), AppError>;
+
+    /// Closes connections once no more requests will be made. Stores whose
+    /// connections close on drop need not implement this.
+    async fn close(&self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Scylla,
+    #[serde(alias = "postgresql")]
+    #[value(alias = "postgresql")]
+    Postgres,
+    Sqlite,
+    Memory,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    /// Connection URL of the SQL backends; defaults per backend when unset.
+    pub database_url: Option<String>,
+    /// Apply pending ScyllaDB migrations on startup instead of only warning.
+    pub migrate_on_startup: bool,
+    /// Connection attempts on startup before giving up.
+    pub connect_attempts: u32,
+    pub scylla: ScyllaConfig,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::Scylla,
+            database_url: None,
+            migrate_on_startup: true,
+            connect_attempts: 10,
+            scylla: ScyllaConfig::default(),
+        }
+    }
+}
+
+impl StorageConfig {
+    pub fn database_url(&self) -> &str {
+        match (&self.database_url, self.backend) {
+            (Some(url), _) => url,
+            (None, StorageBackend::Sqlite) => "sqlite://form_portal.db",
+            (None, _) => "postgres://postgres@localhost/form_portal",
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.connect_attempts == 0 {
+            bail!("connect_attempts must be at least 1");
+        }
+        match self.backend {
+            StorageBackend::Scylla => self.scylla.validate(),
+            StorageBackend::Postgres if !self.database_url().starts_with("postgres") => {
+                bail!("The postgres backend needs a postgres:// database URL")
+            }
+            StorageBackend::Sqlite if !self.database_url().starts_with("sqlite:") => {
+                bail!("The sqlite backend needs a sqlite:// database URL")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Builds the configured store. Connecting is retried, so the service can
+/// start before its database.
+pub async fn connect(config: &StorageConfig) -> Result<Arc<dyn FormStore>> {
+    let attempts = config.connect_attempts;
+    let url = config.database_url();
+
+    match config.backend {
+        StorageBackend::Scylla => {
+            let migrate = config.migrate_on_startup;
+            Ok(Arc::new(
+                retry(attempts, || ScyllaStore::connect(config.scylla.clone(), migrate)).await?,
+            ))
+        }
+        StorageBackend::Postgres => Ok(Arc::new(retry(attempts, || PostgresStore::connect(url)).await?)),
+        StorageBackend::Sqlite => Ok(Arc::new(retry(attempts, || SqliteStore::connect(url)).await?)),
+        StorageBackend::Memory => Ok(Arc::new(MemoryStore::default())),
+    }
+}
+
+/// Calls `connect` until it succeeds, backing off exponentially between
+/// attempts, up to `attempts` times.
+async fn retry<T, F, Fut>(attempts: u32, connect: F) -> Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = Duration::from_secs(1);
+    let mut attempt = 1;
+
+    loop {
+        match connect().await {
+            Ok(store) => return Ok(store),
+            Err(e) if attempt < attempts => {
+                warn!(
+                    "Failed to connect to the database (attempt {}/{}), retrying in {:?}: {:#}",
+                    attempt, attempts, delay, e
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(Duration::from_secs(30));
+                attempt += 1;
+            }
+            Err(e) => return Err(e.context(format!("Giving up after {} attempts", attempts))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{parse, Format};
+    use chrono::Duration as ChronoDuration;
+    use serde_json::json;
+
+    fn schema() -> FormSchema {
+        let yaml = "name: Signup\nfields:\n\
+            \x20 - { id: name, label: Name, field_type: Text, required: true }\n";
+        parse(yaml, Format::Yaml).unwrap()
+    }
+
+    fn response(form_id: Uuid, name: &str, created_at: DateTime<Utc>) -> FormResponse {
+        FormResponse {
+            id: None,
+            form_id,
+            data: [("name".to_string(), json!(name))].into(),
+            created_at: Some(created_at),
+        }
+    }
+
+    /// The behaviour every [`FormStore`] must share.
+    async fn check_conformance(store: &dyn FormStore) {
+        store.check_ready().await.unwrap();
+
+        // Schemas
+        let form_id = store.create_form_schema(schema()).await.unwrap();
+        let other_id = store.create_form_schema(schema()).await.unwrap();
+        let stored = store.get_form_schema(form_id).await.unwrap();
+        assert_eq!(stored.id, Some(form_id));
+        assert_eq!(stored.name, "Signup");
+        assert_eq!(stored.fields.len(), 1);
+        assert!(stored.created_at.is_some() && stored.updated_at.is_some());
+        // Shared databases may hold other forms and keys, so only look for ours
+        let ids = store.list_form_ids().await.unwrap();
+        assert!(ids.contains(&form_id) && ids.contains(&other_id));
+        assert!(matches!(store.get_form_schema(Uuid::new_v4()).await, Err(AppError::NotFound(_))));
+
+        // Responses come back oldest first whatever order they were submitted
+        // in, and only those of the form
+        let now = Utc::now();
+        let new_id = store.submit_form_response(response(form_id, "Grace", now)).await.unwrap();
+        let old_id = store
+            .submit_form_response(response(form_id, "Ada", now - ChronoDuration::days(2)))
+            .await
+            .unwrap();
+        store.submit_form_response(response(other_id, "Edsger", now)).await.unwrap();
+        let responses = store.get_form_responses(form_id).await.unwrap();
+        assert_eq!(
+            responses.iter().map(|r| r.id).collect::<Vec<_>>(),
+            vec![Some(old_id), Some(new_id)]
+        );
+        assert_eq!(responses[0].data["name"], "Ada");
+        assert!(store.submit_form_response(response(Uuid::new_v4(), "Nobody", now)).await.is_err());
+
+        // Creation time bounds are inclusive, and either may be left open
+        let between = |from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>| async move {
+            let responses = store.get_form_responses_between(form_id, from, to).await.unwrap();
+            responses.iter().map(|r| r.id).collect::<Vec<_>>()
+        };
+        assert_eq!(between(None, None).await, vec![Some(old_id), Some(new_id)]);
+        assert_eq!(between(Some(now), None).await, vec![Some(new_id)]);
+        assert_eq!(between(None, Some(now - ChronoDuration::days(2))).await, vec![Some(old_id)]);
+        assert_eq!(between(Some(now - ChronoDuration::days(1)), Some(now - ChronoDuration::hours(1))).await, vec![]);
+
+        let deleted = store
+            .delete_responses_before(form_id, now - ChronoDuration::days(1))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        let responses = store.get_form_responses(form_id).await.unwrap();
+        assert_eq!(responses.iter().map(|r| r.id).collect::<Vec<_>>(), vec![Some(new_id)]);
+
+        // Notification settings are replaced, not merged
+        assert!(store.get_notification_settings(form_id).await.unwrap().is_none());
+        let mut settings = NotificationSettings {
+            enabled: true,
+            recipients: vec!["team@example.com".to_string()],
+            subject_template: None,
+            include_answers: true,
+            send_respondent_copy: false,
+            respondent_email_field: None,
+        };
+        store.set_notification_settings(form_id, &settings).await.unwrap();
+        settings.recipients = vec!["other@example.com".to_string()];
+        store.set_notification_settings(form_id, &settings).await.unwrap();
+        let stored = store.get_notification_settings(form_id).await.unwrap().unwrap();
+        assert_eq!(stored.recipients, vec!["other@example.com"]);
+
+        // Deleting a form takes its responses and settings along
+        store.delete_form(form_id).await.unwrap();
+        assert!(matches!(store.get_form_schema(form_id).await, Err(AppError::NotFound(_))));
+        assert!(store.get_form_responses(form_id).await.unwrap().is_empty());
+        assert!(store.get_notification_settings(form_id).await.unwrap().is_none());
+        assert!(matches!(store.delete_form(form_id).await, Err(AppError::NotFound(_))));
+        assert_eq!(store.get_form_responses(other_id).await.unwrap().len(), 1);
+
+        // API keys
+        let (key, secret) = ApiKey::generate("ci");
+        store.create_api_key(&key).await.unwrap();
+        let listed = store.list_api_keys().await.unwrap();
+        assert!(listed.iter().any(|k| k.id == key.id));
+        let found = store.find_api_key(&crate::auth::hash(&secret)).await.unwrap().unwrap();
+        assert_eq!((found.id, found.name.as_str()), (key.id, "ci"));
+        assert!(store.find_api_key("unknown").await.unwrap().is_none());
+        assert!(store.delete_api_key(key.id).await.unwrap());
+        assert!(!store.delete_api_key(key.id).await.unwrap());
+        assert!(store.list_api_keys().await.unwrap().iter().all(|k| k.id != key.id));
+    }
+
+    #[actix_web::test]
+    async fn memory_store_conforms() {
+        check_conformance(&MemoryStore::default()).await;
+    }
+
+    #[actix_web::test]
+    async fn sqlite_store_conforms() {
+        let store = SqliteStore::connect("sqlite::memory:").await.unwrap();
+        check_conformance(&store).await;
+        store.close().await;
+    }
+
+    #[actix_web::test]
+    async fn private_in_memory_sqlite_keeps_one_database() {
+        let store = SqliteStore::connect("sqlite://?mode=memory&cache=private").await.unwrap();
+        check_conformance(&store).await;
+    }
+
+    #[actix_web::test]
+    async fn retries_connecting_until_the_attempts_run_out() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let calls = AtomicU32::new(0);
+        let flaky = || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => bail!("connection refused"),
+                n => Ok(n),
+            }
+        };
+        assert_eq!(retry(2, flaky).await.unwrap(), 1);
+
+        calls.store(0, Ordering::SeqCst);
+        let error = retry(1, flaky).await.unwrap_err();
+        assert_eq!(format!("{:#}", error), "Giving up after 1 attempts: connection refused");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    /// Runs against the database at `FORM_PORTAL_TEST_POSTGRES_URL`, when set.
+    #[actix_web::test]
+    async fn postgres_store_conforms() {
+        let Ok(url) = std::env::var("FORM_PORTAL_TEST_POSTGRES_URL") else {
+            return;
+        };
+        let store = PostgresStore::connect(&url).await.unwrap();
+        check_conformance(&store).await;
+        store.close().await;
+    }
+
+    /// Runs against the cluster at `FORM_PORTAL_TEST_SCYLLA_NODES`, a comma
+    /// separated list of `host:port`, when set. Each run creates its own
+    /// keyspace and drops it afterwards.
+    #[actix_web::test]
+    async fn scylla_store_conforms() {
+        let Ok(nodes) = std::env::var("FORM_PORTAL_TEST_SCYLLA_NODES") else {
+            return;
+        };
+        let config = ScyllaConfig {
+            nodes: nodes.split(',').map(|node| node.trim().to_string()).collect(),
+            read_consistency: scylla::statement::Consistency::One,
+            write_consistency: scylla::statement::Consistency::One,
+            keyspace: format!("form_portal_test_{}", Uuid::new_v4().simple()),
+            ..ScyllaConfig::default()
+        };
+        let store = ScyllaStore::connect(config.clone(), true).await.unwrap();
+        check_conformance(&store).await;
+        store.close().await;
+
+        let session = crate::db::connect_session(&config).await.unwrap();
+        session
+            .query(format!("DROP KEYSPACE {}", config.keyspace), &[])
+            .await
+            .unwrap();
+    }
+}
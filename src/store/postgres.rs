@@ -0,0 +1,68 @@
+// src/store/postgres.rs
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
+use sqlx::types::Json;
+use sqlx::Row;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::sql::{db_error, sql_store};
+use super::FormStore;
+use crate::{
+    auth::ApiKey,
+    error::AppError,
+    models::{FormField, FormResponse, FormSchema, NotificationSettings},
+};
+
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS form_schemas (
+        id UUID PRIMARY KEY,
+        name TEXT NOT NULL,
+        description TEXT,
+        fields JSONB NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL,
+        updated_at TIMESTAMPTZ NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS form_responses (
+        id UUID PRIMARY KEY,
+        form_id UUID NOT NULL REFERENCES form_schemas (id) ON DELETE CASCADE,
+        data JSONB NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS form_responses_form_id_created_at_idx ON form_responses (form_id, created_at)",
+    "CREATE INDEX IF NOT EXISTS form_responses_created_at_idx ON form_responses (created_at)",
+    "CREATE TABLE IF NOT EXISTS form_notifications (
+        form_id UUID PRIMARY KEY REFERENCES form_schemas (id) ON DELETE CASCADE,
+        settings JSONB NOT NULL,
+        updated_at TIMESTAMPTZ NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS api_keys (
+        id UUID PRIMARY KEY,
+        name TEXT NOT NULL,
+        key_hash TEXT NOT NULL UNIQUE,
+        created_at TIMESTAMPTZ NOT NULL
+    )",
+];
+
+/// PostgreSQL implementation of [`FormStore`], storing answers as JSONB.
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    // The URL may hold a password
+    #[instrument(skip_all)]
+    pub async fn connect(url: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new().max_connections(10).connect(url).await?;
+
+        for statement in MIGRATIONS {
+            sqlx::query(statement).execute(&pool).await?;
+        }
+
+        Ok(Self { pool })
+    }
+}
+
+sql_store!(PostgresStore, PgRow);
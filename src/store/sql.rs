@@ -0,0 +1,278 @@
+// src/store/sql.rs
+//! The queries shared by the SQL backends. Every statement uses `$n`
+//! placeholders, which both PostgreSQL and SQLite accept, so only the table
+//! definitions and connection setup differ per backend.
+
+use crate::error::AppError;
+
+pub(super) fn db_error(action: &str) -> impl FnOnce(sqlx::Error) -> AppError + '_ {
+    move |e| AppError::DbError(format!("Failed to {}: {}", action, e))
+}
+
+/// Implements [`FormStore`](super::FormStore) for `$store`, a struct with a
+/// `pool` field, whose rows are `$row`.
+macro_rules! sql_store {
+    ($store:ident, $row:ty) => {
+        fn schema_from_row(row: &$row) -> Result<FormSchema, sqlx::Error> {
+            let Json(fields): Json<Vec<FormField>> = row.try_get("fields")?;
+            Ok(FormSchema {
+                id: Some(row.try_get("id")?),
+                name: row.try_get("name")?,
+                description: row.try_get("description")?,
+                fields,
+                created_at: Some(row.try_get("created_at")?),
+                updated_at: Some(row.try_get("updated_at")?),
+            })
+        }
+
+        fn response_from_row(row: &$row) -> Result<FormResponse, sqlx::Error> {
+            let Json(data) = row.try_get("data")?;
+            Ok(FormResponse {
+                id: Some(row.try_get("id")?),
+                form_id: row.try_get("form_id")?,
+                data,
+                created_at: Some(row.try_get::<DateTime<Utc>, _>("created_at")?),
+            })
+        }
+
+        fn api_key_from_row(row: &$row) -> Result<ApiKey, sqlx::Error> {
+            Ok(ApiKey {
+                id: row.try_get("id")?,
+                name: row.try_get("name")?,
+                key_hash: row.try_get("key_hash")?,
+                created_at: row.try_get("created_at")?,
+            })
+        }
+
+        #[async_trait]
+        impl FormStore for $store {
+            #[instrument(skip_all, fields(name = %schema.name))]
+            async fn create_form_schema(&self, schema: FormSchema) -> Result<Uuid, AppError> {
+                let id = schema.id.unwrap_or_else(Uuid::new_v4);
+                let now = Utc::now();
+
+                sqlx::query(
+                    "INSERT INTO form_schemas (id, name, description, fields, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6)",
+                )
+                .bind(id)
+                .bind(&schema.name)
+                .bind(&schema.description)
+                .bind(Json(&schema.fields))
+                .bind(now)
+                .bind(now)
+                .execute(&self.pool)
+                .await
+                .map_err(db_error("insert form schema"))?;
+
+                Ok(id)
+            }
+
+            #[instrument(skip(self))]
+            async fn get_form_schema(&self, id: Uuid) -> Result<FormSchema, AppError> {
+                let row = sqlx::query(
+                    "SELECT id, name, description, fields, created_at, updated_at FROM form_schemas WHERE id = $1",
+                )
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(db_error("fetch form schema"))?
+                .ok_or_else(|| AppError::NotFound(format!("Form schema with ID {} not found", id)))?;
+
+                schema_from_row(&row).map_err(db_error("decode form schema"))
+            }
+
+            #[instrument(skip(self))]
+            async fn list_form_ids(&self) -> Result<Vec<Uuid>, AppError> {
+                sqlx::query_scalar("SELECT id FROM form_schemas ORDER BY created_at")
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(db_error("list form schemas"))
+            }
+
+            #[instrument(skip_all, fields(form_id = %response.form_id))]
+            async fn submit_form_response(&self, response: FormResponse) -> Result<Uuid, AppError> {
+                let id = response.id.unwrap_or_else(Uuid::new_v4);
+
+                sqlx::query("INSERT INTO form_responses (id, form_id, data, created_at) VALUES ($1, $2, $3, $4)")
+                    .bind(id)
+                    .bind(response.form_id)
+                    .bind(Json(&response.data))
+                    .bind(response.created_at.unwrap_or_else(Utc::now))
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error("insert form response"))?;
+
+                Ok(id)
+            }
+
+            #[instrument(skip(self))]
+            async fn get_form_responses(&self, form_id: Uuid) -> Result<Vec<FormResponse>, AppError> {
+                let rows = sqlx::query(
+                    "SELECT id, form_id, data, created_at FROM form_responses WHERE form_id = $1 ORDER BY created_at",
+                )
+                .bind(form_id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(db_error("fetch form responses"))?;
+
+                rows.iter()
+                    .map(response_from_row)
+                    .collect::<Result<_, _>>()
+                    .map_err(db_error("decode form response"))
+            }
+
+            #[instrument(skip(self))]
+            async fn get_form_responses_between(
+                &self,
+                form_id: Uuid,
+                from: Option<DateTime<Utc>>,
+                to: Option<DateTime<Utc>>,
+            ) -> Result<Vec<FormResponse>, AppError> {
+                let rows = sqlx::query(
+                    "SELECT id, form_id, data, created_at FROM form_responses
+                     WHERE form_id = $1 AND ($2 IS NULL OR created_at >= $2) AND ($3 IS NULL OR created_at <= $3)
+                     ORDER BY created_at",
+                )
+                .bind(form_id)
+                .bind(from)
+                .bind(to)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(db_error("fetch form responses"))?;
+
+                rows.iter()
+                    .map(response_from_row)
+                    .collect::<Result<_, _>>()
+                    .map_err(db_error("decode form response"))
+            }
+
+            #[instrument(skip(self, settings))]
+            async fn set_notification_settings(
+                &self,
+                form_id: Uuid,
+                settings: &NotificationSettings,
+            ) -> Result<(), AppError> {
+                sqlx::query(
+                    "INSERT INTO form_notifications (form_id, settings, updated_at) VALUES ($1, $2, $3)
+                     ON CONFLICT (form_id) DO UPDATE SET settings = EXCLUDED.settings, updated_at = EXCLUDED.updated_at",
+                )
+                .bind(form_id)
+                .bind(Json(settings))
+                .bind(Utc::now())
+                .execute(&self.pool)
+                .await
+                .map_err(db_error("store notification settings"))?;
+
+                Ok(())
+            }
+
+            #[instrument(skip(self))]
+            async fn get_notification_settings(&self, form_id: Uuid) -> Result<Option<NotificationSettings>, AppError> {
+                let settings: Option<Json<NotificationSettings>> =
+                    sqlx::query_scalar("SELECT settings FROM form_notifications WHERE form_id = $1")
+                        .bind(form_id)
+                        .fetch_optional(&self.pool)
+                        .await
+                        .map_err(db_error("fetch notification settings"))?;
+
+                Ok(settings.map(|Json(settings)| settings))
+            }
+
+            #[instrument(skip(self))]
+            async fn delete_form(&self, id: Uuid) -> Result<(), AppError> {
+                // Responses and settings go with it through ON DELETE CASCADE
+                let result = sqlx::query("DELETE FROM form_schemas WHERE id = $1")
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error("delete form schema"))?;
+
+                if result.rows_affected() == 0 {
+                    return Err(AppError::NotFound(format!("Form schema with ID {} not found", id)));
+                }
+                Ok(())
+            }
+
+            #[instrument(skip(self))]
+            async fn delete_responses_before(&self, form_id: Uuid, before: DateTime<Utc>) -> Result<usize, AppError> {
+                let result = sqlx::query("DELETE FROM form_responses WHERE form_id = $1 AND created_at < $2")
+                    .bind(form_id)
+                    .bind(before)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error("delete form responses"))?;
+
+                Ok(result.rows_affected() as usize)
+            }
+
+            #[instrument(skip_all, fields(name = %key.name))]
+            async fn create_api_key(&self, key: &ApiKey) -> Result<(), AppError> {
+                sqlx::query("INSERT INTO api_keys (id, name, key_hash, created_at) VALUES ($1, $2, $3, $4)")
+                    .bind(key.id)
+                    .bind(&key.name)
+                    .bind(&key.key_hash)
+                    .bind(key.created_at)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error("insert API key"))?;
+
+                Ok(())
+            }
+
+            #[instrument(skip(self))]
+            async fn list_api_keys(&self) -> Result<Vec<ApiKey>, AppError> {
+                let rows = sqlx::query("SELECT id, name, key_hash, created_at FROM api_keys ORDER BY created_at")
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(db_error("list API keys"))?;
+
+                rows.iter()
+                    .map(api_key_from_row)
+                    .collect::<Result<_, _>>()
+                    .map_err(db_error("decode API key"))
+            }
+
+            #[instrument(skip_all)]
+            async fn find_api_key(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError> {
+                let row = sqlx::query("SELECT id, name, key_hash, created_at FROM api_keys WHERE key_hash = $1")
+                    .bind(key_hash)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(db_error("fetch API key"))?;
+
+                row.as_ref()
+                    .map(api_key_from_row)
+                    .transpose()
+                    .map_err(db_error("decode API key"))
+            }
+
+            #[instrument(skip(self))]
+            async fn delete_api_key(&self, id: Uuid) -> Result<bool, AppError> {
+                let result = sqlx::query("DELETE FROM api_keys WHERE id = $1")
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error("delete API key"))?;
+
+                Ok(result.rows_affected() > 0)
+            }
+
+            #[instrument(skip(self))]
+            async fn check_ready(&self) -> Result<(), AppError> {
+                // Also fails while the tables are missing
+                sqlx::query("SELECT 1 FROM form_schemas LIMIT 1")
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(db_error("reach the database"))?;
+                Ok(())
+            }
+
+            #[instrument(skip(self))]
+            async fn close(&self) {
+                self.pool.close().await;
+            }
+        }
+    };
+}
+
+pub(super) use sql_store;
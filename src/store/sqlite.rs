@@ -0,0 +1,87 @@
+// src/store/sqlite.rs
+use std::str::FromStr;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::types::Json;
+use sqlx::Row;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::sql::{db_error, sql_store};
+use super::FormStore;
+use crate::{
+    auth::ApiKey,
+    error::AppError,
+    models::{FormField, FormResponse, FormSchema, NotificationSettings},
+};
+
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS form_schemas (
+        id BLOB PRIMARY KEY,
+        name TEXT NOT NULL,
+        description TEXT,
+        fields TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS form_responses (
+        id BLOB PRIMARY KEY,
+        form_id BLOB NOT NULL REFERENCES form_schemas (id) ON DELETE CASCADE,
+        data TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS form_responses_form_id_created_at_idx ON form_responses (form_id, created_at)",
+    "CREATE INDEX IF NOT EXISTS form_responses_created_at_idx ON form_responses (created_at)",
+    "CREATE TABLE IF NOT EXISTS form_notifications (
+        form_id BLOB PRIMARY KEY REFERENCES form_schemas (id) ON DELETE CASCADE,
+        settings TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS api_keys (
+        id BLOB PRIMARY KEY,
+        name TEXT NOT NULL,
+        key_hash TEXT NOT NULL UNIQUE,
+        created_at TEXT NOT NULL
+    )",
+];
+
+/// SQLite implementation of [`FormStore`] for single-file deployments. Answers
+/// are stored as JSON text.
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    // The URL may hold a password
+    #[instrument(skip_all)]
+    pub async fn connect(url: &str) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .foreign_keys(true);
+        // An in-memory database lives only as long as its connections and,
+        // with a private cache, is not shared between them: keep it to one
+        // connection that is never recycled
+        let pool = if url.contains(":memory:") || url.contains("mode=memory") {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new().max_connections(5)
+        }
+        .connect_with(options)
+        .await?;
+
+        for statement in MIGRATIONS {
+            sqlx::query(statement).execute(&pool).await?;
+        }
+
+        Ok(Self { pool })
+    }
+}
+
+sql_store!(SqliteStore, SqliteRow);
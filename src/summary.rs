@@ -0,0 +1,714 @@
+// src/summary.rs
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate};
+use serde_json::Value;
+
+use crate::{
+    error::AppError,
+    models::{
+        query::{DateInterval, SummaryQuery},
+        summary::{
+            DateBucket, FieldStats, FieldSummary, FormSummary, MatrixRowStats, NpsBucket, OptionCount, OptionRank,
+            Percentiles, ScoreCount, ValueCount,
+        },
+        FieldOption, FieldType, FormField, FormResponse, FormSchema,
+    },
+    validation::{rating_max, NPS_MAX},
+};
+
+const DEFAULT_TOP_VALUES: usize = 10;
+
+/// Aggregates the given responses per field of the schema, keeping only
+/// responses created within `[from, to)` when bounds are given.
+pub fn summarize(
+    schema: &FormSchema,
+    responses: &[FormResponse],
+    query: &SummaryQuery,
+) -> Result<FormSummary, AppError> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Err(AppError::BadRequest("`from` must be earlier than `to`".to_string()));
+        }
+    }
+
+    let responses: Vec<&FormResponse> = responses
+        .iter()
+        .filter(|r| match r.created_at {
+            Some(created_at) => {
+                query.from.is_none_or(|from| created_at >= from) && query.to.is_none_or(|to| created_at < to)
+            }
+            None => query.from.is_none() && query.to.is_none(),
+        })
+        .collect();
+
+    let fields = schema
+        .fields
+        .iter()
+        .map(|field| summarize_field(field, &responses, query))
+        .collect();
+
+    Ok(FormSummary {
+        form_id: schema.id.unwrap_or_default(),
+        total_responses: responses.len(),
+        from: query.from,
+        to: query.to,
+        fields,
+    })
+}
+
+fn summarize_field(field: &FormField, responses: &[&FormResponse], query: &SummaryQuery) -> FieldSummary {
+    let values: Vec<&Value> = responses
+        .iter()
+        .filter_map(|r| r.data.get(&field.id))
+        .filter(|v| !is_blank(v))
+        .collect();
+
+    let stats = match field.field_type {
+        FieldType::Select | FieldType::Radio | FieldType::Checkbox | FieldType::MultiSelect => {
+            option_stats(field, &values)
+        }
+        FieldType::Ranking => ranking_stats(field, &values),
+        FieldType::Matrix => matrix_stats(field, &values),
+        FieldType::Rating => rating_stats(field, &values),
+        FieldType::Nps => nps_stats(&field.id, responses, query.interval.unwrap_or_default()),
+        FieldType::Group => group_stats(field, &values, responses, query),
+        FieldType::Number | FieldType::Range => number_stats(&values),
+        FieldType::Date | FieldType::DateTime => date_stats(&values, query.interval.unwrap_or_default()),
+        FieldType::Text
+        | FieldType::Email
+        | FieldType::Textarea
+        | FieldType::Phone
+        | FieldType::Url
+        | FieldType::Time
+        | FieldType::Color => {
+            text_stats(&values, query.top.unwrap_or(DEFAULT_TOP_VALUES))
+        }
+    };
+
+    FieldSummary {
+        field_id: field.id.clone(),
+        label: field.label.clone(),
+        field_type: field.field_type.clone(),
+        responses: values.len(),
+        blanks: responses.len() - values.len(),
+        stats,
+    }
+}
+
+pub fn is_blank(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.trim().is_empty(),
+        Value::Array(items) => items.is_empty(),
+        Value::Object(entries) => entries.is_empty(),
+        _ => false,
+    }
+}
+
+/// Flattens a stored answer into the option values it selects.
+fn selected_values(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter().flat_map(selected_values).collect(),
+        Value::String(s) => vec![s.clone()],
+        Value::Null => Vec::new(),
+        other => vec![other.to_string()],
+    }
+}
+
+/// How often each value was selected across `values`.
+fn count_selections(values: &[&Value]) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for value in values {
+        for selected in selected_values(value) {
+            *counts.entry(selected).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// Takes the counts of `options` out of `counts`, in option order.
+fn option_counts(options: &[FieldOption], counts: &mut HashMap<String, usize>) -> Vec<OptionCount> {
+    options
+        .iter()
+        .map(|o| OptionCount {
+            value: o.value.clone(),
+            label: o.label.clone(),
+            count: counts.remove(&o.value).unwrap_or(0),
+        })
+        .collect()
+}
+
+fn option_stats(field: &FormField, values: &[&Value]) -> FieldStats {
+    let mut counts = count_selections(values);
+
+    let frequencies: Vec<OptionCount> = match &field.options {
+        Some(options) => option_counts(options, &mut counts),
+        // Option-less checkboxes store a plain boolean
+        None => sorted_counts(counts.drain().collect())
+            .into_iter()
+            .map(|v| OptionCount {
+                label: v.value.clone(),
+                value: v.value,
+                count: v.count,
+            })
+            .collect(),
+    };
+
+    FieldStats::Options {
+        frequencies,
+        other: counts.values().sum(),
+    }
+}
+
+fn matrix_stats(field: &FormField, values: &[&Value]) -> FieldStats {
+    let options = field.options.as_deref().unwrap_or_default();
+    let rows = field
+        .rows
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .map(|row| {
+            let answers: Vec<&Value> = values
+                .iter()
+                .filter_map(|v| v.get(&row.id))
+                .filter(|v| !is_blank(v))
+                .collect();
+            let mut counts = count_selections(&answers);
+            MatrixRowStats {
+                row_id: row.id.clone(),
+                label: row.label.clone(),
+                responses: answers.len(),
+                frequencies: option_counts(options, &mut counts),
+                other: counts.values().sum(),
+            }
+        })
+        .collect();
+
+    FieldStats::Matrix { rows }
+}
+
+fn ranking_stats(field: &FormField, values: &[&Value]) -> FieldStats {
+    let options = field.options.as_deref().unwrap_or_default();
+    let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+    for value in values {
+        for (index, selected) in selected_values(value).into_iter().enumerate() {
+            positions.entry(selected).or_default().push(index + 1);
+        }
+    }
+
+    let mut rankings: Vec<OptionRank> = options
+        .iter()
+        .map(|o| {
+            let ranks = positions.remove(&o.value).unwrap_or_default();
+            OptionRank {
+                value: o.value.clone(),
+                label: o.label.clone(),
+                ranked: ranks.len(),
+                first_choice: ranks.iter().filter(|&&rank| rank == 1).count(),
+                mean_rank: (!ranks.is_empty()).then(|| ranks.iter().sum::<usize>() as f64 / ranks.len() as f64),
+                score: ranks.iter().map(|rank| (options.len() + 1).saturating_sub(*rank)).sum(),
+            }
+        })
+        .collect();
+    // Stable, so ties keep the order of the options
+    rankings.sort_by_key(|r| std::cmp::Reverse(r.score));
+
+    FieldStats::Ranking {
+        rankings,
+        other: positions.values().map(Vec::len).sum(),
+    }
+}
+
+/// A whole, non-negative number, as stored by `Rating` and `Nps` fields.
+fn as_score(value: &Value) -> Option<u32> {
+    let number = match value {
+        Value::Number(n) => n.as_f64()?,
+        Value::String(s) => s.trim().parse().ok()?,
+        _ => return None,
+    };
+    (number.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&number)).then_some(number as u32)
+}
+
+fn score_distribution(scores: &[u32], min: u32, max: u32) -> Vec<ScoreCount> {
+    (min..=max)
+        .map(|score| ScoreCount {
+            score,
+            count: scores.iter().filter(|&&s| s == score).count(),
+        })
+        .collect()
+}
+
+fn rating_stats(field: &FormField, values: &[&Value]) -> FieldStats {
+    let max = rating_max(field);
+    let scores: Vec<u32> = values
+        .iter()
+        .filter_map(|v| as_score(v))
+        .filter(|score| (1..=max).contains(score))
+        .collect();
+
+    FieldStats::Rating {
+        count: scores.len(),
+        invalid: values.len() - scores.len(),
+        max,
+        mean: (!scores.is_empty()).then(|| scores.iter().map(|&s| u64::from(s)).sum::<u64>() as f64 / scores.len() as f64),
+        distribution: score_distribution(&scores, 1, max),
+    }
+}
+
+#[derive(Default)]
+struct NpsTally {
+    promoters: usize,
+    passives: usize,
+    detractors: usize,
+}
+
+impl NpsTally {
+    fn add(&mut self, score: u32) {
+        match score {
+            9.. => self.promoters += 1,
+            7 | 8 => self.passives += 1,
+            _ => self.detractors += 1,
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.promoters + self.passives + self.detractors
+    }
+
+    fn score(&self) -> Option<f64> {
+        let count = self.count();
+        (count > 0).then(|| (self.promoters as f64 - self.detractors as f64) * 100.0 / count as f64)
+    }
+}
+
+fn nps_stats(field_id: &str, responses: &[&FormResponse], interval: DateInterval) -> FieldStats {
+    let mut scores = Vec::new();
+    let mut invalid = 0;
+    let mut total = NpsTally::default();
+    let mut buckets: HashMap<NaiveDate, NpsTally> = HashMap::new();
+
+    for response in responses {
+        let Some(value) = response.data.get(field_id).filter(|v| !is_blank(v)) else {
+            continue;
+        };
+        let Some(score) = as_score(value).filter(|score| *score <= NPS_MAX) else {
+            invalid += 1;
+            continue;
+        };
+        scores.push(score);
+        total.add(score);
+        if let Some(created_at) = response.created_at {
+            buckets
+                .entry(bucket_start(created_at.date_naive(), interval))
+                .or_default()
+                .add(score);
+        }
+    }
+
+    let mut trend: Vec<NpsBucket> = buckets
+        .into_iter()
+        .map(|(start, tally)| NpsBucket {
+            start,
+            count: tally.count(),
+            score: tally.score().unwrap_or_default(),
+            promoters: tally.promoters,
+            passives: tally.passives,
+            detractors: tally.detractors,
+        })
+        .collect();
+    trend.sort_by_key(|b| b.start);
+
+    FieldStats::Nps {
+        count: total.count(),
+        invalid,
+        score: total.score(),
+        promoters: total.promoters,
+        passives: total.passives,
+        detractors: total.detractors,
+        distribution: score_distribution(&scores, 0, NPS_MAX),
+        trend,
+    }
+}
+
+/// Summarizes the nested fields over every item, each item counting as a
+/// response submitted with the response it belongs to.
+fn group_stats(field: &FormField, values: &[&Value], responses: &[&FormResponse], query: &SummaryQuery) -> FieldStats {
+    let items: Vec<FormResponse> = responses
+        .iter()
+        .filter_map(|r| Some((r, r.data.get(&field.id)?.as_array()?)))
+        .flat_map(|(response, items)| {
+            items.iter().filter_map(Value::as_object).map(|item| FormResponse {
+                id: response.id,
+                form_id: response.form_id,
+                data: item.clone().into_iter().collect(),
+                created_at: response.created_at,
+            })
+        })
+        .collect();
+    let item_refs: Vec<&FormResponse> = items.iter().collect();
+    let counts: Vec<usize> = values.iter().filter_map(|v| v.as_array()).map(Vec::len).collect();
+
+    FieldStats::Group {
+        items: items.len(),
+        mean_items: (!counts.is_empty()).then(|| counts.iter().sum::<usize>() as f64 / counts.len() as f64),
+        max_items: counts.into_iter().max().unwrap_or(0),
+        fields: field
+            .fields
+            .iter()
+            .flatten()
+            .map(|nested| summarize_field(nested, &item_refs, query))
+            .collect(),
+    }
+}
+
+fn number_stats(values: &[&Value]) -> FieldStats {
+    let mut numbers: Vec<f64> = values
+        .iter()
+        .filter_map(|v| match v {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        })
+        .filter(|n: &f64| n.is_finite())
+        .collect();
+    numbers.sort_by(|a, b| a.total_cmp(b));
+
+    let invalid = values.len() - numbers.len();
+    if numbers.is_empty() {
+        return FieldStats::Number {
+            count: 0,
+            invalid,
+            min: None,
+            max: None,
+            mean: None,
+            median: None,
+            percentiles: None,
+        };
+    }
+
+    FieldStats::Number {
+        count: numbers.len(),
+        invalid,
+        min: numbers.first().copied(),
+        max: numbers.last().copied(),
+        mean: Some(numbers.iter().sum::<f64>() / numbers.len() as f64),
+        median: Some(percentile(&numbers, 50.0)),
+        percentiles: Some(Percentiles {
+            p25: percentile(&numbers, 25.0),
+            p75: percentile(&numbers, 75.0),
+            p90: percentile(&numbers, 90.0),
+            p95: percentile(&numbers, 95.0),
+            p99: percentile(&numbers, 99.0),
+        }),
+    }
+}
+
+/// Linear interpolation between closest ranks; `sorted` must not be empty.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+fn date_stats(values: &[&Value], interval: DateInterval) -> FieldStats {
+    let dates: Vec<NaiveDate> = values
+        .iter()
+        .filter_map(|v| v.as_str())
+        .filter_map(|s| {
+            // Timestamps count towards the date in their own offset
+            NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+                .ok()
+                .or_else(|| DateTime::parse_from_rfc3339(s.trim()).ok().map(|ts| ts.date_naive()))
+        })
+        .collect();
+
+    let mut buckets: HashMap<NaiveDate, usize> = HashMap::new();
+    for date in &dates {
+        *buckets.entry(bucket_start(*date, interval)).or_default() += 1;
+    }
+    let mut histogram: Vec<DateBucket> = buckets
+        .into_iter()
+        .map(|(start, count)| DateBucket { start, count })
+        .collect();
+    histogram.sort_by_key(|b| b.start);
+
+    FieldStats::Date {
+        invalid: values.len() - dates.len(),
+        earliest: dates.iter().min().copied(),
+        latest: dates.iter().max().copied(),
+        histogram,
+    }
+}
+
+fn bucket_start(date: NaiveDate, interval: DateInterval) -> NaiveDate {
+    match interval {
+        DateInterval::Day => date,
+        DateInterval::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        DateInterval::Month => date.with_day(1).unwrap_or(date),
+    }
+}
+
+fn text_stats(values: &[&Value], top: usize) -> FieldStats {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for value in values {
+        let text = match value {
+            Value::String(s) => s.trim().to_string(),
+            other => other.to_string(),
+        };
+        *counts.entry(text).or_default() += 1;
+    }
+
+    let distinct = counts.len();
+    let mut top_values = sorted_counts(counts.into_iter().collect());
+    top_values.truncate(top);
+
+    FieldStats::Text { distinct, top_values }
+}
+
+/// Most frequent first, ties broken alphabetically for stable output.
+fn sorted_counts(counts: Vec<(String, usize)>) -> Vec<ValueCount> {
+    let mut counts: Vec<ValueCount> = counts
+        .into_iter()
+        .map(|(value, count)| ValueCount { value, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{parse, Format};
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    /// Summarizes responses with the given data, all submitted at the same time.
+    fn summarize_fields(fields: &str, data: &[Value]) -> Vec<FieldSummary> {
+        let schema = parse(&format!("name: Test\nfields:\n{}", fields), Format::Yaml).unwrap();
+        let responses: Vec<FormResponse> = data
+            .iter()
+            .map(|data| FormResponse {
+                id: None,
+                form_id: uuid::Uuid::nil(),
+                data: serde_json::from_value(data.clone()).unwrap(),
+                created_at: Some(Utc.with_ymd_and_hms(2024, 5, 13, 12, 0, 0).unwrap()),
+            })
+            .collect();
+        summarize(&schema, &responses, &SummaryQuery::default()).unwrap().fields
+    }
+
+    fn histogram(stats: &FieldStats) -> Vec<(String, usize)> {
+        let FieldStats::Date { histogram, .. } = stats else {
+            panic!("not date stats: {:?}", stats);
+        };
+        histogram.iter().map(|b| (b.start.to_string(), b.count)).collect()
+    }
+
+    #[test]
+    fn interpolates_percentiles_between_ranks() {
+        let sorted = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 25.0), 1.75);
+        assert_eq!(percentile(&sorted, 50.0), 2.5);
+        assert_eq!(percentile(&sorted, 100.0), 4.0);
+
+        let tens: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert!((percentile(&tens, 90.0) - 9.1).abs() < 1e-9);
+        assert_eq!(percentile(&[7.0], 99.0), 7.0);
+    }
+
+    #[test]
+    fn summarizes_numbers_and_counts_the_unparseable() {
+        let values = [json!(4), json!(" 1.5 "), json!(10), json!("many"), json!(true)];
+        let refs: Vec<&Value> = values.iter().collect();
+        let FieldStats::Number { count, invalid, min, max, mean, median, percentiles } = number_stats(&refs) else {
+            panic!("not number stats");
+        };
+        assert_eq!((count, invalid), (3, 2));
+        assert_eq!((min, max, median), (Some(1.5), Some(10.0), Some(4.0)));
+        assert_eq!(mean, Some(15.5 / 3.0));
+        assert_eq!(percentiles.unwrap().p25, 2.75);
+
+        let FieldStats::Number { count, median, .. } = number_stats(&[]) else {
+            panic!("not number stats");
+        };
+        assert_eq!((count, median), (0, None));
+    }
+
+    #[test]
+    fn buckets_start_on_mondays_and_first_days() {
+        // 2024-05-15 is a Wednesday
+        assert_eq!(bucket_start(date("2024-05-15"), DateInterval::Day), date("2024-05-15"));
+        assert_eq!(bucket_start(date("2024-05-15"), DateInterval::Week), date("2024-05-13"));
+        assert_eq!(bucket_start(date("2024-05-13"), DateInterval::Week), date("2024-05-13"));
+        assert_eq!(bucket_start(date("2024-05-19"), DateInterval::Week), date("2024-05-13"));
+        assert_eq!(bucket_start(date("2024-05-15"), DateInterval::Month), date("2024-05-01"));
+        // Weeks may start in the previous month, or year
+        assert_eq!(bucket_start(date("2025-01-01"), DateInterval::Week), date("2024-12-30"));
+    }
+
+    #[test]
+    fn builds_date_histograms_per_interval() {
+        let values = [
+            json!("2024-05-15"),
+            json!("2024-05-13"),
+            // Counted on the 20th, the date in its own offset
+            json!("2024-05-20T23:30:00-05:00"),
+            json!("2024-06-01"),
+            json!("2024-06-01"),
+            json!("soon"),
+        ];
+        let refs: Vec<&Value> = values.iter().collect();
+
+        let stats = date_stats(&refs, DateInterval::Day);
+        assert_eq!(
+            histogram(&stats),
+            vec![
+                ("2024-05-13".to_string(), 1),
+                ("2024-05-15".to_string(), 1),
+                ("2024-05-20".to_string(), 1),
+                ("2024-06-01".to_string(), 2),
+            ]
+        );
+        let FieldStats::Date { invalid, earliest, latest, .. } = stats else {
+            panic!("not date stats");
+        };
+        assert_eq!((invalid, earliest, latest), (1, Some(date("2024-05-13")), Some(date("2024-06-01"))));
+
+        assert_eq!(
+            histogram(&date_stats(&refs, DateInterval::Week)),
+            vec![
+                ("2024-05-13".to_string(), 2),
+                ("2024-05-20".to_string(), 1),
+                ("2024-05-27".to_string(), 2),
+            ]
+        );
+        assert_eq!(
+            histogram(&date_stats(&refs, DateInterval::Month)),
+            vec![("2024-05-01".to_string(), 3), ("2024-06-01".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn tallies_nps_per_interval() {
+        let response = |score: Value, day: u32| FormResponse {
+            id: None,
+            form_id: uuid::Uuid::nil(),
+            data: [("nps".to_string(), score)].into(),
+            created_at: Some(Utc.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap()),
+        };
+        let responses = [
+            response(json!(10), 13),
+            response(json!(9), 14),
+            response(json!(8), 15),
+            response(json!(0), 20),
+            response(json!(11), 20),
+            response(json!(""), 20),
+        ];
+        let refs: Vec<&FormResponse> = responses.iter().collect();
+
+        let FieldStats::Nps { count, invalid, score, distribution, trend, .. } =
+            nps_stats("nps", &refs, DateInterval::Week)
+        else {
+            panic!("not NPS stats");
+        };
+        assert_eq!((count, invalid, score), (4, 1, Some(25.0)));
+        assert_eq!(distribution.len(), 11);
+        assert_eq!(distribution.iter().map(|d| d.count).sum::<usize>(), 4);
+        let trend: Vec<(String, usize, f64)> = trend.iter().map(|b| (b.start.to_string(), b.count, b.score)).collect();
+        assert_eq!(
+            trend,
+            vec![("2024-05-13".to_string(), 3, 200.0 / 3.0), ("2024-05-20".to_string(), 1, -100.0)]
+        );
+    }
+
+    #[test]
+    fn ranks_options_by_score_and_counts_unknown_values() {
+        let fields = summarize_fields(
+            "\x20 - { id: mains, label: Mains, field_type: Ranking, required: true,\n\
+             \x20     options: [{ value: fish, label: Fish }, { value: pasta, label: Pasta }, { value: curry, label: Curry }, { value: soup, label: Soup }] }\n",
+            &[json!({ "mains": ["curry", "fish"] }), json!({ "mains": ["fish", "pasta", "curry", "stew"] })],
+        );
+        let FieldStats::Ranking { rankings, other } = &fields[0].stats else {
+            panic!("not ranking stats: {:?}", fields[0].stats);
+        };
+        let ranked: Vec<(&str, usize, usize, Option<f64>)> =
+            rankings.iter().map(|r| (r.value.as_str(), r.score, r.first_choice, r.mean_rank)).collect();
+        assert_eq!(
+            ranked,
+            vec![("fish", 7, 1, Some(1.5)), ("curry", 6, 1, Some(2.0)), ("pasta", 3, 0, Some(2.0)), ("soup", 0, 0, None)]
+        );
+        assert_eq!(*other, 1);
+    }
+
+    #[test]
+    fn counts_matrix_answers_per_row() {
+        let fields = summarize_fields(
+            "\x20 - id: service\n\
+             \x20   label: Service\n\
+             \x20   field_type: Matrix\n\
+             \x20   required: false\n\
+             \x20   rows: [{ id: speed, label: Speed }, { id: price, label: Price }]\n\
+             \x20   options: [{ value: agree, label: Agree }, { value: disagree, label: Disagree }]\n",
+            &[
+                json!({ "service": { "speed": "agree", "price": "disagree" } }),
+                json!({ "service": { "speed": "meh" } }),
+            ],
+        );
+        let FieldStats::Matrix { rows } = &fields[0].stats else {
+            panic!("not matrix stats: {:?}", fields[0].stats);
+        };
+        let counts: Vec<(&str, usize, Vec<usize>, usize)> = rows
+            .iter()
+            .map(|r| (r.row_id.as_str(), r.responses, r.frequencies.iter().map(|f| f.count).collect(), r.other))
+            .collect();
+        assert_eq!(counts, vec![("speed", 2, vec![1, 0], 1), ("price", 1, vec![0, 1], 0)]);
+    }
+
+    #[test]
+    fn summarizes_ratings_up_to_their_maximum() {
+        let fields = summarize_fields(
+            "\x20 - { id: stars, label: Stars, field_type: Rating, required: true, max: 4 }\n",
+            &[json!({ "stars": 4 }), json!({ "stars": 1 }), json!({ "stars": 5 }), json!({ "stars": "" })],
+        );
+        assert_eq!((fields[0].responses, fields[0].blanks), (3, 1));
+        let FieldStats::Rating { count, invalid, max, mean, distribution } = &fields[0].stats else {
+            panic!("not rating stats: {:?}", fields[0].stats);
+        };
+        assert_eq!((*count, *invalid, *max, *mean), (2, 1, 4, Some(2.5)));
+        assert_eq!(distribution.iter().map(|d| d.count).collect::<Vec<_>>(), vec![1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn summarizes_group_fields_over_every_item() {
+        let fields = summarize_fields(
+            "\x20 - id: dependents\n\
+             \x20   label: Dependents\n\
+             \x20   field_type: Group\n\
+             \x20   required: false\n\
+             \x20   fields:\n\
+             \x20     - { id: name, label: Name, field_type: Text, required: true }\n\
+             \x20     - { id: age, label: Age, field_type: Number, required: false }\n",
+            &[
+                json!({ "dependents": [{ "name": "Ann", "age": 7.0 }, { "name": "Bob" }] }),
+                json!({ "dependents": [{ "name": "Cy" }] }),
+                json!({}),
+            ],
+        );
+        let FieldStats::Group { items, mean_items, max_items, fields } = &fields[0].stats else {
+            panic!("not group stats: {:?}", fields[0].stats);
+        };
+        assert_eq!((*items, *mean_items, *max_items), (3, Some(1.5), 2));
+        assert_eq!((fields[0].responses, fields[1].responses, fields[1].blanks), (3, 1, 2));
+        let FieldStats::Number { mean, .. } = fields[1].stats else {
+            panic!("not number stats: {:?}", fields[1].stats);
+        };
+        assert_eq!(mean, Some(7.0));
+    }
+}
@@ -0,0 +1,242 @@
+// src/telemetry.rs
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn, Instrument};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+use uuid::Uuid;
+
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One JSON object per line, for log shippers.
+    #[default]
+    Json,
+    /// Human-readable lines, for development.
+    Text,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    /// Filter directives such as `info` or `form_portal=debug,scylla=warn`;
+    /// `RUST_LOG` takes precedence.
+    pub level: String,
+    /// OTLP/gRPC endpoint of an OpenTelemetry collector, e.g.
+    /// `http://localhost:4317`. Requires the `otel` feature.
+    pub otlp_endpoint: Option<String>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Json,
+            level: "info".to_string(),
+            otlp_endpoint: None,
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub fn validate(&self) -> Result<()> {
+        EnvFilter::try_new(&self.level)?;
+        Ok(())
+    }
+}
+
+/// Flushes exported spans when dropped at shutdown.
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+/// Installs the global subscriber. Records from crates logging through `log`
+/// are forwarded to it as well.
+pub fn init(config: &LoggingConfig) -> Result<TelemetryGuard> {
+    let filter = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(&config.level))?;
+
+    let fmt_layer = match config.format {
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+        LogFormat::Text => fmt::layer().boxed(),
+    };
+
+    #[cfg(feature = "otel")]
+    {
+        let (otel_layer, provider) = match &config.otlp_endpoint {
+            Some(endpoint) => {
+                let (layer, provider) = otel_layer(endpoint)?;
+                (Some(layer), Some(provider))
+            }
+            None => (None, None),
+        };
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt_layer)
+            .with(otel_layer)
+            .try_init()?;
+        if let Some(endpoint) = &config.otlp_endpoint {
+            info!("Exporting traces to {}", endpoint);
+        }
+        Ok(TelemetryGuard { provider })
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        tracing_subscriber::registry().with(filter).with(fmt_layer).try_init()?;
+        if config.otlp_endpoint.is_some() {
+            warn!("An OTLP endpoint is configured, but this build lacks the `otel` feature; traces are not exported");
+        }
+        Ok(TelemetryGuard {})
+    }
+}
+
+#[cfg(feature = "otel")]
+fn otel_layer<S>(
+    endpoint: &str,
+) -> Result<(
+    tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>,
+    opentelemetry_sdk::trace::TracerProvider,
+)>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(opentelemetry_sdk::Resource::new([KeyValue::new(
+            "service.name",
+            env!("CARGO_PKG_NAME"),
+        )]))
+        .build();
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+
+    Ok((tracing_opentelemetry::layer().with_tracer(tracer), provider))
+}
+
+/// Middleware running each request in a span carrying its request id, taken
+/// from `X-Request-Id` when the client sent a sensible one, and echoing the
+/// id back in the response. Everything logged while handling the request,
+/// database calls included, is tagged with the id.
+pub async fn trace_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let request_id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.path(),
+    );
+
+    async move {
+        let start = Instant::now();
+        let mut result = next.call(req).await;
+        let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        match &mut result {
+            Ok(response) => {
+                if let Ok(value) = HeaderValue::from_str(&request_id) {
+                    response.headers_mut().insert(REQUEST_ID_HEADER, value);
+                }
+                info!(status = response.status().as_u16(), elapsed_ms, "Request completed");
+            }
+            Err(e) => warn!(elapsed_ms, error = %e, "Request failed"),
+        }
+        result
+    }
+    .instrument(span)
+    .await
+}
+
+/// Accepts ids that are safe to log and echo: short, printable ASCII.
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{middleware, web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn echoes_sensible_request_ids_and_makes_up_the_rest() {
+        let app = init_service(
+            App::new()
+                .wrap(middleware::from_fn(trace_requests))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::get().insert_header(("X-Request-Id", "abc-123")).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.headers().get("X-Request-Id").unwrap(), "abc-123");
+
+        let too_long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        for sent in [None, Some("two words"), Some(""), Some(too_long.as_str())] {
+            let mut req = TestRequest::get();
+            if let Some(id) = sent {
+                req = req.insert_header(("X-Request-Id", id));
+            }
+            let resp = call_service(&app, req.to_request()).await;
+            let id = resp.headers().get("X-Request-Id").unwrap().to_str().unwrap();
+            assert!(Uuid::parse_str(id).is_ok(), "{:?} was answered with {}", sent, id);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_log_levels() {
+        assert!(LoggingConfig::default().validate().is_ok());
+        let config = LoggingConfig {
+            level: "form_portal=debug,scylla=warn".to_string(),
+            ..LoggingConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        let config = LoggingConfig {
+            level: "form_portal=loud".to_string(),
+            ..LoggingConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -1,18 +1,24 @@
 // src/templates.rs
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
-use handlebars::{Handlebars};
-use serde_json::json;
+use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext, Renderable};
+use serde_json::{json, Value};
 
-use crate::models::{FieldType, FormSchema};
+use crate::error::AppError;
+use crate::metrics::METRICS;
+use crate::models::{FieldType, FormField, FormSchema};
+use crate::validation::{group_bounds, rating_max, selection_bounds, NPS_MAX, RANGE_MAX, RANGE_MIN, RANGE_STEP};
 
-pub fn generate_form_html(schema: &FormSchema) -> String {
-    let mut handlebars = Handlebars::new();
-    
-    // Register the form template
-    handlebars
-        .register_template_string(
-            "form",
-            r#"
+const RENDER_CACHE_CAPACITY: usize = 1024;
+
+/// A form id and the schema version it was rendered from.
+type RenderKey = (Uuid, Option<DateTime<Utc>>);
+
+const FORM_TEMPLATE: &str = r#"
             <!DOCTYPE html>
             <html lang="en">
             <head>
@@ -38,6 +44,10 @@ pub fn generate_form_html(schema: &FormSchema) -> String {
                     input[type="number"],
                     input[type="email"],
                     input[type="date"],
+                    input[type="tel"],
+                    input[type="url"],
+                    input[type="time"],
+                    input[type="datetime-local"],
                     textarea,
                     select {
                         width: 100%;
@@ -62,6 +72,101 @@ pub fn generate_form_html(schema: &FormSchema) -> String {
                     .checkbox-group input, .radio-group input {
                         margin-right: 10px;
                     }
+                    .hint {
+                        display: block;
+                        margin-top: 5px;
+                        color: #666;
+                    }
+                    .ranking {
+                        padding-left: 0;
+                        list-style: none;
+                    }
+                    .ranking li {
+                        margin-bottom: 5px;
+                    }
+                    .ranking label {
+                        font-weight: normal;
+                    }
+                    .matrix {
+                        width: 100%;
+                        border-collapse: collapse;
+                    }
+                    .matrix th, .matrix td {
+                        padding: 8px;
+                        border-bottom: 1px solid #ddd;
+                        text-align: center;
+                    }
+                    .matrix th[scope="row"] {
+                        text-align: left;
+                        font-weight: normal;
+                    }
+                    .visually-hidden {
+                        position: absolute;
+                        width: 1px;
+                        height: 1px;
+                        overflow: hidden;
+                        clip: rect(0 0 0 0);
+                        white-space: nowrap;
+                    }
+                    .stars {
+                        display: inline-flex;
+                        flex-direction: row-reverse;
+                    }
+                    .stars input {
+                        position: absolute;
+                        opacity: 0;
+                    }
+                    .stars label {
+                        display: inline;
+                        margin: 0 2px;
+                        font-size: 2em;
+                        font-weight: normal;
+                        color: #ccc;
+                        cursor: pointer;
+                    }
+                    .stars input:checked ~ label,
+                    .stars label:hover,
+                    .stars label:hover ~ label {
+                        color: #f5a623;
+                    }
+                    .stars input:focus-visible + label {
+                        outline: 2px solid #4CAF50;
+                    }
+                    .nps, .nps-hint {
+                        display: flex;
+                        justify-content: space-between;
+                    }
+                    .nps label {
+                        display: flex;
+                        flex-direction: column;
+                        align-items: center;
+                        font-weight: normal;
+                    }
+                    .range-group {
+                        display: flex;
+                        align-items: center;
+                    }
+                    .range-group input {
+                        flex: 1;
+                        margin-right: 10px;
+                    }
+                    .group {
+                        border: 1px solid #ddd;
+                        border-radius: 4px;
+                        padding: 10px;
+                    }
+                    .group legend {
+                        font-weight: bold;
+                    }
+                    .group-item {
+                        border-bottom: 1px solid #ddd;
+                        margin-bottom: 10px;
+                        padding-bottom: 10px;
+                    }
+                    .group button:disabled {
+                        opacity: 0.5;
+                        cursor: default;
+                    }
                 </style>
             </head>
             <body>
@@ -72,131 +177,444 @@ pub fn generate_form_html(schema: &FormSchema) -> String {
                 
                 <form action="/api/forms/{{id}}/submit" method="post">
                     {{#each fields}}
-                    <div class="form-group">
-                        <label for="{{id}}">{{label}}{{#if required}} *{{/if}}</label>
-                        
-                        {{#if_eq field_type "Text"}}
-                        <input type="text" id="{{id}}" name="{{id}}" {{#if required}}required{{/if}} {{#if placeholder}}placeholder="{{placeholder}}"{{/if}}>
-                        {{/if_eq}}
-                        
-                        {{#if_eq field_type "Number"}}
-                        <input type="number" id="{{id}}" name="{{id}}" {{#if required}}required{{/if}} {{#if placeholder}}placeholder="{{placeholder}}"{{/if}}>
-                        {{/if_eq}}
-                        
-                        {{#if_eq field_type "Email"}}
-                        <input type="email" id="{{id}}" name="{{id}}" {{#if required}}required{{/if}} {{#if placeholder}}placeholder="{{placeholder}}"{{/if}}>
-                        {{/if_eq}}
-                        
-                        {{#if_eq field_type "Date"}}
-                        <input type="date" id="{{id}}" name="{{id}}" {{#if required}}required{{/if}}>
-                        {{/if_eq}}
-                        
-                        {{#if_eq field_type "Textarea"}}
-                        <textarea id="{{id}}" name="{{id}}" {{#if required}}required{{/if}} {{#if placeholder}}placeholder="{{placeholder}}"{{/if}}></textarea>
-                        {{/if_eq}}
-                        
-                        {{#if_eq field_type "Select"}}
-                        <select id="{{id}}" name="{{id}}" {{#if required}}required{{/if}}>
+                    {{> field}}
+                    {{/each}}
+                    
+                    <button type="submit">Submit</button>
+                </form>
+                <script>
+                    // datetime-local inputs carry no offset, so send the respondent's along
+                    document.querySelector("form").addEventListener("submit", function () {
+                        document.querySelectorAll("[data-offset-for]").forEach(function (offset) {
+                            var input = document.getElementById(offset.dataset.offsetFor);
+                            if (input.value) {
+                                offset.value = -new Date(input.value).getTimezoneOffset();
+                            }
+                        });
+                    });
+
+                    // Group items are copies of the group's template, numbered by
+                    // replacing its placeholder so that every item submits its own names
+                    function initGroup(group) {
+                        var items = group.querySelector(":scope > .group-items");
+                        var template = group.querySelector(":scope > template");
+                        var add = group.querySelector(":scope > .add-item");
+                        var min = Number(group.dataset.min);
+                        var max = group.dataset.max ? Number(group.dataset.max) : Infinity;
+                        var next = 0;
+                        function update() {
+                            var count = items.children.length;
+                            add.disabled = count >= max;
+                            items.querySelectorAll(":scope > .group-item > .remove-item").forEach(function (remove) {
+                                remove.disabled = count <= min;
+                            });
+                        }
+                        function addItem() {
+                            var holder = document.createElement("div");
+                            holder.innerHTML = template.innerHTML.split(group.dataset.placeholder).join(next++);
+                            var item = holder.firstElementChild;
+                            item.querySelector(":scope > .remove-item").addEventListener("click", function () {
+                                item.remove();
+                                update();
+                            });
+                            items.appendChild(item);
+                            item.querySelectorAll("fieldset.group").forEach(initGroup);
+                            update();
+                        }
+                        add.addEventListener("click", addItem);
+                        for (var i = 0; i < min; i++) {
+                            addItem();
+                        }
+                        update();
+                    }
+                    document.querySelectorAll("fieldset.group").forEach(initGroup);
+                </script>
+            </body>
+            </html>
+            "#;
+
+/// The markup of one field, with `name` and `dom_id` qualified by the
+/// enclosing groups.
+const FIELD_TEMPLATE: &str = r#"
+            <div class="form-group">
+                {{#if_eq field_type "Group"}}
+                <fieldset class="group" id="{{dom_id}}" data-min="{{min_items}}" {{#if max_items}}data-max="{{max_items}}"{{/if}} data-placeholder="{{item_placeholder}}">
+                    <legend id="{{dom_id}}-label">{{label}}{{#if required}} *{{/if}}</legend>
+                    <div class="group-items"></div>
+                    <template>
+                        <div class="group-item">
+                            {{#each fields}}
+                            {{> field}}
+                            {{/each}}
+                            <button type="button" class="remove-item">Remove</button>
+                        </div>
+                    </template>
+                    <button type="button" class="add-item">Add another</button>
+                </fieldset>
+                {{else}}
+                <label for="{{dom_id}}" id="{{dom_id}}-label">{{label}}{{#if required}} *{{/if}}</label>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Text"}}
+                <input type="text" id="{{dom_id}}" name="{{name}}" {{#if required}}required{{/if}} {{#if placeholder}}placeholder="{{placeholder}}"{{/if}}>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Number"}}
+                <input type="number" id="{{dom_id}}" name="{{name}}" {{#if required}}required{{/if}} {{#if placeholder}}placeholder="{{placeholder}}"{{/if}} {{#if min}}min="{{min}}"{{/if}} {{#if max}}max="{{max}}"{{/if}}>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Email"}}
+                <input type="email" id="{{dom_id}}" name="{{name}}" {{#if required}}required{{/if}} {{#if placeholder}}placeholder="{{placeholder}}"{{/if}}>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Date"}}
+                <input type="date" id="{{dom_id}}" name="{{name}}" {{#if required}}required{{/if}}>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Textarea"}}
+                <textarea id="{{dom_id}}" name="{{name}}" {{#if required}}required{{/if}} {{#if placeholder}}placeholder="{{placeholder}}"{{/if}}></textarea>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Select"}}
+                <select id="{{dom_id}}" name="{{name}}" {{#if required}}required{{/if}}>
+                    <option value="">-- Select --</option>
+                    {{#each options}}
+                    <option value="{{value}}">{{label}}</option>
+                    {{/each}}
+                </select>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Phone"}}
+                <input type="tel" id="{{dom_id}}" name="{{name}}" autocomplete="tel" {{#if required}}required{{/if}} {{#if placeholder}}placeholder="{{placeholder}}"{{/if}}>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Url"}}
+                <input type="url" id="{{dom_id}}" name="{{name}}" {{#if required}}required{{/if}} {{#if placeholder}}placeholder="{{placeholder}}"{{/if}}>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Time"}}
+                <input type="time" id="{{dom_id}}" name="{{name}}" {{#if required}}required{{/if}}>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "DateTime"}}
+                <input type="datetime-local" id="{{dom_id}}" name="{{name}}" {{#if required}}required{{/if}}>
+                <input type="hidden" name="{{name}}__offset" data-offset-for="{{dom_id}}">
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Range"}}
+                <div class="range-group">
+                    <input type="range" id="{{dom_id}}" name="{{name}}" min="{{min}}" max="{{max}}" step="{{step}}" value="{{min}}" oninput="this.nextElementSibling.value = this.value">
+                    <output for="{{dom_id}}">{{min}}</output>
+                </div>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Color"}}
+                <input type="color" id="{{dom_id}}" name="{{name}}">
+                {{/if_eq}}
+                
+                {{#if_eq field_type "MultiSelect"}}
+                <select id="{{dom_id}}" name="{{name}}" multiple size="{{size}}" aria-describedby="{{dom_id}}-hint" {{#if required}}required{{/if}}>
+                    {{#each options}}
+                    <option value="{{value}}">{{label}}</option>
+                    {{/each}}
+                </select>
+                <small class="hint" id="{{dom_id}}-hint">{{hint}} Hold Ctrl (Cmd on a Mac) to select several.</small>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Ranking"}}
+                <ol class="ranking" aria-describedby="{{dom_id}}-hint">
+                    {{#each slots}}
+                    <li>
+                        <label for="{{slot_id}}">Choice {{position}}</label>
+                        <select id="{{slot_id}}" name="{{../name}}" {{#if required}}required{{/if}}>
                             <option value="">-- Select --</option>
-                            {{#each options}}
+                            {{#each ../options}}
                             <option value="{{value}}">{{label}}</option>
                             {{/each}}
                         </select>
-                        {{/if_eq}}
-                        
-                        {{#if_eq field_type "Checkbox"}}
-                        <div class="checkbox-group">
+                    </li>
+                    {{/each}}
+                </ol>
+                <small class="hint" id="{{dom_id}}-hint">{{hint}} Pick each option at most once, most preferred first.</small>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Rating"}}
+                <div class="stars" role="radiogroup" aria-labelledby="{{dom_id}}-label">
+                    {{#each scale}}
+                    <input type="radio" id="{{../dom_id}}-{{this}}" name="{{../name}}" value="{{this}}" {{#if ../required}}required{{/if}}>
+                    <label for="{{../dom_id}}-{{this}}" title="{{this}} of {{../max}}">&#9733;<span class="visually-hidden">{{this}} of {{../max}}</span></label>
+                    {{/each}}
+                </div>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Nps"}}
+                <div class="nps" role="radiogroup" aria-labelledby="{{dom_id}}-label" aria-describedby="{{dom_id}}-hint">
+                    {{#each scale}}
+                    <label>
+                        <input type="radio" name="{{../name}}" value="{{this}}" {{#if ../required}}required{{/if}}>
+                        <span>{{this}}</span>
+                    </label>
+                    {{/each}}
+                </div>
+                <small class="hint nps-hint" id="{{dom_id}}-hint"><span>0 = Not at all likely</span><span>10 = Extremely likely</span></small>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Matrix"}}
+                <table class="matrix" aria-labelledby="{{dom_id}}-label">
+                    <thead>
+                        <tr>
+                            <td></td>
                             {{#each options}}
-                            <label>
-                                <input type="checkbox" name="{{../id}}" value="{{value}}"> {{label}}
-                            </label>
+                            <th scope="col">{{label}}</th>
                             {{/each}}
-                        </div>
-                        {{/if_eq}}
-                        
-                        {{#if_eq field_type "Radio"}}
-                        <div class="radio-group">
-                            {{#each options}}
-                            <label>
-                                <input type="radio" name="{{../id}}" value="{{value}}" {{#if ../required}}required{{/if}}> {{label}}
-                            </label>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {{#each rows}}
+                        <tr>
+                            <th scope="row">{{label}}{{#if required}} *{{/if}}</th>
+                            {{#each ../options}}
+                            <td>
+                                <input type="{{../../input_type}}" name="{{../../name}}[{{../id}}]" value="{{value}}" aria-label="{{../label}}: {{label}}" {{#if ../required}}{{#if_eq ../../input_type "radio"}}required{{/if_eq}}{{/if}}>
+                            </td>
                             {{/each}}
-                        </div>
-                        {{/if_eq}}
-                    </div>
+                        </tr>
+                        {{/each}}
+                    </tbody>
+                </table>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Checkbox"}}
+                <div class="checkbox-group">
+                    {{#if options}}
+                    {{#each options}}
+                    <label>
+                        <input type="checkbox" name="{{../name}}" value="{{value}}"> {{label}}
+                    </label>
                     {{/each}}
-                    
-                    <button type="submit">Submit</button>
-                </form>
-            </body>
-            </html>
-            "#,
-        )
-        .expect("Failed to register template");
-    
-    // Replace the if_eq helper implementation with this version:
-    handlebars.register_helper(
-        "if_eq",
-        Box::new(move |h: &handlebars::Helper,
-                      _: &handlebars::Handlebars,
-                      _: &handlebars::Context,
-                      _: &mut handlebars::RenderContext,
-                      out: &mut dyn handlebars::Output| {
-            // Get parameters
-            let param = h.param(0).and_then(|v| Some(v.value()));
-            let value = h.param(1).and_then(|v| Some(v.value()));
-            
-            // Compare values if both are present
-            let result = match (param, value) {
-                (Some(p), Some(v)) => p == v,
-                _ => false,
-            };
-            
-            // Just output true or false instead of rendering templates
-            out.write(if result { "true" } else { "false" })?;
-            
-            Ok(())
+                    {{else}}
+                    <input type="checkbox" id="{{dom_id}}" name="{{name}}" {{#if required}}required{{/if}}>
+                    {{/if}}
+                </div>
+                {{/if_eq}}
+                
+                {{#if_eq field_type "Radio"}}
+                <div class="radio-group">
+                    {{#each options}}
+                    <label>
+                        <input type="radio" name="{{../name}}" value="{{value}}" {{#if ../required}}required{{/if}}> {{label}}
+                    </label>
+                    {{/each}}
+                </div>
+                {{/if_eq}}
+            </div>
+            "#;
+
+/// Renders forms with the built-in template, or with `form.hbs` from the
+/// configured template directory when it has one. Rendered pages are cached
+/// per schema version.
+pub struct FormRenderer {
+    handlebars: Handlebars<'static>,
+    cache: RwLock<HashMap<RenderKey, String>>,
+}
+
+impl FormRenderer {
+    pub fn new(template_dir: Option<&Path>) -> Result<Self, AppError> {
+        let mut handlebars = Handlebars::new();
+
+        // Register the form template
+        match template_dir.map(|dir| dir.join("form.hbs")).filter(|path| path.is_file()) {
+            Some(path) => handlebars.register_template_file("form", &path),
+            None => handlebars.register_template_string("form", FORM_TEMPLATE),
+        }
+        .map_err(|e| AppError::InternalError(format!("Failed to register form template: {}", e)))?;
+
+        handlebars
+            .register_partial("field", FIELD_TEMPLATE)
+            .map_err(|e| AppError::InternalError(format!("Failed to register field template: {}", e)))?;
+        handlebars.register_helper("if_eq", Box::new(if_eq));
+
+        Ok(Self {
+            handlebars,
+            cache: RwLock::new(HashMap::new()),
         })
-    );
+    }
+
+    pub fn render(&self, schema: &FormSchema) -> String {
+        let key = schema.id.map(|id| (id, schema.updated_at));
+        if let Some(key) = &key {
+            if let Some(html) = self.cache.read().unwrap_or_else(|e| e.into_inner()).get(key) {
+                METRICS.record_render(true);
+                return html.clone();
+            }
+        }
+        METRICS.record_render(false);
+
+        let html = self.render_uncached(schema);
+        if let Some(key) = key {
+            let mut cache = self.cache.write().unwrap_or_else(|e| e.into_inner());
+            // Crude, but forms are rarely numerous enough to make this matter
+            if cache.len() >= RENDER_CACHE_CAPACITY {
+                cache.clear();
+            }
+            cache.insert(key, html.clone());
+        }
+        html
+    }
+
+    fn render_uncached(&self, schema: &FormSchema) -> String {
+        // Convert fields to proper JSON representation
+        let fields_json = schema
+            .fields
+            .iter()
+            .map(|field| field_json(field, &field.id, &field.id, 0))
+            .collect::<Vec<_>>();
     
-    // Convert fields to proper JSON representation
-    let fields_json = schema
-        .fields
+        // Render the template
+        self.handlebars
+            .render(
+                "form",
+                &json!({
+                    "id": schema.id.unwrap_or_else(Uuid::new_v4).to_string(),
+                    "name": schema.name,
+                    "description": schema.description,
+                    "fields": fields_json
+                }),
+            )
+            .unwrap_or_else(|_| "Failed to render form template".to_string())
+    }
+}
+
+/// The template data of a field, named `name` in submissions and `dom_id`
+/// in the page. The fields of a group are named after their item, numbered by
+/// the page in place of the group's placeholder.
+fn field_json(field: &FormField, name: &str, dom_id: &str, depth: usize) -> Value {
+    let field_type = match field.field_type {
+        FieldType::Text => "Text",
+        FieldType::Number => "Number",
+        FieldType::Email => "Email",
+        FieldType::Date => "Date",
+        FieldType::Checkbox => "Checkbox",
+        FieldType::Select => "Select",
+        FieldType::Radio => "Radio",
+        FieldType::Textarea => "Textarea",
+        FieldType::Phone => "Phone",
+        FieldType::Url => "Url",
+        FieldType::Time => "Time",
+        FieldType::DateTime => "DateTime",
+        FieldType::Range => "Range",
+        FieldType::Color => "Color",
+        FieldType::MultiSelect => "MultiSelect",
+        FieldType::Ranking => "Ranking",
+        FieldType::Matrix => "Matrix",
+        FieldType::Rating => "Rating",
+        FieldType::Nps => "Nps",
+        FieldType::Group => "Group",
+    };
+    // Stars are listed from the highest down, see the `.stars` styles
+    let scale: Vec<u32> = match field.field_type {
+        FieldType::Rating => (1..=rating_max(field)).rev().collect(),
+        FieldType::Nps => (0..=NPS_MAX).collect(),
+        _ => Vec::new(),
+    };
+    let rows = field
+        .rows
         .iter()
-        .map(|field| {
-            let field_type = match field.field_type {
-                FieldType::Text => "Text",
-                FieldType::Number => "Number",
-                FieldType::Email => "Email",
-                FieldType::Date => "Date",
-                FieldType::Checkbox => "Checkbox",
-                FieldType::Select => "Select",
-                FieldType::Radio => "Radio",
-                FieldType::Textarea => "Textarea",
-            };
-            
+        .flatten()
+        .map(|row| {
             json!({
-                "id": field.id,
-                "label": field.label,
-                "field_type": field_type,
-                "required": field.required,
-                "placeholder": field.placeholder,
-                "options": field.options,
-                "validation": field.validation
+                "id": row.id,
+                "label": row.label,
+                "required": row.required.unwrap_or(field.required),
             })
         })
         .collect::<Vec<_>>();
-    
-    // Render the template
-    handlebars
-        .render(
-            "form",
-            &json!({
-                "id": schema.id.unwrap_or_else(Uuid::new_v4).to_string(),
-                "name": schema.name,
-                "description": schema.description,
-                "fields": fields_json
-            }),
-        )
-        .unwrap_or_else(|_| "Failed to render form template".to_string())
-}
\ No newline at end of file
+    let option_count = field.options.as_ref().map_or(0, Vec::len);
+    let (min_selections, max_selections) = selection_bounds(field);
+    let hint = match (min_selections, max_selections) {
+        (min, max) if min == max => format!("Choose {}.", min),
+        (0, max) => format!("Choose up to {}.", max),
+        (min, max) => format!("Choose between {} and {}.", min, max),
+    };
+    // One select per rank; the first labelled by the field's own label
+    let slots = (1..=max_selections)
+        .map(|position| {
+            json!({
+                "position": position,
+                "slot_id": if position == 1 { dom_id.to_string() } else { format!("{}-{}", dom_id, position) },
+                "required": position <= min_selections,
+            })
+        })
+        .collect::<Vec<_>>();
+    // Range inputs always get bounds, so the slider matches validation
+    let (min, max, step) = match field.field_type {
+        FieldType::Range => (
+            Some(field.min.unwrap_or(RANGE_MIN)),
+            Some(field.max.unwrap_or(RANGE_MAX)),
+            Some(field.step.unwrap_or(RANGE_STEP)),
+        ),
+        FieldType::Rating => (None, Some(rating_max(field) as f64), None),
+        _ => (field.min, field.max, field.step),
+    };
+    // Placeholders differ per depth, so nested groups keep theirs when an outer item is numbered
+    let item_placeholder = format!("__i{}__", depth);
+    let (min_items, max_items) = group_bounds(field);
+    let fields = field
+        .fields
+        .iter()
+        .flatten()
+        .map(|nested| {
+            field_json(
+                nested,
+                &format!("{}[{}][{}]", name, item_placeholder, nested.id),
+                &format!("{}-{}-{}", dom_id, item_placeholder, nested.id),
+                depth + 1,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "id": field.id,
+        "name": name,
+        "dom_id": dom_id,
+        "label": field.label,
+        "field_type": field_type,
+        "required": field.required,
+        "placeholder": field.placeholder,
+        "options": field.options,
+        "validation": field.validation,
+        // As strings, so a bound of 0 still passes `{{#if}}`
+        "min": min.map(|v| v.to_string()),
+        "max": max.map(|v| v.to_string()),
+        "step": step.map(|v| v.to_string()),
+        "size": option_count.clamp(2, 8),
+        "hint": hint,
+        "slots": slots,
+        "rows": rows,
+        "scale": scale,
+        "input_type": if field.multiple { "checkbox" } else { "radio" },
+        "fields": fields,
+        "item_placeholder": item_placeholder,
+        "min_items": min_items,
+        "max_items": (max_items != usize::MAX).then_some(max_items)
+    })
+}
+
+/// `{{#if_eq a b}}...{{else}}...{{/if_eq}}`: renders the block when both
+/// parameters are equal, and the `else` block otherwise.
+fn if_eq<'reg, 'rc>(
+    h: &Helper<'reg, 'rc>,
+    r: &'reg Handlebars<'reg>,
+    ctx: &'rc Context,
+    rc: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let equal = match (h.param(0), h.param(1)) {
+        (Some(a), Some(b)) => a.value() == b.value(),
+        _ => false,
+    };
+    match if equal { h.template() } else { h.inverse() } {
+        Some(template) => template.render(r, ctx, rc, out),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,171 @@
+// src/typescript.rs
+//! TypeScript declarations of a form's response data, generated from the
+//! JSON Schema of [`jsonschema::export`] so both describe the same shape.
+
+use std::fmt::Write;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{jsonschema, models::FormSchema};
+
+static IDENTIFIER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Za-z_$][A-Za-z0-9_$]*$").unwrap());
+
+/// An exported interface named after the form, e.g. `CustomerFeedbackResponse`
+/// for "Customer Feedback".
+pub fn interface(schema: &FormSchema) -> String {
+    let document = jsonschema::export(schema);
+
+    let mut out = String::new();
+    if let Some(id) = schema.id {
+        let _ = writeln!(out, "// Generated by form_portal from form {}.", id);
+        let _ = writeln!(out);
+    }
+    let _ = writeln!(out, "{}", doc_comment(&schema.name, ""));
+    let _ = writeln!(out, "export interface {} {}", interface_name(&schema.name), object_type(&document, ""));
+    out
+}
+
+/// PascalCase of the form name's alphanumeric words, with `Response` appended.
+fn interface_name(form_name: &str) -> String {
+    let mut name: String = form_name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first.into_iter().chain(chars).collect::<String>()
+        })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert_str(0, "Form");
+    }
+    name + "Response"
+}
+
+fn object_type(schema: &Value, indent: &str) -> String {
+    let Some(properties) = schema["properties"].as_object() else {
+        return "Record<string, unknown>".to_string();
+    };
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let inner = format!("{}  ", indent);
+
+    let mut out = String::from("{\n");
+    for (name, property) in properties {
+        if let Some(title) = property["title"].as_str() {
+            let _ = writeln!(out, "{}", doc_comment(title, &inner));
+        }
+        let optional = if required.contains(&name.as_str()) { "" } else { "?" };
+        let _ = writeln!(out, "{}{}{}: {};", inner, property_name(name), optional, type_of(property, &inner));
+    }
+    out.push_str(indent);
+    out.push('}');
+    out
+}
+
+fn type_of(schema: &Value, indent: &str) -> String {
+    if let Some(values) = schema["enum"].as_array() {
+        return values.iter().map(Value::to_string).collect::<Vec<_>>().join(" | ");
+    }
+    let types: Vec<&str> = match &schema["type"] {
+        Value::String(t) => vec![t.as_str()],
+        Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+        _ => return "unknown".to_string(),
+    };
+    types
+        .iter()
+        .map(|t| match *t {
+            "string" => "string".to_string(),
+            "number" | "integer" => "number".to_string(),
+            "boolean" => "boolean".to_string(),
+            "null" => "null".to_string(),
+            "array" => format!("Array<{}>", type_of(&schema["items"], indent)),
+            "object" => object_type(schema, indent),
+            _ => "unknown".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// The name as is when it is an identifier, quoted otherwise.
+fn property_name(name: &str) -> String {
+    if IDENTIFIER.is_match(name) {
+        name.to_string()
+    } else {
+        Value::from(name).to_string()
+    }
+}
+
+fn doc_comment(text: &str, indent: &str) -> String {
+    format!("{}/** {} */", indent, text.replace("*/", "*\\/").replace('\n', " "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{parse, Format};
+
+    #[test]
+    fn declares_choices_patterns_matrices_and_groups() {
+        let yaml = "name: Trip booking (2024)\nfields:\n\
+            \x20 - { id: contact-email, label: Email, field_type: Email, required: true, validation: '@example\\.com$' }\n\
+            \x20 - id: seat\n\
+            \x20   label: Seat\n\
+            \x20   field_type: Select\n\
+            \x20   required: false\n\
+            \x20   options: [{ value: aisle, label: Aisle }, { value: window, label: Window }]\n\
+            \x20 - id: service\n\
+            \x20   label: Service\n\
+            \x20   field_type: Matrix\n\
+            \x20   required: true\n\
+            \x20   rows: [{ id: speed, label: Speed }, { id: price, label: Price, required: false }]\n\
+            \x20   options: [{ value: good, label: Good }, { value: bad, label: Bad }]\n\
+            \x20 - id: travellers\n\
+            \x20   label: Travellers\n\
+            \x20   field_type: Group\n\
+            \x20   required: false\n\
+            \x20   fields:\n\
+            \x20     - { id: name, label: Name, field_type: Text, required: true }\n\
+            \x20     - { id: age, label: Age, field_type: Number, required: false }\n";
+        let types = interface(&parse(yaml, Format::Yaml).unwrap());
+
+        let expected = [
+            "/** Trip booking (2024) */",
+            "export interface TripBooking2024Response {",
+            "  /** Email */",
+            "  \"contact-email\": string;",
+            "  /** Seat */",
+            "  seat?: \"aisle\" | \"window\" | null;",
+            "  /** Service */",
+            "  service: {",
+            "    /** Speed */",
+            "    speed: \"good\" | \"bad\";",
+            "    /** Price */",
+            "    price?: \"good\" | \"bad\";",
+            "  };",
+            "  /** Travellers */",
+            "  travellers?: Array<{",
+            "    /** Name */",
+            "    name: string;",
+            "    /** Age */",
+            "    age?: number | null;",
+            "  }> | null;",
+            "}",
+            "",
+        ];
+        assert_eq!(types, expected.join("\n"));
+    }
+
+    #[test]
+    fn names_interfaces_and_properties_safely() {
+        assert_eq!(interface_name("Customer feedback"), "CustomerFeedbackResponse");
+        assert_eq!(interface_name("2024 survey"), "Form2024SurveyResponse");
+        assert_eq!(property_name("$rating_2"), "$rating_2");
+        assert_eq!(property_name("first name"), "\"first name\"");
+        assert_eq!(doc_comment("Ends */ here\nand on", "  "), "  /** Ends *\\/ here and on */");
+    }
+}
@@ -0,0 +1,707 @@
+// src/validation.rs
+//! Server-side checks of form submissions.
+//!
+//! Browsers enforce most of these rules already, but submissions can come
+//! from anywhere. Each answer is checked against its field and converted to
+//! the value that is stored: numbers as numbers, option-less checkboxes as
+//! booleans, and phone numbers, times, timestamps and colors in one
+//! canonical spelling so they filter and sort consistently.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{LazyLock, RwLock};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone};
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use url::Url;
+
+use crate::{
+    error::AppError,
+    models::{FieldType, FormField, FormSchema},
+};
+
+/// Suffix of the hidden input through which the rendered form reports the
+/// respondent's UTC offset, in minutes, for each `DateTime` field.
+pub const OFFSET_SUFFIX: &str = "__offset";
+
+/// Stars of a `Rating` field that does not set `max`.
+pub const RATING_MAX: u32 = 5;
+
+/// Most stars a `Rating` field may have.
+pub const RATING_LIMIT: u32 = 10;
+
+/// Highest answer to an `Nps` field; the lowest is 0.
+pub const NPS_MAX: u32 = 10;
+
+/// Bounds and increment of a `Range` field that does not set its own.
+pub const RANGE_MIN: f64 = 0.0;
+pub const RANGE_MAX: f64 = 100.0;
+pub const RANGE_STEP: f64 = 1.0;
+
+/// Compiled `validation` patterns kept before the cache starts over.
+const PATTERN_CACHE_SIZE: usize = 1024;
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s]+$").unwrap());
+static E164: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\+[1-9]\d{1,14}$").unwrap());
+
+/// The `validation` patterns of fields, compiled or rejected, by pattern.
+static PATTERNS: LazyLock<RwLock<HashMap<String, Result<Regex, String>>>> = LazyLock::new(Default::default);
+
+/// Submitted values by name, in submission order.
+type Submitted<'a> = HashMap<String, Vec<&'a str>>;
+
+/// The data to store for a submission, or a `ValidationError` listing every
+/// answer that was rejected. Answers to unknown fields are dropped. Fields
+/// holding a single value take the last one submitted for them.
+pub fn coerce_submission(
+    schema: &FormSchema,
+    answers: &[(String, String)],
+) -> Result<HashMap<String, Value>, AppError> {
+    let mut submitted = Submitted::new();
+    for (name, value) in answers {
+        submitted.entry(name.clone()).or_default().push(value.as_str());
+    }
+
+    let mut errors = Vec::new();
+    let data = coerce_fields(&schema.fields, &submitted, "", &mut errors);
+
+    if errors.is_empty() {
+        Ok(data.into_iter().collect())
+    } else {
+        Err(AppError::ValidationError(errors.join("; ")))
+    }
+}
+
+/// The answers that, submitted to a form with `fields`, produce `data`, so
+/// stored or imported data can be checked with [`coerce_submission`].
+pub fn submission_from_data(fields: &[FormField], data: &HashMap<String, Value>) -> Vec<(String, String)> {
+    let mut answers = Vec::new();
+    for field in fields {
+        if let Some(value) = data.get(&field.id) {
+            add_answers(&field.id, value, &mut answers);
+        }
+    }
+    answers
+}
+
+/// Adds `value` submitted as `name`: arrays as repeated answers, objects
+/// (matrix rows and group items) under `{name}[{key}]`.
+fn add_answers(name: &str, value: &Value, answers: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => answers.push((name.to_string(), String::new())),
+        Value::String(s) => answers.push((name.to_string(), s.clone())),
+        Value::Bool(_) | Value::Number(_) => answers.push((name.to_string(), value.to_string())),
+        Value::Array(items) if items.iter().all(Value::is_object) && !items.is_empty() => {
+            for (index, item) in items.iter().enumerate() {
+                add_answers(&format!("{}[{}]", name, index), item, answers);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                add_answers(name, item, answers);
+            }
+        }
+        Value::Object(entries) => {
+            for (key, value) in entries {
+                add_answers(&format!("{}[{}]", name, key), value, answers);
+            }
+        }
+    }
+}
+
+/// Answers to `fields`, looked up by field id in `submitted`. Rejected
+/// answers are added to `errors` under their full name, which is the field
+/// id nested in `prefix` when given: `{prefix}[{id}]`.
+fn coerce_fields(
+    fields: &[FormField],
+    submitted: &Submitted,
+    prefix: &str,
+    errors: &mut Vec<String>,
+) -> Map<String, Value> {
+    let mut data = Map::new();
+
+    for field in fields {
+        let name = if prefix.is_empty() {
+            field.id.clone()
+        } else {
+            format!("{}[{}]", prefix, field.id)
+        };
+        let values = submitted.get(&field.id).map(Vec::as_slice).unwrap_or_default();
+        let answered: Vec<&str> = values.iter().copied().filter(|v| !v.trim().is_empty()).collect();
+
+        let coerced = match (answered.last(), &field.field_type) {
+            (_, FieldType::Matrix) => {
+                let rows = coerce_matrix(field, &name, submitted, errors);
+                if rows.is_empty() {
+                    continue;
+                }
+                Ok(Value::Object(rows))
+            }
+            (_, FieldType::Group) => match coerce_group(field, &name, submitted, errors) {
+                Ok(items) if items.is_empty() => continue,
+                result => result.map(Value::from),
+            },
+            // Browsers leave unticked checkboxes out of the submission
+            (None, FieldType::Checkbox) if field.options.is_none() && !field.required => Ok(json!(false)),
+            (None, _) if field.required => Err("is required".to_string()),
+            (None, _) if !values.is_empty() => Ok(Value::Null),
+            (None, _) => continue,
+            (Some(_), FieldType::MultiSelect | FieldType::Ranking) => coerce_selections(field, &answered),
+            (Some(answer), _) => {
+                let offset = submitted
+                    .get(&format!("{}{}", field.id, OFFSET_SUFFIX))
+                    .and_then(|offsets| offsets.last().copied());
+                coerce(field, answer, offset)
+            }
+        };
+        match coerced {
+            Ok(value) => {
+                data.insert(field.id.clone(), value);
+            }
+            Err(message) => errors.push(format!("{}: {}", name, message)),
+        }
+    }
+    data
+}
+
+/// The answered rows of a `Matrix` field, each submitted as `{field}[{row}]`.
+fn coerce_matrix(
+    field: &FormField,
+    name: &str,
+    submitted: &Submitted,
+    errors: &mut Vec<String>,
+) -> Map<String, Value> {
+    let options = field.options.as_deref().unwrap_or_default();
+    let mut answers = Map::new();
+
+    for row in field.rows.as_deref().unwrap_or_default() {
+        let mut selected: Vec<&str> = Vec::new();
+        let values = submitted.get(&format!("{}[{}]", field.id, row.id));
+        for value in values.into_iter().flatten().map(|v| v.trim()) {
+            if !value.is_empty() && !selected.contains(&value) {
+                selected.push(value);
+            }
+        }
+
+        let answer = match selected.as_slice() {
+            [] if row.required.unwrap_or(field.required) => Err("is required".to_string()),
+            [] => continue,
+            _ => match selected.iter().find(|v| !options.iter().any(|o| o.value == **v)) {
+                Some(unknown) => Err(format!("`{}` is not one of the options", unknown)),
+                None if field.multiple => Ok(json!(selected)),
+                None => Ok(json!(selected[selected.len() - 1])),
+            },
+        };
+        match answer {
+            Ok(value) => {
+                answers.insert(row.id.clone(), value);
+            }
+            Err(message) => errors.push(format!("{}[{}]: {}", name, row.id, message)),
+        }
+    }
+    answers
+}
+
+/// The items of a `Group` field, each of whose fields is submitted as
+/// `{field}[{index}][{id}]`. Items are kept in index order, which may have
+/// gaps where items were removed, and items left entirely blank are dropped.
+fn coerce_group(
+    field: &FormField,
+    name: &str,
+    submitted: &Submitted,
+    errors: &mut Vec<String>,
+) -> Result<Vec<Value>, String> {
+    let prefix = format!("{}[", field.id);
+    let mut items: BTreeMap<usize, Submitted> = BTreeMap::new();
+    for (key, values) in submitted {
+        let Some((index, rest)) = key.strip_prefix(&prefix).and_then(|rest| rest.split_once(']')) else {
+            continue;
+        };
+        let (Ok(index), Some((id, rest))) = (index.parse::<usize>(), rest.strip_prefix('[').and_then(|r| r.split_once(']')))
+        else {
+            continue;
+        };
+        // `deps[0][name]` becomes `name` and `deps[0][scale][row]` becomes `scale[row]`
+        items.entry(index).or_default().insert(format!("{}{}", id, rest), values.clone());
+    }
+    items.retain(|_, answers| answers.values().flatten().any(|v| !v.trim().is_empty()));
+
+    let (min, max) = group_bounds(field);
+    if items.len() < min {
+        return Err(if items.is_empty() { "is required".to_string() } else { format!("add at least {} items", min) });
+    }
+    if items.len() > max {
+        return Err(format!("add at most {} items", max));
+    }
+
+    let fields = field.fields.as_deref().unwrap_or_default();
+    Ok(items
+        .iter()
+        .map(|(index, answers)| Value::Object(coerce_fields(fields, answers, &format!("{}[{}]", name, index), errors)))
+        .collect())
+}
+
+/// How many items a `Group` answer may hold.
+pub fn group_bounds(field: &FormField) -> (usize, usize) {
+    let min = field.min_items.unwrap_or(usize::from(field.required));
+    (min, field.max_items.unwrap_or(usize::MAX))
+}
+
+/// Number of stars of a `Rating` field.
+pub fn rating_max(field: &FormField) -> u32 {
+    field.max.map_or(RATING_MAX, |max| max.clamp(1.0, RATING_LIMIT as f64) as u32)
+}
+
+/// How many options a `MultiSelect` or `Ranking` answer may hold.
+pub fn selection_bounds(field: &FormField) -> (usize, usize) {
+    let options = field.options.as_ref().map_or(0, Vec::len);
+    let min = field.min_selections.unwrap_or(usize::from(field.required));
+    let max = field.max_selections.unwrap_or(options).min(options);
+    (min, max)
+}
+
+/// The selected option values in the order they were submitted, which for
+/// a `Ranking` is the order of preference.
+fn coerce_selections(field: &FormField, selected: &[&str]) -> Result<Value, String> {
+    let options = field.options.as_deref().unwrap_or_default();
+    let mut values: Vec<&str> = Vec::new();
+    for value in selected.iter().map(|v| v.trim()) {
+        if !options.iter().any(|o| o.value == value) {
+            return Err(format!("`{}` is not one of the options", value));
+        }
+        if values.contains(&value) {
+            return Err(format!("`{}` is chosen more than once", value));
+        }
+        values.push(value);
+    }
+
+    let (min, max) = selection_bounds(field);
+    if values.len() < min {
+        return Err(format!("choose at least {}", min));
+    }
+    if values.len() > max {
+        return Err(format!("choose at most {}", max));
+    }
+    Ok(json!(values))
+}
+
+fn coerce(field: &FormField, raw: &str, offset: Option<&str>) -> Result<Value, String> {
+    let answer = raw.trim();
+    let value = match field.field_type {
+        FieldType::Number => {
+            let number = parse_number(answer)?;
+            check_bounds(number, field.min, field.max)?;
+            json!(number)
+        }
+        FieldType::Range => {
+            let number = parse_number(answer)?;
+            let min = field.min.unwrap_or(RANGE_MIN);
+            check_bounds(number, Some(min), Some(field.max.unwrap_or(RANGE_MAX)))?;
+            let step = field.step.unwrap_or(RANGE_STEP);
+            let steps = (number - min) / step;
+            if step > 0.0 && (steps - steps.round()).abs() > 1e-9 {
+                return Err(format!("must be {} plus a multiple of {}", min, step));
+            }
+            json!(number)
+        }
+        FieldType::Rating => json!(parse_score(answer, 1, rating_max(field))?),
+        FieldType::Nps => json!(parse_score(answer, 0, NPS_MAX)?),
+        FieldType::Checkbox if field.options.is_none() => match answer.to_ascii_lowercase().as_str() {
+            "on" | "true" | "yes" | "1" => json!(true),
+            "off" | "false" | "no" | "0" if !field.required => json!(false),
+            "off" | "false" | "no" | "0" => return Err("must be checked".to_string()),
+            _ => return Err(format!("`{}` is not a checkbox value", answer)),
+        },
+        FieldType::Select | FieldType::Radio | FieldType::Checkbox => {
+            let options = field.options.as_deref().unwrap_or_default();
+            if !options.is_empty() && !options.iter().any(|o| o.value == answer) {
+                return Err(format!("`{}` is not one of the options", answer));
+            }
+            json!(answer)
+        }
+        FieldType::Email => {
+            if !EMAIL.is_match(answer) {
+                return Err("is not an email address".to_string());
+            }
+            json!(answer)
+        }
+        FieldType::Date => {
+            NaiveDate::parse_from_str(answer, "%Y-%m-%d").map_err(|_| "is not a date (YYYY-MM-DD)".to_string())?;
+            json!(answer)
+        }
+        FieldType::Time => json!(parse_time(answer)?.format("%H:%M:%S").to_string()),
+        FieldType::DateTime => json!(parse_datetime(answer, offset)?.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        FieldType::Phone => json!(normalize_phone(answer)?),
+        FieldType::Url => json!(parse_url(answer)?),
+        FieldType::Color => json!(normalize_color(answer)?),
+        FieldType::Text | FieldType::Textarea => json!(raw),
+        FieldType::MultiSelect | FieldType::Ranking => return coerce_selections(field, &[answer]),
+        FieldType::Matrix => return Err("must be submitted per row".to_string()),
+        FieldType::Group => return Err("must be submitted per item".to_string()),
+    };
+
+    if let (Some(pattern), Value::String(s)) = (&field.validation, &value) {
+        // Unanchored, as `pattern` is in JSON Schema; broken patterns, which
+        // only forms stored before they were rejected can have, are ignored
+        if let Ok(re) = compile_pattern(pattern) {
+            if !re.is_match(s) {
+                return Err(format!("does not match {}", pattern));
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// The compiled `validation` pattern of a field, or why it does not compile.
+/// Patterns are compiled once and then reused across submissions.
+pub fn compile_pattern(pattern: &str) -> Result<Regex, String> {
+    if let Some(compiled) = PATTERNS.read().unwrap_or_else(|e| e.into_inner()).get(pattern) {
+        return compiled.clone();
+    }
+
+    // The last line of a syntax error says what is wrong; those above quote the pattern
+    let compiled = Regex::new(pattern).map_err(|e| {
+        let message = e.to_string();
+        let reason = message.lines().last().unwrap_or_default();
+        reason.trim_start_matches("error: ").to_string()
+    });
+    let mut patterns = PATTERNS.write().unwrap_or_else(|e| e.into_inner());
+    if patterns.len() >= PATTERN_CACHE_SIZE {
+        patterns.clear();
+    }
+    patterns.insert(pattern.to_string(), compiled.clone());
+    compiled
+}
+
+fn parse_number(answer: &str) -> Result<f64, String> {
+    answer
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite())
+        .ok_or_else(|| format!("`{}` is not a number", answer))
+}
+
+fn parse_score(answer: &str, min: u32, max: u32) -> Result<u32, String> {
+    answer
+        .parse::<u32>()
+        .ok()
+        .filter(|score| (min..=max).contains(score))
+        .ok_or_else(|| format!("must be a whole number from {} to {}", min, max))
+}
+
+fn check_bounds(number: f64, min: Option<f64>, max: Option<f64>) -> Result<(), String> {
+    if let Some(min) = min.filter(|min| number < *min) {
+        return Err(format!("must be at least {}", min));
+    }
+    if let Some(max) = max.filter(|max| number > *max) {
+        return Err(format!("must be at most {}", max));
+    }
+    Ok(())
+}
+
+fn parse_time(answer: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(answer, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(answer, "%H:%M"))
+        .map_err(|_| "is not a time (HH:MM or HH:MM:SS)".to_string())
+}
+
+/// RFC 3339 timestamps keep their offset. `datetime-local` inputs carry no
+/// offset, so theirs comes from the hidden offset input, or is UTC.
+fn parse_datetime(answer: &str, offset: Option<&str>) -> Result<DateTime<FixedOffset>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(answer) {
+        return Ok(timestamp);
+    }
+    let local = NaiveDateTime::parse_from_str(answer, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(answer, "%Y-%m-%dT%H:%M"))
+        .map_err(|_| "is not a date and time (RFC 3339 or YYYY-MM-DDTHH:MM)".to_string())?;
+
+    let minutes: i32 = match offset.map(str::trim).filter(|o| !o.is_empty()) {
+        Some(offset) => offset
+            .parse()
+            .ok()
+            .filter(|m| (-720..=840).contains(m))
+            .ok_or_else(|| format!("`{}` is not a UTC offset in minutes", offset))?,
+        None => 0,
+    };
+    FixedOffset::east_opt(minutes * 60)
+        .and_then(|offset| offset.from_local_datetime(&local).single())
+        .ok_or_else(|| "is not a valid local time".to_string())
+}
+
+/// E.164: a `+`, the country code and at most 15 digits in all. Spaces,
+/// dashes, dots and parentheses are dropped and a leading `00` becomes `+`.
+fn normalize_phone(answer: &str) -> Result<String, String> {
+    let mut phone: String = answer
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect();
+    if let Some(rest) = phone.strip_prefix("00") {
+        phone = format!("+{}", rest);
+    }
+    if E164.is_match(&phone) {
+        Ok(phone)
+    } else {
+        Err("is not an international phone number such as +4930123456".to_string())
+    }
+}
+
+fn parse_url(answer: &str) -> Result<String, String> {
+    let url = Url::parse(answer).map_err(|_| "is not a URL".to_string())?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none_or(str::is_empty) {
+        return Err("must be an http or https URL".to_string());
+    }
+    Ok(url.to_string())
+}
+
+/// `#rgb` or `#rrggbb`, stored as lowercase `#rrggbb`.
+fn normalize_color(answer: &str) -> Result<String, String> {
+    let hex = answer
+        .strip_prefix('#')
+        .filter(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| "is not a color (#rrggbb)".to_string())?
+        .to_ascii_lowercase();
+    if hex.len() == 3 {
+        Ok(hex.chars().fold(String::from("#"), |mut color, c| {
+            color.push(c);
+            color.push(c);
+            color
+        }))
+    } else {
+        Ok(format!("#{}", hex))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{parse, Format};
+
+    fn schema(fields: &str) -> FormSchema {
+        parse(&format!("name: Test\nfields:\n{}", fields), Format::Yaml).unwrap()
+    }
+
+    fn answers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn stored_data_resubmits_to_the_same_data() {
+        let schema = schema(
+            "\x20 - { id: name, label: Name, field_type: Text, required: true }\n\
+             \x20 - { id: age, label: Age, field_type: Number, required: false }\n\
+             \x20 - { id: stars, label: Stars, field_type: Rating, required: false }\n\
+             \x20 - { id: agree, label: Agree, field_type: Checkbox, required: false }\n\
+             \x20 - { id: at, label: At, field_type: DateTime, required: false }\n\
+             \x20 - { id: note, label: Note, field_type: Textarea, required: false }\n\
+             \x20 - id: order\n\
+             \x20   label: Order\n\
+             \x20   field_type: Ranking\n\
+             \x20   required: false\n\
+             \x20   options: [{ value: a, label: A }, { value: b, label: B }, { value: c, label: C }]\n\
+             \x20 - id: service\n\
+             \x20   label: Service\n\
+             \x20   field_type: Matrix\n\
+             \x20   required: false\n\
+             \x20   rows: [{ id: speed, label: Speed }, { id: price, label: Price }]\n\
+             \x20   options: [{ value: good, label: Good }, { value: bad, label: Bad }]\n\
+             \x20 - id: kids\n\
+             \x20   label: Kids\n\
+             \x20   field_type: Group\n\
+             \x20   required: false\n\
+             \x20   fields:\n\
+             \x20     - { id: name, label: Name, field_type: Text, required: true }\n\
+             \x20     - { id: age, label: Age, field_type: Number, required: false }\n",
+        );
+        let submitted = answers(&[
+            ("name", "Ada"),
+            ("age", "36.5"),
+            ("stars", "4"),
+            ("agree", "on"),
+            ("at", "2024-05-01T09:30"),
+            ("at__offset", "120"),
+            ("note", ""),
+            ("order", "c"),
+            ("order", "a"),
+            ("service[speed]", "good"),
+            ("kids[0][name]", "Byron"),
+            ("kids[3][name]", "Lovelace"),
+            ("kids[3][age]", "2"),
+        ]);
+        let data = coerce_submission(&schema, &submitted).unwrap();
+        assert_eq!(data["at"], "2024-05-01T09:30:00+02:00");
+        assert_eq!(data["order"], json!(["c", "a"]));
+
+        let resubmitted = submission_from_data(&schema.fields, &data);
+        assert_eq!(coerce_submission(&schema, &resubmitted).unwrap(), data);
+    }
+
+    #[test]
+    fn resubmitted_data_is_validated_again() {
+        let schema = schema(
+            "\x20 - { id: email, label: Email, field_type: Email, required: true }\n\
+             \x20 - { id: stars, label: Stars, field_type: Rating, required: false }\n",
+        );
+        let data = [
+            ("email".to_string(), json!("not an address")),
+            ("stars".to_string(), json!(9)),
+            ("extra".to_string(), json!("dropped")),
+        ]
+        .into();
+        let error = coerce_submission(&schema, &submission_from_data(&schema.fields, &data))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("email: is not an email address"), "{}", error);
+        assert!(error.contains("stars: must be a whole number from 1 to 5"), "{}", error);
+
+        let missing = coerce_submission(&schema, &submission_from_data(&schema.fields, &HashMap::new()));
+        assert!(missing.unwrap_err().to_string().contains("email: is required"));
+    }
+
+    #[test]
+    fn validation_patterns_are_enforced_unanchored() {
+        let schema = schema("\x20 - { id: code, label: Code, field_type: Text, required: true, validation: '[A-Z]{3}' }\n");
+        assert!(coerce_submission(&schema, &answers(&[("code", "ref ABC")])).is_ok());
+        let error = coerce_submission(&schema, &answers(&[("code", "abc")])).unwrap_err().to_string();
+        assert!(error.contains("code: does not match [A-Z]{3}"), "{}", error);
+    }
+
+    #[test]
+    fn broken_patterns_give_the_same_reason_every_time() {
+        let reason = compile_pattern("a(").unwrap_err();
+        assert_eq!(reason, "unclosed group");
+        assert_eq!(compile_pattern("a(").unwrap_err(), reason);
+    }
+
+    fn rejected(schema: &FormSchema, pairs: &[(&str, &str)]) -> String {
+        coerce_submission(schema, &answers(pairs)).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn normalizes_richer_field_types() {
+        let schema = schema(
+            "\x20 - { id: phone, label: Phone, field_type: Phone, required: true }\n\
+             \x20 - { id: site, label: Website, field_type: Url, required: false }\n\
+             \x20 - { id: start, label: Start, field_type: DateTime, required: true }\n\
+             \x20 - { id: arrival, label: Arrival, field_type: Time, required: false }\n\
+             \x20 - { id: guests, label: Guests, field_type: Range, required: true, min: 1, max: 9, step: 2 }\n\
+             \x20 - { id: color, label: Color, field_type: Color, required: false }\n",
+        );
+        let data = coerce_submission(
+            &schema,
+            &answers(&[
+                ("phone", "0049 (30) 123-456"),
+                ("site", ""),
+                ("start", "2025-05-04T09:30"),
+                ("start__offset", "120"),
+                ("arrival", "18:45"),
+                ("guests", "5"),
+                ("color", "#1A2"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(data["phone"], "+4930123456");
+        assert_eq!(data["site"], Value::Null);
+        assert_eq!(data["start"], "2025-05-04T09:30:00+02:00");
+        assert_eq!(data["arrival"], "18:45:00");
+        assert_eq!(data["guests"], 5.0);
+        assert_eq!(data["color"], "#11aa22");
+
+        // Timestamps that carry their own offset need no `__offset`
+        let error = rejected(
+            &schema,
+            &[("phone", "12345"), ("site", "ftp://example.com"), ("start", "2025-05-04T09:30:00Z"), ("guests", "4")],
+        );
+        assert!(error.starts_with("Validation error: phone: "), "{}", error);
+        assert!(error.contains("site: must be an http or https URL"), "{}", error);
+        assert!(error.contains("guests: must be 1 plus a multiple of 2"), "{}", error);
+        assert!(!error.contains("start"), "{}", error);
+    }
+
+    #[test]
+    fn keeps_selections_and_rankings_in_submission_order() {
+        let schema = schema(
+            "\x20 - { id: sides, label: Sides, field_type: MultiSelect, required: false, max_selections: 2,\n\
+             \x20     options: [{ value: rice, label: Rice }, { value: salad, label: Salad }, { value: soup, label: Soup }] }\n\
+             \x20 - { id: mains, label: Mains, field_type: Ranking, required: true,\n\
+             \x20     options: [{ value: fish, label: Fish }, { value: pasta, label: Pasta }, { value: curry, label: Curry }] }\n",
+        );
+        let data = coerce_submission(
+            &schema,
+            &answers(&[("sides", "salad"), ("sides", "rice"), ("mains", "curry"), ("mains", "fish"), ("mains", "")]),
+        )
+        .unwrap();
+        assert_eq!(data["sides"], json!(["salad", "rice"]));
+        assert_eq!(data["mains"], json!(["curry", "fish"]));
+
+        let error = rejected(&schema, &[("sides", "rice"), ("sides", "salad"), ("sides", "soup"), ("mains", "fish"), ("mains", "fish")]);
+        assert_eq!(error, "Validation error: sides: choose at most 2; mains: `fish` is chosen more than once");
+    }
+
+    #[test]
+    fn checks_matrix_rows_separately() {
+        let schema = schema(
+            "\x20 - id: service\n\
+             \x20   label: How much do you agree?\n\
+             \x20   field_type: Matrix\n\
+             \x20   required: false\n\
+             \x20   rows: [{ id: speed, label: It was fast, required: true }, { id: price, label: It was cheap }]\n\
+             \x20   options: [{ value: agree, label: Agree }, { value: disagree, label: Disagree }]\n",
+        );
+        let data = coerce_submission(&schema, &answers(&[("service[speed]", "agree"), ("service[price]", "disagree")])).unwrap();
+        assert_eq!(data["service"], json!({ "speed": "agree", "price": "disagree" }));
+
+        let error = rejected(&schema, &[("service[price]", "cheap")]);
+        assert_eq!(
+            error,
+            "Validation error: service[speed]: is required; service[price]: `cheap` is not one of the options"
+        );
+    }
+
+    #[test]
+    fn bounds_ratings_and_net_promoter_scores() {
+        let schema = schema(
+            "\x20 - { id: stars, label: Stars, field_type: Rating, required: true, max: 4 }\n\
+             \x20 - { id: nps, label: Recommend?, field_type: Nps, required: false }\n",
+        );
+        let data = coerce_submission(&schema, &answers(&[("stars", "4"), ("nps", "0")])).unwrap();
+        assert_eq!((data["stars"].as_u64(), data["nps"].as_u64()), (Some(4), Some(0)));
+
+        let error = rejected(&schema, &[("stars", "5"), ("nps", "11")]);
+        assert_eq!(
+            error,
+            "Validation error: stars: must be a whole number from 1 to 4; nps: must be a whole number from 0 to 10"
+        );
+    }
+
+    #[test]
+    fn compacts_group_items_and_checks_them() {
+        let schema = schema(
+            "\x20 - id: dependents\n\
+             \x20   label: Dependents\n\
+             \x20   field_type: Group\n\
+             \x20   required: false\n\
+             \x20   max_items: 2\n\
+             \x20   fields:\n\
+             \x20     - { id: name, label: Name, field_type: Text, required: true }\n\
+             \x20     - { id: age, label: Age, field_type: Number, required: false, min: 0 }\n",
+        );
+        // Indexes only order the items; blank items are dropped
+        let data = coerce_submission(
+            &schema,
+            &answers(&[
+                ("dependents[0][name]", "Ann"),
+                ("dependents[0][age]", "7"),
+                ("dependents[3][name]", "Bob"),
+                ("dependents[5][name]", ""),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(data["dependents"], json!([{ "name": "Ann", "age": 7.0 }, { "name": "Bob" }]));
+
+        let error = rejected(&schema, &[("dependents[0][age]", "4"), ("dependents[1][name]", "Di"), ("dependents[1][age]", "-1")]);
+        assert_eq!(error, "Validation error: dependents[0][name]: is required; dependents[1][age]: must be at least 0");
+
+        let error = rejected(&schema, &[("dependents[0][name]", "A"), ("dependents[1][name]", "B"), ("dependents[2][name]", "C")]);
+        assert_eq!(error, "Validation error: dependents: add at most 2 items");
+    }
+}